@@ -0,0 +1,243 @@
+use super::captcha_solver::{CaptchaProvider, CaptchaWidget, DEFAULT_RECAPTCHA_V3_MIN_SCORE};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, info};
+
+const API_URL: &str = "https://api.anti-captcha.com";
+
+/// Response from anti-captcha.com `createTask`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateTaskResponse {
+    error_id: i32,
+    error_code: Option<String>,
+    error_description: Option<String>,
+    task_id: Option<u64>,
+}
+
+/// Response from anti-captcha.com `getTaskResult`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskResultResponse {
+    error_id: i32,
+    error_code: Option<String>,
+    error_description: Option<String>,
+    status: Option<String>,
+    solution: Option<TaskSolution>,
+}
+
+/// Response from anti-captcha.com `getBalance`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BalanceResponse {
+    error_id: i32,
+    error_code: Option<String>,
+    error_description: Option<String>,
+    balance: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskSolution {
+    g_recaptcha_response: String,
+}
+
+/// anti-captcha.com API client, used when `ANTICAPTCHA_API_KEY` is set
+pub struct AntiCaptcha {
+    api_key: String,
+    client: Client,
+}
+
+impl AntiCaptcha {
+    /// Create a new anti-captcha.com client with API key
+    pub fn new(api_key: String) -> Self {
+        Self {
+            api_key,
+            client: Client::new(),
+        }
+    }
+
+    /// Create from environment variable
+    pub fn from_env() -> Option<Self> {
+        std::env::var("ANTICAPTCHA_API_KEY").ok().map(Self::new)
+    }
+
+    /// `createTask` body for a captcha widget
+    fn task(widget: &CaptchaWidget, page_url: &str) -> Value {
+        match widget {
+            CaptchaWidget::RecaptchaV2 { site_key } => json!({
+                "type": "RecaptchaV2TaskProxyless",
+                "websiteURL": page_url,
+                "websiteKey": site_key,
+            }),
+            CaptchaWidget::RecaptchaV3 { site_key, action } => json!({
+                "type": "RecaptchaV3TaskProxyless",
+                "websiteURL": page_url,
+                "websiteKey": site_key,
+                "minScore": DEFAULT_RECAPTCHA_V3_MIN_SCORE,
+                "pageAction": action,
+            }),
+            CaptchaWidget::HCaptcha { site_key } => json!({
+                "type": "HCaptchaTaskProxyless",
+                "websiteURL": page_url,
+                "websiteKey": site_key,
+            }),
+        }
+    }
+
+    /// Create a task and poll until it is solved
+    async fn solve(&self, label: &str, task: Value) -> Result<String> {
+        debug!("Submitting captcha to anti-captcha.com...");
+        let created: CreateTaskResponse = self
+            .client
+            .post(format!("{}/createTask", API_URL))
+            .json(&json!({ "clientKey": self.api_key, "task": task }))
+            .send()
+            .await?
+            .json()
+            .await
+            .context("Failed to parse anti-captcha.com createTask response")?;
+
+        if created.error_id != 0 {
+            bail!(
+                "Failed to submit captcha: {} ({})",
+                created.error_code.unwrap_or_default(),
+                created.error_description.unwrap_or_default()
+            );
+        }
+        let task_id = created
+            .task_id
+            .context("anti-captcha.com returned no task ID")?;
+        info!("Captcha submitted, ID: {}", task_id);
+
+        // Poll for result (usually takes 10-30 seconds)
+        let max_attempts = 60; // 2 minutes max
+        let poll_interval = Duration::from_secs(2);
+
+        for attempt in 1..=max_attempts {
+            sleep(poll_interval).await;
+
+            let result: TaskResultResponse = self
+                .client
+                .post(format!("{}/getTaskResult", API_URL))
+                .json(&json!({ "clientKey": self.api_key, "taskId": task_id }))
+                .send()
+                .await?
+                .json()
+                .await
+                .context("Failed to parse anti-captcha.com getTaskResult response")?;
+
+            if result.error_id != 0 {
+                bail!(
+                    "anti-captcha.com error: {} ({})",
+                    result.error_code.unwrap_or_default(),
+                    result.error_description.unwrap_or_default()
+                );
+            }
+
+            match (result.status.as_deref(), result.solution) {
+                (Some("ready"), Some(solution)) => {
+                    info!(
+                        "✅ {} solved successfully! (attempt {}/{})",
+                        label, attempt, max_attempts
+                    );
+                    return Ok(solution.g_recaptcha_response);
+                }
+                _ => debug!(
+                    "Captcha not ready yet, waiting... (attempt {}/{})",
+                    attempt, max_attempts
+                ),
+            }
+        }
+
+        bail!("Timeout waiting for captcha solution")
+    }
+}
+
+#[async_trait]
+impl CaptchaProvider for AntiCaptcha {
+    fn name(&self) -> &'static str {
+        "anti-captcha.com"
+    }
+
+    async fn get_balance(&self) -> Result<f64> {
+        let response: BalanceResponse = self
+            .client
+            .post(format!("{}/getBalance", API_URL))
+            .json(&json!({ "clientKey": self.api_key }))
+            .send()
+            .await?
+            .json()
+            .await
+            .context("Failed to parse anti-captcha.com getBalance response")?;
+
+        if response.error_id != 0 {
+            bail!(
+                "anti-captcha.com error: {} ({})",
+                response.error_code.unwrap_or_default(),
+                response.error_description.unwrap_or_default()
+            );
+        }
+        response
+            .balance
+            .context("anti-captcha.com returned no balance")
+    }
+
+    async fn solve_recaptcha_v2(&self, site_key: &str, page_url: &str) -> Result<String> {
+        self.solve_widget(
+            &CaptchaWidget::RecaptchaV2 {
+                site_key: site_key.to_string(),
+            },
+            page_url,
+        )
+        .await
+    }
+
+    async fn solve_widget(&self, widget: &CaptchaWidget, page_url: &str) -> Result<String> {
+        info!("🤖 Solving {} using anti-captcha.com API...", widget.name());
+        self.solve(widget.name(), Self::task(widget, page_url))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_body() {
+        let task = AntiCaptcha::task(
+            &CaptchaWidget::RecaptchaV3 {
+                site_key: "6LdV3".to_string(),
+                action: "login".to_string(),
+            },
+            "https://app.dbase.com.br",
+        );
+        assert_eq!(task["type"], "RecaptchaV3TaskProxyless");
+        assert_eq!(task["websiteKey"], "6LdV3");
+        assert_eq!(task["pageAction"], "login");
+
+        let task = AntiCaptcha::task(
+            &CaptchaWidget::HCaptcha {
+                site_key: "10000000-ffff".to_string(),
+            },
+            "https://app.dbase.com.br",
+        );
+        assert_eq!(task["type"], "HCaptchaTaskProxyless");
+        assert_eq!(task["websiteURL"], "https://app.dbase.com.br");
+    }
+
+    #[test]
+    fn test_task_result_parsing() {
+        let result: TaskResultResponse = serde_json::from_str(
+            r#"{"errorId":0,"status":"ready","solution":{"gRecaptchaResponse":"token"}}"#,
+        )
+        .unwrap();
+        assert_eq!(result.solution.unwrap().g_recaptcha_response, "token");
+    }
+}
@@ -1,11 +1,16 @@
-use anyhow::{Context, Result};
+use super::anti_captcha::AntiCaptcha;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use scraper::{Html, Selector};
+use serde::Deserialize;
 use std::time::Duration;
+use thiserror::Error;
 use tokio::time::sleep;
 use tracing::{debug, info, warn};
 
 /// Response from 2Captcha API when submitting a captcha
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct CaptchaSubmitResponse {
     status: i32,
@@ -13,16 +18,122 @@ struct CaptchaSubmitResponse {
 }
 
 /// Response from 2Captcha API when checking captcha result
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct CaptchaResultResponse {
     status: i32,
     request: String,
 }
 
-/// 2Captcha API client for solving reCAPTCHA
+/// reCAPTCHA v3 action used when the page does not name one
+const DEFAULT_RECAPTCHA_V3_ACTION: &str = "verify";
+
+/// Lowest reCAPTCHA v3 score we ask 2Captcha for
+pub const DEFAULT_RECAPTCHA_V3_MIN_SCORE: f64 = 0.3;
+
+/// Captcha widget found on a page, with the site key needed to solve it
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptchaWidget {
+    RecaptchaV2 { site_key: String },
+    RecaptchaV3 { site_key: String, action: String },
+    HCaptcha { site_key: String },
+}
+
+impl CaptchaWidget {
+    pub fn site_key(&self) -> &str {
+        match self {
+            CaptchaWidget::RecaptchaV2 { site_key }
+            | CaptchaWidget::RecaptchaV3 { site_key, .. }
+            | CaptchaWidget::HCaptcha { site_key } => site_key,
+        }
+    }
+
+    /// Name for log messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            CaptchaWidget::RecaptchaV2 { .. } => "reCAPTCHA v2",
+            CaptchaWidget::RecaptchaV3 { .. } => "reCAPTCHA v3",
+            CaptchaWidget::HCaptcha { .. } => "hCaptcha",
+        }
+    }
+}
+
+/// Fresh captchas 2Captcha gets after the first one fails (`TWOCAPTCHA_MAX_RESUBMITS`)
+const DEFAULT_MAX_RESUBMITS: u32 = 2;
+
+/// Why 2Captcha did not return a solution
+#[derive(Debug, Error)]
+pub enum TwoCaptchaError {
+    /// The workers gave up on this captcha; a fresh submission may succeed
+    #[error("2Captcha could not solve the captcha ({0})")]
+    Unsolvable(String),
+    /// The request itself is wrong (key, balance, site key, ban); retrying won't help
+    #[error("2Captcha rejected the request ({0})")]
+    Rejected(String),
+    #[error("Timeout waiting for captcha solution after {secs}s")]
+    Timeout { secs: u64 },
+}
+
+impl TwoCaptchaError {
+    /// Classify an `in.php`/`res.php` error response
+    fn from_response(text: &str) -> Self {
+        let code = text.trim().to_string();
+        match code.as_str() {
+            "ERROR_CAPTCHA_UNSOLVABLE"
+            | "ERROR_BAD_DUPLICATES"
+            | "ERROR_NO_SLOT_AVAILABLE"
+            | "ERROR_WRONG_CAPTCHA_ID" => TwoCaptchaError::Unsolvable(code),
+            _ => TwoCaptchaError::Rejected(code),
+        }
+    }
+
+    /// Whether submitting a fresh captcha could succeed
+    pub fn should_resubmit(&self) -> bool {
+        !matches!(self, TwoCaptchaError::Rejected(_))
+    }
+}
+
+/// A captcha-solving service. `DbaseScraper` only talks to this trait, so the
+/// service is chosen by which API key is configured.
+#[async_trait]
+pub trait CaptchaProvider: Send + Sync {
+    /// Service name for log messages
+    fn name(&self) -> &'static str;
+
+    /// Solve reCAPTCHA v2
+    async fn solve_recaptcha_v2(&self, site_key: &str, page_url: &str) -> Result<String>;
+
+    /// Remaining account balance in USD
+    async fn get_balance(&self) -> Result<f64>;
+
+    /// Solve whichever captcha `widget` is. The default only handles reCAPTCHA v2.
+    async fn solve_widget(&self, widget: &CaptchaWidget, page_url: &str) -> Result<String> {
+        match widget {
+            CaptchaWidget::RecaptchaV2 { site_key } => {
+                self.solve_recaptcha_v2(site_key, page_url).await
+            }
+            other => bail!("{} cannot solve {}", self.name(), other.name()),
+        }
+    }
+}
+
+impl dyn CaptchaProvider {
+    /// Provider for whichever API key is set, preferring `TWOCAPTCHA_API_KEY`
+    /// over `ANTICAPTCHA_API_KEY`
+    pub fn from_env() -> Option<Box<dyn CaptchaProvider>> {
+        if let Some(solver) = CaptchaSolver::from_env() {
+            return Some(Box::new(solver));
+        }
+        AntiCaptcha::from_env().map(|solver| Box::new(solver) as Box<dyn CaptchaProvider>)
+    }
+}
+
+/// 2Captcha API client for solving reCAPTCHA and hCaptcha
 pub struct CaptchaSolver {
     api_key: String,
     client: Client,
+    /// Fresh captchas to submit after an unsolvable one or a polling timeout
+    max_resubmits: u32,
 }
 
 impl CaptchaSolver {
@@ -31,37 +142,115 @@ impl CaptchaSolver {
         Self {
             api_key,
             client: Client::new(),
+            max_resubmits: DEFAULT_MAX_RESUBMITS,
         }
     }
 
+    /// Set how many fresh captchas to submit when one cannot be solved
+    pub fn with_max_resubmits(mut self, max_resubmits: u32) -> Self {
+        self.max_resubmits = max_resubmits;
+        self
+    }
+
     /// Check if 2Captcha API key is configured
+    #[allow(dead_code)]
     pub fn is_available() -> bool {
         std::env::var("TWOCAPTCHA_API_KEY").is_ok()
     }
 
-    /// Create from environment variable
+    /// Create from environment variable (`TWOCAPTCHA_MAX_RESUBMITS` overrides
+    /// the default of 2 re-submissions)
     pub fn from_env() -> Option<Self> {
-        std::env::var("TWOCAPTCHA_API_KEY")
+        let solver = std::env::var("TWOCAPTCHA_API_KEY").ok().map(Self::new)?;
+        let max_resubmits = std::env::var("TWOCAPTCHA_MAX_RESUBMITS")
             .ok()
-            .map(|key| Self::new(key))
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(DEFAULT_MAX_RESUBMITS);
+        Some(solver.with_max_resubmits(max_resubmits))
     }
 
-    /// Solve reCAPTCHA v2
-    pub async fn solve_recaptcha_v2(&self, site_key: &str, page_url: &str) -> Result<String> {
-        info!("🤖 Solving reCAPTCHA using 2Captcha API...");
-
-        // Submit captcha
-        let submit_url = format!(
-            "https://2captcha.com/in.php?key={}&method=userrecaptcha&googlekey={}&pageurl={}",
-            self.api_key, site_key, page_url
+    /// Solve reCAPTCHA v3, asking for a token scored at least `min_score`
+    pub async fn solve_recaptcha_v3(
+        &self,
+        site_key: &str,
+        page_url: &str,
+        action: &str,
+        min_score: f64,
+    ) -> Result<String> {
+        info!(
+            "🤖 Solving reCAPTCHA v3 (action '{}') using 2Captcha API...",
+            action
         );
+        let min_score = min_score.to_string();
+        self.solve(
+            "reCAPTCHA v3",
+            &[
+                ("method", "userrecaptcha"),
+                ("version", "v3"),
+                ("googlekey", site_key),
+                ("pageurl", page_url),
+                ("action", action),
+                ("min_score", &min_score),
+            ],
+        )
+        .await
+    }
+
+    /// Solve hCaptcha
+    pub async fn solve_hcaptcha(&self, site_key: &str, page_url: &str) -> Result<String> {
+        info!("🤖 Solving hCaptcha using 2Captcha API...");
+        self.solve(
+            "hCaptcha",
+            &[
+                ("method", "hcaptcha"),
+                ("sitekey", site_key),
+                ("pageurl", page_url),
+            ],
+        )
+        .await
+    }
+
+    /// Submit a captcha and poll until solved, submitting a fresh one up to
+    /// `max_resubmits` times when 2Captcha gives up on it or polling times out
+    async fn solve(&self, label: &str, params: &[(&str, &str)]) -> Result<String> {
+        let submissions = self.max_resubmits + 1;
+        for submission in 1..=submissions {
+            let error = match self.submit_and_poll(label, params).await {
+                Ok(solution) => return Ok(solution),
+                Err(e) => e,
+            };
+            let resubmit = error
+                .downcast_ref::<TwoCaptchaError>()
+                .is_some_and(TwoCaptchaError::should_resubmit);
+            if !resubmit || submission == submissions {
+                return Err(error.context(format!(
+                    "{} not solved after {} submission(s)",
+                    label, submission
+                )));
+            }
+            warn!(
+                "{} (submission {}/{}), submitting a fresh captcha...",
+                error, submission, submissions
+            );
+        }
+        unreachable!("the last submission always returns")
+    }
 
+    /// Submit a captcha with the given `in.php` parameters and poll until solved
+    async fn submit_and_poll(&self, label: &str, params: &[(&str, &str)]) -> Result<String> {
+        // Submit captcha
         debug!("Submitting captcha to 2Captcha...");
-        let response = self.client.get(&submit_url).send().await?;
+        let response = self
+            .client
+            .get("https://2captcha.com/in.php")
+            .query(&[("key", self.api_key.as_str())])
+            .query(params)
+            .send()
+            .await?;
         let text = response.text().await?;
 
         if !text.starts_with("OK|") {
-            anyhow::bail!("Failed to submit captcha: {}", text);
+            return Err(TwoCaptchaError::from_response(&text).into());
         }
 
         let captcha_id = text.strip_prefix("OK|").unwrap();
@@ -85,8 +274,8 @@ impl CaptchaSolver {
             if text.starts_with("OK|") {
                 let solution = text.strip_prefix("OK|").unwrap();
                 info!(
-                    "✅ reCAPTCHA solved successfully! (attempt {}/{})",
-                    attempt, max_attempts
+                    "✅ {} solved successfully! (attempt {}/{})",
+                    label, attempt, max_attempts
                 );
                 return Ok(solution.to_string());
             } else if text == "CAPCHA_NOT_READY" {
@@ -94,17 +283,86 @@ impl CaptchaSolver {
                     "Captcha not ready yet, waiting... (attempt {}/{})",
                     attempt, max_attempts
                 );
+            } else if text.starts_with("ERROR_") {
+                return Err(TwoCaptchaError::from_response(&text).into());
             } else {
                 warn!("Unexpected response from 2Captcha: {}", text);
             }
         }
 
-        anyhow::bail!("Timeout waiting for captcha solution")
+        Err(TwoCaptchaError::Timeout {
+            secs: max_attempts * poll_interval.as_secs(),
+        }
+        .into())
+    }
+
+    /// Find the captcha widget on a page. hCaptcha is checked first, then
+    /// reCAPTCHA v3 (`grecaptcha.execute` or `api.js?render=<key>`), then v2.
+    pub fn detect_captcha(html: &str) -> Option<CaptchaWidget> {
+        let document = Html::parse_document(html);
+        let hcaptcha = Selector::parse(".h-captcha[data-sitekey]").unwrap();
+        if let Some(site_key) = document
+            .select(&hcaptcha)
+            .find_map(|elem| elem.value().attr("data-sitekey"))
+        {
+            return Some(CaptchaWidget::HCaptcha {
+                site_key: site_key.to_string(),
+            });
+        }
+
+        if let Some(site_key) = Self::recaptcha_v3_site_key(html) {
+            return Some(CaptchaWidget::RecaptchaV3 {
+                site_key,
+                action: Self::recaptcha_v3_action(html)
+                    .unwrap_or_else(|| DEFAULT_RECAPTCHA_V3_ACTION.to_string()),
+            });
+        }
+
+        Self::extract_site_key(html).map(|site_key| CaptchaWidget::RecaptchaV2 { site_key })
+    }
+
+    /// Site key of an invisible reCAPTCHA v3, which has no widget element
+    fn recaptcha_v3_site_key(html: &str) -> Option<String> {
+        // Pattern: grecaptcha.execute('SITE_KEY', ...)
+        if let Some(start) = html.find("grecaptcha.execute('") {
+            let start_idx = start + 20;
+            if let Some(end) = html[start_idx..].find('\'') {
+                return Some(html[start_idx..start_idx + end].to_string());
+            }
+        }
+
+        // Pattern: recaptcha/api.js?render=SITE_KEY (v2 uses render=explicit)
+        let start = html.find("recaptcha/api.js?render=")? + 24;
+        let end = html[start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(html.len() - start);
+        let site_key = &html[start..start + end];
+        (!site_key.is_empty() && site_key != "explicit").then(|| site_key.to_string())
+    }
+
+    /// Action passed to `grecaptcha.execute(key, {action: '...'})`
+    fn recaptcha_v3_action(html: &str) -> Option<String> {
+        let start = html.find("action:")? + 7;
+        let rest = html[start..].trim_start();
+        let quote = rest.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+        let end = rest[1..].find(quote)?;
+        Some(rest[1..1 + end].to_string())
     }
 
     /// Get site key from page HTML
     pub fn extract_site_key(html: &str) -> Option<String> {
-        // Look for reCAPTCHA site key in HTML
+        // Prefer the key on a reCAPTCHA or hCaptcha widget
+        let document = Html::parse_document(html);
+        let widget =
+            Selector::parse(".g-recaptcha[data-sitekey], .h-captcha[data-sitekey]").unwrap();
+        if let Some(site_key) = document
+            .select(&widget)
+            .find_map(|elem| elem.value().attr("data-sitekey"))
+        {
+            return Some(site_key.to_string());
+        }
+
+        // Look for any site key in HTML
         // Pattern: data-sitekey="XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX"
         if let Some(start) = html.find("data-sitekey=\"") {
             let start_idx = start + 14;
@@ -123,9 +381,16 @@ impl CaptchaSolver {
 
         None
     }
+}
+
+#[async_trait]
+impl CaptchaProvider for CaptchaSolver {
+    fn name(&self) -> &'static str {
+        "2Captcha"
+    }
 
     /// Check account balance
-    pub async fn get_balance(&self) -> Result<f64> {
+    async fn get_balance(&self) -> Result<f64> {
         let url = format!(
             "https://2captcha.com/res.php?key={}&action=getbalance",
             self.api_key
@@ -134,7 +399,37 @@ impl CaptchaSolver {
         let response = self.client.get(&url).send().await?;
         let text = response.text().await?;
 
-        text.parse::<f64>().context("Failed to parse balance")
+        text.trim()
+            .parse::<f64>()
+            .with_context(|| format!("Failed to parse balance: {}", text))
+    }
+
+    /// Solve reCAPTCHA v2
+    async fn solve_recaptcha_v2(&self, site_key: &str, page_url: &str) -> Result<String> {
+        info!("🤖 Solving reCAPTCHA using 2Captcha API...");
+        self.solve(
+            "reCAPTCHA",
+            &[
+                ("method", "userrecaptcha"),
+                ("googlekey", site_key),
+                ("pageurl", page_url),
+            ],
+        )
+        .await
+    }
+
+    /// Solve whichever captcha `widget` is
+    async fn solve_widget(&self, widget: &CaptchaWidget, page_url: &str) -> Result<String> {
+        match widget {
+            CaptchaWidget::RecaptchaV2 { site_key } => {
+                self.solve_recaptcha_v2(site_key, page_url).await
+            }
+            CaptchaWidget::RecaptchaV3 { site_key, action } => {
+                self.solve_recaptcha_v3(site_key, page_url, action, DEFAULT_RECAPTCHA_V3_MIN_SCORE)
+                    .await
+            }
+            CaptchaWidget::HCaptcha { site_key } => self.solve_hcaptcha(site_key, page_url).await,
+        }
     }
 }
 
@@ -156,6 +451,69 @@ mod tests {
         assert_eq!(site_key, Some("6LdAlternative123".to_string()));
     }
 
+    #[test]
+    fn test_extract_site_key_hcaptcha() {
+        let html = r#"<div data-sitekey="other"></div><div class="h-captcha" data-sitekey="10000000-ffff"></div>"#;
+        let site_key = CaptchaSolver::extract_site_key(html);
+        assert_eq!(site_key, Some("10000000-ffff".to_string()));
+    }
+
+    #[test]
+    fn test_detect_captcha() {
+        let html = r#"<div class="g-recaptcha" data-sitekey="6LdV2"></div>"#;
+        assert_eq!(
+            CaptchaSolver::detect_captcha(html),
+            Some(CaptchaWidget::RecaptchaV2 {
+                site_key: "6LdV2".to_string()
+            })
+        );
+
+        let html = r#"<div class="h-captcha" data-sitekey="10000000-ffff"></div>"#;
+        assert_eq!(
+            CaptchaSolver::detect_captcha(html),
+            Some(CaptchaWidget::HCaptcha {
+                site_key: "10000000-ffff".to_string()
+            })
+        );
+
+        let html = r#"<script src="https://www.google.com/recaptcha/api.js?render=6LdV3"></script>
+            <script>grecaptcha.execute('6LdV3', {action: 'login'})</script>"#;
+        assert_eq!(
+            CaptchaSolver::detect_captcha(html),
+            Some(CaptchaWidget::RecaptchaV3 {
+                site_key: "6LdV3".to_string(),
+                action: "login".to_string()
+            })
+        );
+
+        let html =
+            r#"<script src="https://www.google.com/recaptcha/api.js?render=6LdV3"></script>"#;
+        assert_eq!(
+            CaptchaSolver::detect_captcha(html),
+            Some(CaptchaWidget::RecaptchaV3 {
+                site_key: "6LdV3".to_string(),
+                action: "verify".to_string()
+            })
+        );
+
+        assert_eq!(CaptchaSolver::detect_captcha("<form></form>"), None);
+    }
+
+    #[test]
+    fn test_two_captcha_error_classification() {
+        let error = TwoCaptchaError::from_response("ERROR_CAPTCHA_UNSOLVABLE");
+        assert!(matches!(error, TwoCaptchaError::Unsolvable(_)));
+        assert!(error.should_resubmit());
+        assert!(TwoCaptchaError::Timeout { secs: 120 }.should_resubmit());
+
+        let error = TwoCaptchaError::from_response("ERROR_ZERO_BALANCE");
+        assert!(!error.should_resubmit());
+        assert_eq!(
+            error.to_string(),
+            "2Captcha rejected the request (ERROR_ZERO_BALANCE)"
+        );
+    }
+
     #[test]
     fn test_is_available() {
         // This will fail if env var is not set, which is expected in test env
@@ -1,19 +1,50 @@
+mod anti_captcha;
 mod captcha_solver;
-mod session_manager;
 
+use crate::browser::{start_session, Browser, BrowserCapabilities};
+use crate::export::{
+    address_row, exporter_for, with_schema_version, CsvExportOptions, ExportFormat, ADDRESS_COLUMNS,
+};
+use crate::scraper::ProxyConfig;
+use crate::session_manager::SessionManager;
 use anyhow::{bail, Context, Result};
-use captcha_solver::CaptchaSolver;
+use captcha_solver::{CaptchaProvider, CaptchaSolver, CaptchaWidget};
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
-use session_manager::SessionManager;
-use std::fs::File;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::SystemTime;
 use thirtyfour::prelude::*;
 use tokio::time::{sleep, Duration};
 use tracing::{debug, info, warn};
 
+/// Where the DBase login cookies are kept between runs
+const DBASE_SESSION_FILE: &str = "dbase_session.json";
+
+/// Captcha balance (USD) below which login warns; about 300 solves at 2Captcha prices
+const LOW_CAPTCHA_BALANCE: f64 = 1.0;
+
+/// Text DBase shows when an account is rate-limited or blocked (lowercase)
+const BLOCKED_PAGE_MARKERS: &[&str] = &[
+    "limite de consultas",
+    "limite diário",
+    "muitas requisições",
+    "muitas consultas",
+    "acesso bloqueado",
+    "usuário bloqueado",
+    "too many requests",
+];
+
+/// `active_credential` value while running on a restored session whose
+/// account is unknown
+const SAVED_SESSION: usize = usize::MAX;
+
+/// CEP search field, only present while logged in
+const CEP_INPUT_SELECTOR: &str = "input[name='e_cep']";
+
 /// Represents an address record from DBase
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct AddressRecord {
     /// CPF/CNPJ - Brazilian tax identification number
     pub cpf_cnpj: String,
@@ -36,44 +67,112 @@ pub struct DbaseScraper {
     driver: WebDriver,
     base_url: String,
     credentials: Vec<(String, String)>,
+    /// Index into `credentials` of the logged-in account, or `SAVED_SESSION`
+    active_credential: AtomicUsize,
+    /// Captcha service picked from the configured API key, if any
+    captcha_provider: Option<Box<dyn CaptchaProvider>>,
+    /// Refuse to log in when the captcha balance is below this (`--min-captcha-balance`)
+    min_captcha_balance: Option<f64>,
+    /// Generated proxy auth extension, removed on close
+    proxy_extension: Option<PathBuf>,
 }
 
 impl DbaseScraper {
-    /// Create a new DBase scraper with multiple credentials and WebDriver URL
+    /// Create a new DBase scraper with multiple credentials, WebDriver URL, browser
+    /// and optional proxy
     pub async fn new(
         credentials: Vec<(String, String)>,
         webdriver_url: &str,
         headless: bool,
+        browser: Browser,
+        proxy: Option<&ProxyConfig>,
     ) -> Result<Self> {
-        let mut caps = DesiredCapabilities::chrome();
-        if headless {
-            caps.add_chrome_arg("--headless")?;
-        }
-        caps.add_chrome_arg("--no-sandbox")?;
-        caps.add_chrome_arg("--disable-dev-shm-usage")?;
-        caps.add_chrome_arg("--disable-gpu")?;
-        caps.add_chrome_arg("--window-size=1920,1080")?;
+        let mut caps = browser.capabilities(headless)?;
 
         // Add user agent to appear more like a real browser
-        caps.add_chrome_arg("--user-agent=Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")?;
+        if let BrowserCapabilities::Chrome(caps) = &mut caps {
+            caps.add_chrome_arg("--user-agent=Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")?;
+        }
 
-        let driver = WebDriver::new(webdriver_url, caps)
-            .await
-            .context("Failed to connect to WebDriver")?;
+        let (driver, proxy_extension) = start_session(caps, webdriver_url, proxy).await?;
 
         Ok(Self {
             driver,
+            proxy_extension,
             base_url: "https://app.dbase.com.br".to_string(),
             credentials,
+            active_credential: AtomicUsize::new(SAVED_SESSION),
+            captcha_provider: <dyn CaptchaProvider>::from_env(),
+            min_captcha_balance: None,
         })
     }
 
+    /// Stop before logging in when the captcha account holds less than `min` USD
+    pub fn with_min_captcha_balance(mut self, min: Option<f64>) -> Self {
+        self.min_captcha_balance = min;
+        self
+    }
+
+    /// Log the captcha account balance before any captcha is submitted. Fails
+    /// when it is below `--min-captcha-balance`; otherwise a low balance only warns.
+    async fn check_captcha_balance(&self) -> Result<()> {
+        let Some(provider) = &self.captcha_provider else {
+            return Ok(());
+        };
+
+        let balance = match provider.get_balance().await {
+            Ok(balance) => balance,
+            Err(e) => {
+                warn!("Could not check {} balance: {:#}", provider.name(), e);
+                return Ok(());
+            }
+        };
+        info!("{} balance: ${:.2}", provider.name(), balance);
+
+        match self.min_captcha_balance {
+            Some(min) if balance < min => bail!(
+                "{} balance ${:.2} is below --min-captcha-balance ${:.2}; top up before scraping",
+                provider.name(),
+                balance,
+                min
+            ),
+            None if balance < LOW_CAPTCHA_BALANCE => warn!(
+                "⚠️  {} balance is low (${:.2}); captcha solving may fail partway through the run",
+                provider.name(),
+                balance
+            ),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Saved DBase cookies, checked against the CEP search form
+    fn session_manager(&self) -> SessionManager {
+        SessionManager::with_config(
+            DBASE_SESSION_FILE,
+            format!("{}/sistema/consultas/", self.base_url),
+            Some(CEP_INPUT_SELECTOR.to_string()),
+        )
+    }
+
+    /// The logged-in account, for log messages
+    fn active_credential_label(&self) -> String {
+        match self.active_credential.load(Ordering::Relaxed) {
+            SAVED_SESSION => "the saved session".to_string(),
+            idx => format!(
+                "credentials #{} (username: {})",
+                idx + 1,
+                self.credentials[idx].0
+            ),
+        }
+    }
+
     /// Login to DBase website with credential rotation and session persistence
     pub async fn login(&self) -> Result<()> {
         info!("Logging in to DBase...");
 
         // Try to load saved session first
-        let session_manager = SessionManager::new();
+        let session_manager = self.session_manager();
 
         if session_manager.load_session(&self.driver).await? {
             info!("Attempting to use saved session...");
@@ -87,6 +186,31 @@ impl DbaseScraper {
             }
         }
 
+        self.check_captcha_balance().await?;
+        self.login_with_credentials(0).await
+    }
+
+    /// Log in again with the account after the active one, after DBase
+    /// rate-limited or blocked it mid-scrape
+    async fn rotate_credentials(&self) -> Result<()> {
+        let next = match self.active_credential.load(Ordering::Relaxed) {
+            SAVED_SESSION => 0,
+            idx => (idx + 1) % self.credentials.len(),
+        };
+        info!(
+            "🔄 Switching from {} to credentials #{}...",
+            self.active_credential_label(),
+            next + 1
+        );
+
+        self.session_manager().clear_session()?;
+        self.driver.delete_all_cookies().await?;
+        self.login_with_credentials(next).await
+    }
+
+    /// Fill the login form with each credential in turn, starting at `start`,
+    /// until one works; the saved session is replaced with the new login
+    async fn login_with_credentials(&self, start: usize) -> Result<()> {
         // Navigate to login page
         self.driver.goto(&self.base_url).await?;
         sleep(Duration::from_secs(3)).await;
@@ -94,7 +218,9 @@ impl DbaseScraper {
         // Try each credential until one works
         let mut login_successful = false;
 
-        for (idx, (username, password)) in self.credentials.iter().enumerate() {
+        for offset in 0..self.credentials.len() {
+            let idx = (start + offset) % self.credentials.len();
+            let (username, password) = &self.credentials[idx];
             info!("Trying credentials #{} (username: {})", idx + 1, username);
 
             // Find and fill username field
@@ -129,59 +255,28 @@ impl DbaseScraper {
             password_field.send_keys(password).await?;
             debug!("Filled password field");
 
-            // Check if 2Captcha API is available
-            let captcha_solver_option = CaptchaSolver::from_env();
-
-            if let Some(solver) = &captcha_solver_option {
-                info!("🤖 2Captcha API detected, attempting automatic reCAPTCHA solving...");
+            // Check if a captcha service is configured
+            if let Some(solver) = &self.captcha_provider {
+                info!(
+                    "🤖 {} API detected, attempting automatic captcha solving...",
+                    solver.name()
+                );
 
                 // Get page HTML to extract site key
                 let html = self.driver.source().await?;
 
-                if let Some(site_key) = CaptchaSolver::extract_site_key(&html) {
-                    info!("Found reCAPTCHA site key: {}", site_key);
+                if let Some(widget) = CaptchaSolver::detect_captcha(&html) {
+                    info!("Found {} site key: {}", widget.name(), widget.site_key());
 
-                    match solver.solve_recaptcha_v2(&site_key, &self.base_url).await {
+                    match solver.solve_widget(&widget, &self.base_url).await {
                         Ok(solution) => {
-                            info!("✅ Got reCAPTCHA solution, injecting into page...");
-
-                            // Inject the solution into the g-recaptcha-response textarea
-                            let inject_script = format!(
-                                r#"
-                                // Set the hidden textarea value
-                                var textarea = document.getElementById('g-recaptcha-response');
-                                if (textarea) {{
-                                    textarea.innerHTML = '{}';
-                                    textarea.value = '{}';
-                                }}
-
-                                // Try to trigger the callback if it exists
-                                if (typeof ___grecaptcha_cfg !== 'undefined') {{
-                                    for (var id in ___grecaptcha_cfg.clients) {{
-                                        var client = ___grecaptcha_cfg.clients[id];
-                                        if (client && typeof client.callback === 'function') {{
-                                            try {{
-                                                client.callback('{}');
-                                            }} catch(e) {{
-                                                console.log('Callback error:', e);
-                                            }}
-                                        }}
-                                    }}
-                                }}
-
-                                // Alternative: trigger change event on textarea
-                                if (textarea) {{
-                                    var event = new Event('change', {{ bubbles: true }});
-                                    textarea.dispatchEvent(event);
-                                }}
-                                "#,
-                                solution, solution, solution
-                            );
+                            info!("✅ Got {} solution, injecting into page...", widget.name());
 
+                            let inject_script = captcha_injection_script(&widget, &solution);
                             self.driver.execute(&inject_script, vec![]).await?;
                             sleep(Duration::from_secs(2)).await;
 
-                            info!("✅ reCAPTCHA solution injected successfully!");
+                            info!("✅ {} solution injected successfully!", widget.name());
 
                             // Also try to enable the login button directly
                             let enable_button_script = r#"
@@ -196,21 +291,21 @@ impl DbaseScraper {
                             sleep(Duration::from_millis(500)).await;
                         }
                         Err(e) => {
-                            warn!("Failed to solve reCAPTCHA automatically: {}", e);
+                            warn!("Failed to solve {} automatically: {:#}", widget.name(), e);
                             info!("Falling back to manual reCAPTCHA completion...");
                         }
                     }
                 } else {
-                    debug!("No reCAPTCHA site key found in page");
+                    debug!("No captcha widget found in page");
                 }
             }
 
             // Wait for user to complete reCAPTCHA if present (or verify auto-solve worked)
-            if captcha_solver_option.is_none() {
+            if self.captcha_provider.is_none() {
                 info!("⚠️  If reCAPTCHA appears, please complete it manually...");
                 info!("   Waiting up to 5 minutes for reCAPTCHA completion...");
                 info!(
-                    "   💡 Tip: Set TWOCAPTCHA_API_KEY environment variable for automatic solving!"
+                    "   💡 Tip: Set TWOCAPTCHA_API_KEY or ANTICAPTCHA_API_KEY for automatic solving!"
                 );
             } else {
                 info!("   Verifying reCAPTCHA solution...");
@@ -221,7 +316,7 @@ impl DbaseScraper {
 
             // Poll for enabled button with generous timeout
             let mut captcha_completed = false;
-            let max_attempts = if captcha_solver_option.is_some() {
+            let max_attempts = if self.captcha_provider.is_some() {
                 12
             } else {
                 60
@@ -261,6 +356,7 @@ impl DbaseScraper {
                 .is_ok()
             {
                 info!("✅ Logged in successfully with credentials #{}", idx + 1);
+                self.active_credential.store(idx, Ordering::Relaxed);
                 login_successful = true;
                 break;
             } else {
@@ -280,7 +376,7 @@ impl DbaseScraper {
 
         // Save session for future use
         info!("Saving session cookies for future logins...");
-        if let Err(e) = session_manager.save_session(&self.driver).await {
+        if let Err(e) = self.session_manager().save_session(&self.driver).await {
             warn!("Failed to save session: {}", e);
         }
 
@@ -326,11 +422,21 @@ impl DbaseScraper {
         numero_inicio: u64,
         numero_fim: u64,
     ) -> Result<Vec<AddressRecord>> {
+        let cep = normalize_cep(cep).map_err(anyhow::Error::msg)?;
+        let cep = cep.as_str();
         info!(
             "Searching DBase for CEP: {} (range: {} - {})",
             cep, numero_inicio, numero_fim
         );
 
+        self.submit_search(cep, numero_inicio, numero_fim).await?;
+
+        // Extract data from all pages
+        self.extract_all_pages(cep, numero_inicio, numero_fim).await
+    }
+
+    /// Fill the CEP search form, submit it and wait for the results table
+    async fn submit_search(&self, cep: &str, numero_inicio: u64, numero_fim: u64) -> Result<()> {
         // Ensure we're on the search page
         self.ensure_on_cep_search_page().await?;
 
@@ -400,14 +506,21 @@ impl DbaseScraper {
             bail!("Timeout waiting for search results.");
         }
 
-        // Extract data from all pages
-        self.extract_all_pages().await
+        Ok(())
     }
 
-    /// Extract data from all paginated pages
-    async fn extract_all_pages(&self) -> Result<Vec<AddressRecord>> {
+    /// Extract data from all paginated pages. If DBase rate-limits or blocks
+    /// the account partway through, log in with the next credential, repeat
+    /// the search and carry on from the same page.
+    async fn extract_all_pages(
+        &self,
+        cep: &str,
+        numero_inicio: u64,
+        numero_fim: u64,
+    ) -> Result<Vec<AddressRecord>> {
         let mut all_records = Vec::new();
         let mut page_num = 1;
+        let mut rotations = 0;
         const MAX_PAGES: usize = 100;
 
         loop {
@@ -415,6 +528,32 @@ impl DbaseScraper {
 
             // Get current page HTML
             let html = self.driver.source().await?;
+
+            if is_blocked_page(&html) {
+                rotations += 1;
+                if rotations > self.credentials.len() {
+                    bail!(
+                        "DBase kept blocking the session on page {} after trying all {} credential(s)",
+                        page_num,
+                        self.credentials.len()
+                    );
+                }
+                warn!(
+                    "⚠️  DBase rate-limited or blocked {} on page {}",
+                    self.active_credential_label(),
+                    page_num
+                );
+                self.rotate_credentials().await?;
+                self.submit_search(cep, numero_inicio, numero_fim).await?;
+                self.skip_to_page(page_num).await?;
+                info!(
+                    "Resuming extraction on page {} with {}",
+                    page_num,
+                    self.active_credential_label()
+                );
+                continue;
+            }
+
             let page_records = extract_table_data(&html)?;
 
             if page_records.is_empty() {
@@ -452,6 +591,14 @@ impl DbaseScraper {
             sleep(Duration::from_millis(1500)).await;
         }
 
+        let (all_records, duplicates) = dedupe_records(all_records);
+        if duplicates > 0 {
+            info!(
+                "   Removed {} duplicate record(s) repeated across pages",
+                duplicates
+            );
+        }
+
         info!(
             "✅ Total extracted: {} records from {} pages",
             all_records.len(),
@@ -461,6 +608,21 @@ impl DbaseScraper {
         Ok(all_records)
     }
 
+    /// Click through the results from page 1 to `page_num`
+    async fn skip_to_page(&self, page_num: usize) -> Result<()> {
+        for page in 2..=page_num {
+            if !self.click_next_page().await? {
+                bail!(
+                    "Could not return to results page {} (stuck on page {})",
+                    page_num,
+                    page - 1
+                );
+            }
+            sleep(Duration::from_millis(1500)).await;
+        }
+        Ok(())
+    }
+
     /// Check if next page button exists
     async fn has_next_page(&self) -> Result<bool> {
         // Look for » (next page) button using JavaScript since CSS :has-text() isn't supported
@@ -525,11 +687,87 @@ impl DbaseScraper {
 
     /// Close the browser
     pub async fn close(self) -> Result<()> {
-        self.driver.quit().await?;
+        let quit = self.driver.quit().await;
+        if let Some(extension) = self.proxy_extension {
+            let _ = std::fs::remove_dir_all(extension);
+        }
+        quit?;
         Ok(())
     }
 }
 
+/// Script that writes a captcha solution into the page's response fields and
+/// fires the widget callback, so the login button unlocks
+fn captcha_injection_script(widget: &CaptchaWidget, solution: &str) -> String {
+    // JSON-quote the token so it is a valid JS string literal
+    let token = serde_json::to_string(solution).unwrap_or_default();
+    let callback = match widget {
+        CaptchaWidget::HCaptcha { .. } => {
+            r#"
+            // hCaptcha names its callback in data-callback
+            var widget = document.querySelector('.h-captcha[data-callback]');
+            if (widget && typeof window[widget.dataset.callback] === 'function') {
+                try {
+                    window[widget.dataset.callback](token);
+                } catch(e) {
+                    console.log('Callback error:', e);
+                }
+            }
+            "#
+        }
+        CaptchaWidget::RecaptchaV2 { .. } | CaptchaWidget::RecaptchaV3 { .. } => {
+            r#"
+            // Try to trigger the callback if it exists
+            if (typeof ___grecaptcha_cfg !== 'undefined') {
+                for (var id in ___grecaptcha_cfg.clients) {
+                    var client = ___grecaptcha_cfg.clients[id];
+                    if (client && typeof client.callback === 'function') {
+                        try {
+                            client.callback(token);
+                        } catch(e) {
+                            console.log('Callback error:', e);
+                        }
+                    }
+                }
+            }
+            "#
+        }
+    };
+
+    format!(
+        r##"
+        var token = {token};
+        // Set the hidden response fields (hCaptcha also fills g-recaptcha-response)
+        var fields = document.querySelectorAll(
+            "#g-recaptcha-response, [name='g-recaptcha-response'], [name='h-captcha-response']"
+        );
+        fields.forEach(function(field) {{
+            field.innerHTML = token;
+            field.value = token;
+            // Trigger change event so listeners notice the token
+            field.dispatchEvent(new Event('change', {{ bubbles: true }}));
+        }});
+        {callback}
+        "##
+    )
+}
+
+/// Whether DBase answered with a rate-limit/blocked notice or bounced the
+/// session back to the login form
+fn is_blocked_page(html: &str) -> bool {
+    let lower = html.to_lowercase();
+    if BLOCKED_PAGE_MARKERS
+        .iter()
+        .any(|marker| lower.contains(marker))
+    {
+        return true;
+    }
+
+    let document = Html::parse_document(html);
+    let login_form = Selector::parse("input[name='user'], input[name='pass']").unwrap();
+    document.select(&login_form).next().is_some()
+}
+
 /// Extract data from HTML table
 fn extract_table_data(html_content: &str) -> Result<Vec<AddressRecord>> {
     let mut records = Vec::new();
@@ -582,44 +820,60 @@ fn extract_table_data(html_content: &str) -> Result<Vec<AddressRecord>> {
     Ok(records)
 }
 
-/// Export records to CSV file
-pub fn export_to_csv(records: &[AddressRecord], filename: &str) -> Result<()> {
-    let file = File::create(filename)
-        .with_context(|| format!("Failed to create CSV file: {}", filename))?;
-
-    let mut wtr = csv::Writer::from_writer(file);
-
-    // Write header
-    wtr.write_record(&[
-        "cpf_cnpj",
-        "nome_razao_social",
-        "logradouro",
-        "numero",
-        "complemento",
-        "bairro",
-        "cep",
-    ])?;
-
-    // Write records
+/// Strip punctuation from a CEP and require exactly 8 digits, so `01455-040`
+/// and `01455040` both search as `01455040`
+pub fn normalize_cep(value: &str) -> std::result::Result<String, String> {
+    let digits: String = value.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.len() != 8 {
+        return Err(format!(
+            "invalid CEP '{}': expected 8 digits (e.g. 01455-040), got {}",
+            value.trim(),
+            digits.len()
+        ));
+    }
+    Ok(digits)
+}
+
+/// Drop records identical to an earlier one (DBase pagination sometimes repeats
+/// boundary rows), keeping first occurrences in order. Returns the number removed.
+fn dedupe_records(records: Vec<AddressRecord>) -> (Vec<AddressRecord>, usize) {
+    let total = records.len();
+    let mut seen = HashSet::new();
+    let unique: Vec<AddressRecord> = records
+        .into_iter()
+        .filter(|record| seen.insert(record.clone()))
+        .collect();
+    let duplicates = total - unique.len();
+    (unique, duplicates)
+}
+
+/// Export records to a file in the given format, optionally appending to an existing file.
+/// With `schema_version`, rows lead with the export schema version.
+pub fn export_records(
+    records: &[AddressRecord],
+    filename: &str,
+    format: ExportFormat,
+    append: bool,
+    csv: CsvExportOptions,
+    schema_version: bool,
+) -> Result<()> {
+    let mut exporter =
+        with_schema_version(exporter_for(format, filename, append, csv)?, schema_version);
+
+    exporter.write_header(&ADDRESS_COLUMNS)?;
+
     for record in records {
-        wtr.write_record(&[
-            &record.cpf_cnpj,
-            &record.nome_razao_social,
-            &record.logradouro,
-            &record.numero,
-            &record.complemento,
-            &record.bairro,
-            &record.cep,
-        ])?;
+        let row = address_row(record);
+        exporter.write_row(&row.iter().map(String::as_str).collect::<Vec<_>>())?;
     }
 
-    wtr.flush()?;
+    exporter.finish()?;
     info!("💾 Exported {} records to {}", records.len(), filename);
     Ok(())
 }
 
-/// Generate timestamped filename for CSV export
-pub fn generate_csv_filename() -> String {
+/// Generate timestamped filename for export in the given format
+pub fn generate_output_filename(format: ExportFormat) -> String {
     let now = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap();
@@ -628,13 +882,26 @@ pub fn generate_csv_filename() -> String {
         .unwrap()
         .format("%Y%m%d_%H%M%S");
 
-    format!("output/dbase_scraped_{}.csv", timestamp)
+    format!("output/dbase_scraped_{}.{}", timestamp, format.extension())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_is_blocked_page() {
+        assert!(is_blocked_page(
+            "<div class='alert'>Você atingiu o Limite de Consultas do dia</div>"
+        ));
+        assert!(is_blocked_page(
+            "<form><input name='user'><input name='pass' type='password'></form>"
+        ));
+        assert!(!is_blocked_page(
+            "<table><tr><td>123.456.789-00</td></tr></table><ul class='pagination'></ul>"
+        ));
+    }
+
     #[test]
     fn test_extract_table_data_empty() {
         let html = "<html><body></body></html>";
@@ -683,8 +950,114 @@ mod tests {
 
     #[test]
     fn test_generate_csv_filename() {
-        let filename = generate_csv_filename();
+        let filename = generate_output_filename(ExportFormat::Csv);
         assert!(filename.starts_with("output/dbase_scraped_"));
         assert!(filename.ends_with(".csv"));
     }
+
+    #[test]
+    fn test_generate_output_filename_uses_format_extension() {
+        assert!(generate_output_filename(ExportFormat::Jsonl).ends_with(".jsonl"));
+    }
+
+    #[test]
+    fn test_normalize_cep() {
+        assert_eq!(normalize_cep("01455-040").unwrap(), "01455040");
+        assert_eq!(normalize_cep(" 01455040 ").unwrap(), "01455040");
+        assert_eq!(normalize_cep("01.455-040").unwrap(), "01455040");
+
+        let err = normalize_cep("1455-040").unwrap_err();
+        assert!(err.contains("expected 8 digits"), "{}", err);
+        assert!(normalize_cep("014550400").is_err());
+        assert!(normalize_cep("").is_err());
+    }
+
+    #[test]
+    fn test_dedupe_records_keeps_first_occurrence() {
+        let record = |cpf: &str, numero: &str| AddressRecord {
+            cpf_cnpj: cpf.to_string(),
+            nome_razao_social: "NOME".to_string(),
+            logradouro: "RUA SERIDO".to_string(),
+            numero: numero.to_string(),
+            complemento: String::new(),
+            bairro: "JARDIM EUROPA".to_string(),
+            cep: "01455040".to_string(),
+        };
+        let records = vec![
+            record("1", "10"),
+            record("2", "20"),
+            record("1", "10"),
+            record("1", "11"),
+            record("2", "20"),
+        ];
+
+        let (unique, duplicates) = dedupe_records(records);
+        assert_eq!(duplicates, 2);
+        assert_eq!(
+            unique,
+            vec![record("1", "10"), record("2", "20"), record("1", "11")]
+        );
+    }
+
+    #[test]
+    fn test_json_exports_match_address_record_serialization() {
+        let records = vec![
+            AddressRecord {
+                cpf_cnpj: "607.661.908-20".to_string(),
+                nome_razao_social: "MARIO CELSO LOPES".to_string(),
+                logradouro: "AV HORACIO LAFER".to_string(),
+                numero: "120".to_string(),
+                complemento: "AP 12".to_string(),
+                bairro: "ITAIM BIBI".to_string(),
+                cep: "04538080".to_string(),
+            },
+            AddressRecord {
+                cpf_cnpj: "12.345.678/0001-90".to_string(),
+                nome_razao_social: "EMPRESA LTDA".to_string(),
+                logradouro: "RUA B".to_string(),
+                numero: "7".to_string(),
+                complemento: String::new(),
+                bairro: "PINHEIROS".to_string(),
+                cep: "05422000".to_string(),
+            },
+        ];
+        let expected = serde_json::to_value(&records).unwrap();
+        let dir = std::env::temp_dir();
+
+        let json_path = dir.join(format!("dbase_{}.json", uuid::Uuid::new_v4()));
+        let json_str = json_path.to_str().unwrap();
+        export_records(
+            &records,
+            json_str,
+            ExportFormat::Json,
+            false,
+            Default::default(),
+            false,
+        )
+        .unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(json, expected);
+
+        let jsonl_path = dir.join(format!("dbase_{}.jsonl", uuid::Uuid::new_v4()));
+        let jsonl_str = jsonl_path.to_str().unwrap();
+        export_records(
+            &records,
+            jsonl_str,
+            ExportFormat::Jsonl,
+            false,
+            Default::default(),
+            false,
+        )
+        .unwrap();
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&jsonl_path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(serde_json::Value::Array(lines), expected);
+
+        std::fs::remove_file(&json_path).ok();
+        std::fs::remove_file(&jsonl_path).ok();
+    }
 }
@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
@@ -11,10 +13,57 @@ use uuid::Uuid;
 
 const DEFAULT_TIMEOUT_SECS: u64 = 20;
 
+/// Name similarity a seed candidate needs unless `ENRICHMENT_NAME_THRESHOLD`
+/// says otherwise
+pub const DEFAULT_NAME_THRESHOLD: f64 = 0.5;
+
+/// Winkler's prefix bonus per shared leading character (up to four)
+const JARO_WINKLER_PREFIX_SCALE: f64 = 0.1;
+
+/// How a seed candidate's name is scored against the searched name
+/// (`ENRICHMENT_NAME_STRATEGY`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NameMatchStrategy {
+    /// Whole-token cosine similarity
+    #[default]
+    Cosine,
+    /// Character-level Jaro-Winkler, tolerant of typos and truncation
+    JaroWinkler,
+    /// The better of the two scores
+    Max,
+    /// Average of the two scores
+    Blend,
+}
+
+impl NameMatchStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NameMatchStrategy::Cosine => "cosine",
+            NameMatchStrategy::JaroWinkler => "jaro-winkler",
+            NameMatchStrategy::Max => "max",
+            NameMatchStrategy::Blend => "blend",
+        }
+    }
+
+    /// Similarity of two names in 0.0..=1.0
+    pub fn score(&self, left: &str, right: &str) -> f64 {
+        match self {
+            NameMatchStrategy::Cosine => cosine_similarity(left, right),
+            NameMatchStrategy::JaroWinkler => jaro_winkler(left, right),
+            NameMatchStrategy::Max => cosine_similarity(left, right).max(jaro_winkler(left, right)),
+            NameMatchStrategy::Blend => {
+                (cosine_similarity(left, right) + jaro_winkler(left, right)) / 2.0
+            }
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum EnrichmentError {
     #[error("Missing configuration: {0}")]
     MissingConfig(&'static str),
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
     #[error("Diretrix request failed with status {status}: {message}")]
     HttpFailure { status: StatusCode, message: String },
 }
@@ -22,19 +71,84 @@ pub enum EnrichmentError {
 #[derive(Clone, Debug)]
 pub struct DiretrixClient {
     http: reqwest::Client,
-    base_url: String,
+    /// Backends tried in order, starting from the active one
+    base_urls: Vec<String>,
+    active: Arc<AtomicUsize>,
     username: String,
     password: String,
+    /// Minimum name similarity a seed candidate needs
+    name_threshold: f64,
+    name_strategy: NameMatchStrategy,
+}
+
+/// Backends from `DIRETRIX_BASE_URLS` (comma-separated), falling back to the
+/// single `DIRETRIX_BASE_URL`
+fn parse_base_urls(multiple: Option<&str>, single: Option<&str>) -> Vec<String> {
+    let split = |value: &str| {
+        value
+            .split(',')
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .collect::<Vec<_>>()
+    };
+
+    match multiple.map(split) {
+        Some(urls) if !urls.is_empty() => urls,
+        _ => single.map(split).unwrap_or_default(),
+    }
+}
+
+/// `ENRICHMENT_NAME_THRESHOLD`, defaulting to `DEFAULT_NAME_THRESHOLD`; must be
+/// a number within 0.0..=1.0
+fn parse_name_threshold(value: Option<&str>) -> Result<f64> {
+    let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(DEFAULT_NAME_THRESHOLD);
+    };
+    match value.parse::<f64>() {
+        Ok(threshold) if (0.0..=1.0).contains(&threshold) => Ok(threshold),
+        _ => Err(EnrichmentError::InvalidConfig(format!(
+            "ENRICHMENT_NAME_THRESHOLD must be a number between 0.0 and 1.0, got '{}'",
+            value
+        ))
+        .into()),
+    }
+}
+
+/// `ENRICHMENT_NAME_STRATEGY`, defaulting to cosine similarity
+fn parse_name_strategy(value: Option<&str>) -> Result<NameMatchStrategy> {
+    let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(NameMatchStrategy::default());
+    };
+    match value.to_ascii_lowercase().replace('_', "-").as_str() {
+        "cosine" => Ok(NameMatchStrategy::Cosine),
+        "jaro-winkler" | "jarowinkler" => Ok(NameMatchStrategy::JaroWinkler),
+        "max" => Ok(NameMatchStrategy::Max),
+        "blend" => Ok(NameMatchStrategy::Blend),
+        _ => Err(EnrichmentError::InvalidConfig(format!(
+            "ENRICHMENT_NAME_STRATEGY must be one of cosine, jaro-winkler, max, blend; got '{}'",
+            value
+        ))
+        .into()),
+    }
 }
 
 impl DiretrixClient {
     pub fn from_env() -> Result<Self> {
-        let base_url = std::env::var("DIRETRIX_BASE_URL")
-            .map_err(|_| EnrichmentError::MissingConfig("DIRETRIX_BASE_URL"))?;
+        let base_urls = parse_base_urls(
+            std::env::var("DIRETRIX_BASE_URLS").ok().as_deref(),
+            std::env::var("DIRETRIX_BASE_URL").ok().as_deref(),
+        );
+        if base_urls.is_empty() {
+            return Err(EnrichmentError::MissingConfig("DIRETRIX_BASE_URL").into());
+        }
         let username = std::env::var("DIRETRIX_USER")
             .map_err(|_| EnrichmentError::MissingConfig("DIRETRIX_USER"))?;
         let password = std::env::var("DIRETRIX_PASS")
             .map_err(|_| EnrichmentError::MissingConfig("DIRETRIX_PASS"))?;
+        let name_threshold =
+            parse_name_threshold(std::env::var("ENRICHMENT_NAME_THRESHOLD").ok().as_deref())?;
+        let name_strategy =
+            parse_name_strategy(std::env::var("ENRICHMENT_NAME_STRATEGY").ok().as_deref())?;
 
         let http = reqwest::Client::builder()
             .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
@@ -45,12 +159,49 @@ impl DiretrixClient {
 
         Ok(Self {
             http,
-            base_url,
+            base_urls,
+            active: Arc::new(AtomicUsize::new(0)),
             username,
             password,
+            name_threshold,
+            name_strategy,
         })
     }
 
+    /// Minimum name similarity a seed candidate needs
+    pub fn name_threshold(&self) -> f64 {
+        self.name_threshold
+    }
+
+    /// How seed candidate names are scored
+    pub fn name_strategy(&self) -> NameMatchStrategy {
+        self.name_strategy
+    }
+
+    /// Client pointed at an unroutable backend, for tests that never reach it
+    #[cfg(test)]
+    #[allow(dead_code)]
+    pub(crate) fn for_tests() -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_urls: vec!["http://127.0.0.1:9".to_string()],
+            active: Arc::new(AtomicUsize::new(0)),
+            username: String::new(),
+            password: String::new(),
+            name_threshold: DEFAULT_NAME_THRESHOLD,
+            name_strategy: NameMatchStrategy::default(),
+        }
+    }
+
+    /// Backend the next request will try first
+    pub fn active_base_url(&self) -> &str {
+        &self.base_urls[self.active.load(Ordering::Relaxed) % self.base_urls.len()]
+    }
+
+    pub fn base_urls(&self) -> &[String] {
+        &self.base_urls
+    }
+
     fn auth_request(&self, url: String) -> reqwest::RequestBuilder {
         self.http
             .get(url)
@@ -58,17 +209,60 @@ impl DiretrixClient {
             .header(reqwest::header::ACCEPT, "application/json")
     }
 
+    /// GET `path` on the active backend, failing over to the next ones on
+    /// connection errors, timeouts and 5xx responses. The backend that answers
+    /// becomes the active one for subsequent requests.
+    async fn get_with_failover(&self, path: &str) -> Result<reqwest::Response> {
+        let count = self.base_urls.len();
+        let start = self.active.load(Ordering::Relaxed) % count;
+        let mut last_error = None;
+
+        for offset in 0..count {
+            let idx = (start + offset) % count;
+            let url = format!("{}/{}", self.base_urls[idx], path);
+
+            match self.auth_request(url).send().await {
+                Ok(resp) if resp.status().is_server_error() => {
+                    let status = resp.status();
+                    let message = resp.text().await.unwrap_or_default();
+                    tracing::warn!(
+                        "Diretrix backend {} returned {}, trying next backend",
+                        self.base_urls[idx],
+                        status
+                    );
+                    last_error = Some(EnrichmentError::HttpFailure { status, message }.into());
+                }
+                Ok(resp) => {
+                    if idx != start {
+                        tracing::info!("Diretrix failover: now using {}", self.base_urls[idx]);
+                        self.active.store(idx, Ordering::Relaxed);
+                    }
+                    return Ok(resp);
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "Diretrix backend {} unreachable ({}), trying next backend",
+                        self.base_urls[idx],
+                        err
+                    );
+                    last_error = Some(anyhow::Error::from(err));
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No Diretrix backends configured")))
+    }
+
     pub async fn pessoa_por_cpf(&self, cpf: &str) -> Result<Option<DiretrixPerson>> {
         if cpf.trim().is_empty() {
             return Ok(None);
         }
 
-        let url = format!("{}/pessoas/{cpf}", self.base_url.trim_end_matches('/'));
         let resp = self
-            .auth_request(url)
-            .send()
+            .get_with_failover(&format!("pessoas/{cpf}"))
             .await
             .context("Failed to execute CPF lookup")?;
+        tracing::debug!(status = %resp.status(), "Diretrix CPF lookup");
 
         if resp.status() == StatusCode::NOT_FOUND {
             return Ok(None);
@@ -97,17 +291,14 @@ impl DiretrixClient {
             return Ok(None);
         }
 
-        let url = format!(
-            "{}/{path}?{key}={}",
-            self.base_url.trim_end_matches('/'),
-            urlencoding::encode(value.trim())
-        );
-
         let resp = self
-            .auth_request(url)
-            .send()
+            .get_with_failover(&format!(
+                "{path}?{key}={}",
+                urlencoding::encode(value.trim())
+            ))
             .await
             .context("Failed to execute seed query")?;
+        tracing::debug!(seed = key, status = %resp.status(), "Diretrix seed lookup");
 
         if resp.status() == StatusCode::NOT_FOUND {
             return Ok(None);
@@ -211,6 +402,49 @@ pub struct GetCustomerData {
     pub emails: Vec<CustomerEmail>,
     pub phones: Vec<CustomerPhone>,
     pub addresses: Vec<CustomerAddress>,
+    /// Name similarity (0.0 to 1.0) of the candidate picked from a seed search;
+    /// 1.0 for a direct document lookup, `None` when no name was compared or the
+    /// backend gives no score
+    #[serde(default)]
+    pub match_score: Option<f64>,
+    /// Which search found this person
+    #[serde(default)]
+    pub match_method: Option<MatchMethod>,
+}
+
+/// How an enrichment result was matched to the person being looked up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMethod {
+    Cpf,
+    Cnpj,
+    Email,
+    Phone,
+    Name,
+}
+
+impl MatchMethod {
+    /// The method behind an enrichment `search_type` (`cpf`, `name`, ...)
+    pub fn from_search_type(search_type: &str) -> Option<Self> {
+        match search_type.to_lowercase().as_str() {
+            "cpf" => Some(MatchMethod::Cpf),
+            "cnpj" => Some(MatchMethod::Cnpj),
+            "email" => Some(MatchMethod::Email),
+            "phone" | "telefone" => Some(MatchMethod::Phone),
+            "name" | "nome" => Some(MatchMethod::Name),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchMethod::Cpf => "cpf",
+            MatchMethod::Cnpj => "cnpj",
+            MatchMethod::Email => "email",
+            MatchMethod::Phone => "phone",
+            MatchMethod::Name => "name",
+        }
+    }
 }
 
 // Workbuscas API response structure
@@ -226,6 +460,77 @@ pub struct WorkbuscasResponse {
     pub enderecos: Option<Vec<WorkbuscasAddress>>,
 }
 
+impl WorkbuscasResponse {
+    /// No recognised section at all, as when the payload sits in an envelope
+    fn is_empty(&self) -> bool {
+        self.dados_basicos.is_none()
+            && self.emails.is_none()
+            && self.telefones.is_none()
+            && self.enderecos.is_none()
+    }
+}
+
+/// `{ "status": ..., "data": {...} }` wrapper some Workbuscas endpoints use;
+/// the payload may also sit under `result`. A key that is present but `null`
+/// deserializes as `Some(Value::Null)`, meaning "no match".
+#[derive(Debug, Deserialize)]
+pub struct WorkbuscasEnvelope {
+    #[serde(default, deserialize_with = "present_value")]
+    pub data: Option<serde_json::Value>,
+    #[serde(default, deserialize_with = "present_value")]
+    pub result: Option<serde_json::Value>,
+}
+
+fn present_value<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<serde_json::Value>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    serde_json::Value::deserialize(deserializer).map(Some)
+}
+
+impl WorkbuscasEnvelope {
+    fn into_payload(self) -> Option<serde_json::Value> {
+        self.data.or(self.result).filter(|value| !value.is_null())
+    }
+}
+
+/// Parse a Workbuscas body that is a single response, an array of responses
+/// (first one wins) or either of those inside a `WorkbuscasEnvelope`
+pub fn parse_workbuscas_body(body: &str) -> serde_json::Result<Option<GetCustomerData>> {
+    let primary = serde_json::from_str::<WorkbuscasResponse>(body);
+    if let Ok(data) = &primary {
+        if !data.is_empty() {
+            return primary.map(|data| Some(data.into()));
+        }
+    }
+
+    if let Ok(as_array) = serde_json::from_str::<Vec<WorkbuscasResponse>>(body) {
+        return Ok(as_array.into_iter().next().map(Into::into));
+    }
+
+    if let Ok(envelope) = serde_json::from_str::<WorkbuscasEnvelope>(body) {
+        let has_payload_key = envelope.data.is_some() || envelope.result.is_some();
+        match envelope.into_payload() {
+            Some(serde_json::Value::Array(items)) => {
+                let responses = serde_json::from_value::<Vec<WorkbuscasResponse>>(
+                    serde_json::Value::Array(items),
+                )?;
+                return Ok(responses.into_iter().next().map(Into::into));
+            }
+            Some(payload) => {
+                return serde_json::from_value::<WorkbuscasResponse>(payload)
+                    .map(|data| Some(data.into()));
+            }
+            None if has_payload_key => return Ok(None),
+            None => {}
+        }
+    }
+
+    primary.map(|data| Some(data.into()))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct WorkbuscasBasicos {
     pub nome: Option<String>,
@@ -242,6 +547,8 @@ pub struct WorkbuscasBasicos {
 #[derive(Debug, Deserialize)]
 pub struct WorkbuscasEmail {
     pub email: Option<String>,
+    #[serde(default, deserialize_with = "lenient_ranking")]
+    pub ranking: Option<i32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -250,6 +557,8 @@ pub struct WorkbuscasPhone {
     pub numero: Option<String>,
     pub operadora: Option<String>,
     pub tipo: Option<String>,
+    #[serde(default, deserialize_with = "lenient_ranking")]
+    pub ranking: Option<i32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -260,6 +569,41 @@ pub struct WorkbuscasAddress {
     pub cidade: Option<String>,
     pub uf: Option<String>,
     pub cep: Option<String>,
+    pub complemento: Option<String>,
+    #[serde(default, deserialize_with = "lenient_ranking")]
+    pub ranking: Option<i32>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub latitude: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub longitude: Option<String>,
+    pub ddd: Option<String>,
+    #[serde(rename = "tipoLogradouro")]
+    pub tipo_logradouro: Option<String>,
+}
+
+/// Workbuscas sends rankings as numbers or numeric strings; anything else is
+/// treated as absent rather than failing the whole payload
+fn lenient_ranking<'de, D>(deserializer: D) -> std::result::Result<Option<i32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Number(n) => n.as_i64().and_then(|n| i32::try_from(n).ok()),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+/// Coordinates arrive as strings or numbers; keep them as text like Diretrix
+fn lenient_string<'de, D>(deserializer: D) -> std::result::Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) if !s.trim().is_empty() => Some(s),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    })
 }
 
 impl From<WorkbuscasResponse> for GetCustomerData {
@@ -295,7 +639,7 @@ impl From<WorkbuscasResponse> for GetCustomerData {
             .filter_map(|e| {
                 e.email.map(|email| CustomerEmail {
                     email,
-                    ranking: None,
+                    ranking: e.ranking,
                 })
             })
             .collect();
@@ -309,7 +653,7 @@ impl From<WorkbuscasResponse> for GetCustomerData {
                 number: p.numero,
                 operator_: p.operadora,
                 kind: p.tipo,
-                ranking: None,
+                ranking: p.ranking,
             })
             .collect();
 
@@ -324,12 +668,12 @@ impl From<WorkbuscasResponse> for GetCustomerData {
                 city: a.cidade,
                 uf: a.uf,
                 postal_code: a.cep,
-                complement: None,
-                ranking: None,
-                latitude: None,
-                longitude: None,
-                ddd: None,
-                street_type: None,
+                complement: a.complemento,
+                ranking: a.ranking,
+                latitude: a.latitude,
+                longitude: a.longitude,
+                ddd: a.ddd,
+                street_type: a.tipo_logradouro,
             })
             .collect();
 
@@ -338,6 +682,8 @@ impl From<WorkbuscasResponse> for GetCustomerData {
             emails,
             phones,
             addresses,
+            match_score: None,
+            match_method: None,
         }
     }
 }
@@ -404,11 +750,16 @@ pub async fn enrich_person(
         .filter(|s| !s.is_empty())
     {
         if let Some(person) = client.pessoa_por_cpf(cpf).await? {
-            return Ok(Some(map_person(person)));
+            return Ok(Some(matched(
+                map_person(person),
+                Some(1.0),
+                MatchMethod::Cpf,
+            )));
         }
     }
 
-    let mut candidate: Option<(Option<String>, f64)> = None;
+    // Best seed match so far: (cpf, name similarity if a name was compared, seed that found it)
+    let mut candidate: Option<(Option<String>, Option<f64>, MatchMethod)> = None;
 
     if let Some(email) = request
         .email
@@ -417,10 +768,18 @@ pub async fn enrich_person(
         .filter(|s| !s.is_empty())
     {
         if let Some(seed_value) = client.seed_by(SeedQuery::Email(email)).await? {
-            if let Some((cpf, score)) = extract_best_candidate(seed_value, request.name.as_deref())
-            {
-                if candidate.as_ref().map(|(_, s)| score > *s).unwrap_or(true) {
-                    candidate = Some((cpf, score));
+            if let Some((cpf, score)) = extract_best_candidate(
+                seed_value,
+                request.name.as_deref(),
+                client.name_threshold,
+                client.name_strategy,
+            ) {
+                if candidate
+                    .as_ref()
+                    .map(|(_, s, _)| score.unwrap_or(0.0) > s.unwrap_or(0.0))
+                    .unwrap_or(true)
+                {
+                    candidate = Some((cpf, score, MatchMethod::Email));
                 }
             }
         }
@@ -428,7 +787,7 @@ pub async fn enrich_person(
 
     if candidate
         .as_ref()
-        .and_then(|(cpf, _)| cpf.clone())
+        .and_then(|(cpf, _, _)| cpf.clone())
         .is_none()
     {
         if let Some(phone) = request
@@ -438,11 +797,18 @@ pub async fn enrich_person(
             .filter(|s| !s.is_empty())
         {
             if let Some(seed_value) = client.seed_by(SeedQuery::Telefone(phone)).await? {
-                if let Some((cpf, score)) =
-                    extract_best_candidate(seed_value, request.name.as_deref())
-                {
-                    if candidate.as_ref().map(|(_, s)| score > *s).unwrap_or(true) {
-                        candidate = Some((cpf, score));
+                if let Some((cpf, score)) = extract_best_candidate(
+                    seed_value,
+                    request.name.as_deref(),
+                    client.name_threshold,
+                    client.name_strategy,
+                ) {
+                    if candidate
+                        .as_ref()
+                        .map(|(_, s, _)| score.unwrap_or(0.0) > s.unwrap_or(0.0))
+                        .unwrap_or(true)
+                    {
+                        candidate = Some((cpf, score, MatchMethod::Phone));
                     }
                 }
             }
@@ -451,7 +817,7 @@ pub async fn enrich_person(
 
     if candidate
         .as_ref()
-        .and_then(|(cpf, _)| cpf.clone())
+        .and_then(|(cpf, _, _)| cpf.clone())
         .is_none()
     {
         if let Some(name) = request
@@ -461,34 +827,60 @@ pub async fn enrich_person(
             .filter(|s| !s.is_empty())
         {
             if let Some(seed_value) = client.seed_by(SeedQuery::Nome(name)).await? {
-                if let Some((cpf, score)) = extract_best_candidate(seed_value, Some(name)) {
-                    if candidate.as_ref().map(|(_, s)| score > *s).unwrap_or(true) {
-                        candidate = Some((cpf, score));
+                if let Some((cpf, score)) = extract_best_candidate(
+                    seed_value,
+                    Some(name),
+                    client.name_threshold,
+                    client.name_strategy,
+                ) {
+                    if candidate
+                        .as_ref()
+                        .map(|(_, s, _)| score.unwrap_or(0.0) > s.unwrap_or(0.0))
+                        .unwrap_or(true)
+                    {
+                        candidate = Some((cpf, score, MatchMethod::Name));
                     }
                 }
             }
         }
     }
 
-    let cpf = match candidate.and_then(|(cpf, _)| cpf) {
-        Some(cpf) => cpf,
-        None => return Ok(None),
+    let (cpf, score, method) = match candidate {
+        Some((Some(cpf), score, method)) => (cpf, score, method),
+        _ => return Ok(None),
     };
 
     if let Some(person) = client.pessoa_por_cpf(&cpf).await? {
-        return Ok(Some(map_person(person)));
+        return Ok(Some(matched(map_person(person), score, method)));
     }
 
     Ok(None)
 }
 
+/// Record how `data` was found; `score` is `None` when no name was compared
+fn matched(mut data: GetCustomerData, score: Option<f64>, method: MatchMethod) -> GetCustomerData {
+    data.match_score = score;
+    data.match_method = Some(method);
+    data
+}
+
+/// Seed candidate whose name best matches `reference_name`, skipping those
+/// scoring below `threshold` under `strategy`. The score is `None` when there
+/// was no name to compare.
 fn extract_best_candidate(
     value: serde_json::Value,
     reference_name: Option<&str>,
-) -> Option<(Option<String>, f64)> {
+    threshold: f64,
+    strategy: NameMatchStrategy,
+) -> Option<(Option<String>, Option<f64>)> {
+    let score = |candidate_name: Option<&str>| {
+        reference_name
+            .zip(candidate_name)
+            .map(|(reference, candidate)| strategy.score(reference, candidate))
+    };
     match value {
         serde_json::Value::Array(items) => {
-            let mut best: Option<(Option<String>, f64)> = None;
+            let mut best: Option<(Option<String>, Option<f64>)> = None;
             for item in items {
                 let candidate_cpf = item
                     .get("cpf")
@@ -496,18 +888,17 @@ fn extract_best_candidate(
                     .map(|s| s.to_string());
                 let candidate_name = item.get("nome").and_then(|v| v.as_str());
 
-                let score =
-                    if let (Some(reference), Some(candidate)) = (reference_name, candidate_name) {
-                        cosine_similarity(reference, candidate)
-                    } else {
-                        0.0
-                    };
+                let score = score(candidate_name);
 
-                if reference_name.is_some() && score < 0.5 {
+                if reference_name.is_some() && score.unwrap_or(0.0) < threshold {
                     continue;
                 }
 
-                if best.as_ref().map(|(_, s)| score > *s).unwrap_or(true) {
+                if best
+                    .as_ref()
+                    .map(|(_, s)| score.unwrap_or(0.0) > s.unwrap_or(0.0))
+                    .unwrap_or(true)
+                {
                     best = Some((candidate_cpf.clone(), score));
                 }
             }
@@ -519,7 +910,8 @@ fn extract_best_candidate(
                 .get("cpf")
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string());
-            Some((cpf, 1.0))
+            let candidate_name = obj.get("nome").and_then(|v| v.as_str());
+            Some((cpf, score(candidate_name)))
         }
         _ => None,
     }
@@ -576,10 +968,13 @@ fn map_person(person: DiretrixPerson) -> GetCustomerData {
                 street_type: a.street_type,
             })
             .collect(),
+        match_score: None,
+        match_method: None,
     }
 }
 
-fn normalize(value: &str) -> Option<String> {
+/// Accent-stripped, lowercased text with whitespace collapsed; `None` when blank
+pub fn normalize(value: &str) -> Option<String> {
     let trimmed = value.trim();
     if trimmed.is_empty() {
         return None;
@@ -621,6 +1016,31 @@ pub fn date_br_to_iso(value: &str) -> Option<String> {
     None
 }
 
+/// Portuguese name connectors that carry no identity ("Jose *da* Silva")
+const NAME_STOPWORDS: &[&str] = &["d", "da", "das", "de", "do", "dos", "e"];
+
+/// Given names common enough in Brazil that sharing one says little
+const COMMON_GIVEN_NAMES: &[&str] = &[
+    "ana",
+    "antonia",
+    "antonio",
+    "carlos",
+    "francisca",
+    "francisco",
+    "joao",
+    "jose",
+    "luiz",
+    "manoel",
+    "marcos",
+    "maria",
+    "paulo",
+    "pedro",
+    "raimundo",
+];
+
+/// Weight of a `COMMON_GIVEN_NAMES` token relative to any other token
+const COMMON_GIVEN_NAME_WEIGHT: f64 = 0.5;
+
 fn cosine_similarity(left: &str, right: &str) -> f64 {
     let left_norm = match normalize(left) {
         Some(value) => value,
@@ -637,20 +1057,12 @@ fn cosine_similarity(left: &str, right: &str) -> f64 {
     let mut dot = 0.0;
     for (token, freq) in &left_vector {
         if let Some(freq_right) = right_vector.get(token) {
-            dot += (*freq as f64) * (*freq_right as f64);
+            dot += freq * freq_right;
         }
     }
 
-    let left_norm = left_vector
-        .values()
-        .map(|v| (*v as f64).powi(2))
-        .sum::<f64>()
-        .sqrt();
-    let right_norm = right_vector
-        .values()
-        .map(|v| (*v as f64).powi(2))
-        .sum::<f64>()
-        .sqrt();
+    let left_norm = left_vector.values().map(|v| v.powi(2)).sum::<f64>().sqrt();
+    let right_norm = right_vector.values().map(|v| v.powi(2)).sum::<f64>().sqrt();
 
     if left_norm == 0.0 || right_norm == 0.0 {
         0.0
@@ -659,10 +1071,76 @@ fn cosine_similarity(left: &str, right: &str) -> f64 {
     }
 }
 
-fn token_frequency(input: &str) -> HashMap<String, usize> {
+/// Jaro-Winkler similarity of two normalized names, in 0.0..=1.0
+pub fn jaro_winkler(left: &str, right: &str) -> f64 {
+    let (Some(left), Some(right)) = (normalize(left), normalize(right)) else {
+        return 0.0;
+    };
+    let left: Vec<char> = left.chars().collect();
+    let right: Vec<char> = right.chars().collect();
+    if left == right {
+        return 1.0;
+    }
+
+    let window = (left.len().max(right.len()) / 2).saturating_sub(1);
+    let mut left_matched = vec![false; left.len()];
+    let mut right_matched = vec![false; right.len()];
+    let mut matches = 0usize;
+    for (i, c) in left.iter().enumerate() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(right.len());
+        for j in start..end {
+            if !right_matched[j] && right[j] == *c {
+                left_matched[i] = true;
+                right_matched[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+    if matches == 0 {
+        return 0.0;
+    }
+
+    let left_seq = left
+        .iter()
+        .zip(&left_matched)
+        .filter(|(_, matched)| **matched)
+        .map(|(c, _)| c);
+    let right_seq = right
+        .iter()
+        .zip(&right_matched)
+        .filter(|(_, matched)| **matched)
+        .map(|(c, _)| c);
+    let transpositions = left_seq.zip(right_seq).filter(|(a, b)| a != b).count() / 2;
+
+    let m = matches as f64;
+    let jaro =
+        (m / left.len() as f64 + m / right.len() as f64 + (m - transpositions as f64) / m) / 3.0;
+
+    let prefix = left
+        .iter()
+        .zip(&right)
+        .take(4)
+        .take_while(|(a, b)| a == b)
+        .count();
+    (jaro + prefix as f64 * JARO_WINKLER_PREFIX_SCALE * (1.0 - jaro)).min(1.0)
+}
+
+/// Weighted token counts of a normalized name, dropping connectors and
+/// discounting common given names
+fn token_frequency(input: &str) -> HashMap<String, f64> {
     let mut map = HashMap::new();
     for token in input.split_whitespace() {
-        *map.entry(token.to_string()).or_insert(0) += 1;
+        if NAME_STOPWORDS.contains(&token) {
+            continue;
+        }
+        let weight = if COMMON_GIVEN_NAMES.contains(&token) {
+            COMMON_GIVEN_NAME_WEIGHT
+        } else {
+            1.0
+        };
+        *map.entry(token.to_string()).or_insert(0.0) += weight;
     }
     map
 }
@@ -671,6 +1149,153 @@ fn token_frequency(input: &str) -> HashMap<String, usize> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_base_urls_prefers_list() {
+        let urls = parse_base_urls(
+            Some("https://a.example.com/, https://b.example.com ,"),
+            Some("https://single.example.com"),
+        );
+        assert_eq!(urls, vec!["https://a.example.com", "https://b.example.com"]);
+    }
+
+    #[test]
+    fn test_parse_base_urls_falls_back_to_single() {
+        assert_eq!(
+            parse_base_urls(Some("  "), Some("https://single.example.com/")),
+            vec!["https://single.example.com"]
+        );
+        assert_eq!(
+            parse_base_urls(None, Some("https://single.example.com")),
+            vec!["https://single.example.com"]
+        );
+        assert!(parse_base_urls(None, None).is_empty());
+    }
+
+    #[test]
+    fn test_parse_name_threshold() {
+        assert_eq!(parse_name_threshold(None).unwrap(), DEFAULT_NAME_THRESHOLD);
+        assert_eq!(
+            parse_name_threshold(Some(" ")).unwrap(),
+            DEFAULT_NAME_THRESHOLD
+        );
+        assert_eq!(parse_name_threshold(Some("0.8")).unwrap(), 0.8);
+        assert_eq!(parse_name_threshold(Some("1")).unwrap(), 1.0);
+        assert!(parse_name_threshold(Some("1.5")).is_err());
+        assert!(parse_name_threshold(Some("-0.1")).is_err());
+        assert!(parse_name_threshold(Some("high")).is_err());
+    }
+
+    #[test]
+    fn test_extract_best_candidate_threshold() {
+        let candidates = serde_json::json!([
+            { "cpf": "11111111111", "nome": "MARIA JOAQUINA SOUZA" },
+            { "cpf": "22222222222", "nome": "JOAO DA SILVA" }
+        ]);
+
+        let (cpf, score) = extract_best_candidate(
+            candidates.clone(),
+            Some("Maria Joaquina"),
+            0.5,
+            NameMatchStrategy::Cosine,
+        )
+        .unwrap();
+        assert_eq!(cpf.as_deref(), Some("11111111111"));
+        assert!((0.5..1.0).contains(&score.unwrap()));
+
+        assert!(extract_best_candidate(
+            candidates,
+            Some("Maria Joaquina"),
+            0.99,
+            NameMatchStrategy::Cosine,
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_extract_best_candidate_without_reference_name_has_no_score() {
+        let candidates = serde_json::json!([{ "cpf": "11111111111", "nome": "MARIA SOUZA" }]);
+        let (cpf, score) =
+            extract_best_candidate(candidates, None, 0.5, NameMatchStrategy::Cosine).unwrap();
+        assert_eq!(cpf.as_deref(), Some("11111111111"));
+        assert_eq!(score, None);
+
+        let single = serde_json::json!({ "cpf": "11111111111" });
+        let (_, score) =
+            extract_best_candidate(single, Some("Maria Souza"), 0.5, NameMatchStrategy::Cosine)
+                .unwrap();
+        assert_eq!(score, None);
+    }
+
+    #[test]
+    fn test_parse_workbuscas_body_shapes() {
+        let single = r#"{"DadosBasicos": {"nome": "Maria Souza", "cpf": "12345678901"}}"#;
+        let data = parse_workbuscas_body(single).unwrap().unwrap();
+        assert_eq!(data.base.cpf.as_deref(), Some("12345678901"));
+
+        let array = r#"[{"DadosBasicos": {"nome": "Maria Souza", "cpf": "12345678901"}}]"#;
+        let data = parse_workbuscas_body(array).unwrap().unwrap();
+        assert_eq!(data.base.name, "Maria Souza");
+        assert!(parse_workbuscas_body("[]").unwrap().is_none());
+
+        assert!(parse_workbuscas_body("\"oops\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_workbuscas_body_envelope() {
+        let enveloped = r#"{
+            "status": 200,
+            "data": {
+                "DadosBasicos": {"nome": "Maria Souza", "cpf": "12345678901"},
+                "emails": [{"email": "maria@example.com"}],
+                "telefones": [{"ddd": "11", "numero": "999990000"}]
+            }
+        }"#;
+        let data = parse_workbuscas_body(enveloped).unwrap().unwrap();
+        assert_eq!(data.base.cpf.as_deref(), Some("12345678901"));
+        assert_eq!(data.emails[0].email, "maria@example.com");
+        assert_eq!(data.phones[0].number.as_deref(), Some("999990000"));
+
+        let result_array = r#"{"status": "ok", "result": [{"DadosBasicos": {"nome": "Ana"}}]}"#;
+        let data = parse_workbuscas_body(result_array).unwrap().unwrap();
+        assert_eq!(data.base.name, "Ana");
+
+        let not_found = r#"{"status": 404, "data": null}"#;
+        assert!(parse_workbuscas_body(not_found).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_workbuscas_rankings_and_address_details() {
+        let body = r#"{
+            "DadosBasicos": {"nome": "Maria Souza"},
+            "emails": [{"email": "maria@example.com", "ranking": 1}, {"email": "m@example.com"}],
+            "telefones": [{"ddd": "11", "numero": "999990000", "ranking": "2"}],
+            "enderecos": [{
+                "logradouro": "Paulista",
+                "numero": "1000",
+                "complemento": "Apto 12",
+                "ranking": "n/a",
+                "latitude": -23.5614,
+                "longitude": "-46.6559",
+                "ddd": "11",
+                "tipoLogradouro": "Avenida",
+                "desconhecido": true
+            }]
+        }"#;
+        let data = parse_workbuscas_body(body).unwrap().unwrap();
+
+        assert_eq!(data.emails[0].ranking, Some(1));
+        assert_eq!(data.emails[1].ranking, None);
+        assert_eq!(data.phones[0].ranking, Some(2));
+
+        let address = &data.addresses[0];
+        assert_eq!(address.complement.as_deref(), Some("Apto 12"));
+        assert_eq!(address.ranking, None);
+        assert_eq!(address.latitude.as_deref(), Some("-23.5614"));
+        assert_eq!(address.longitude.as_deref(), Some("-46.6559"));
+        assert_eq!(address.ddd.as_deref(), Some("11"));
+        assert_eq!(address.street_type.as_deref(), Some("Avenida"));
+    }
+
     #[test]
     fn test_normalize() {
         assert_eq!(
@@ -696,4 +1321,72 @@ mod tests {
         assert!(score > 0.5);
         assert!(cosine_similarity("Joao", "Maria") < 0.2);
     }
+
+    #[test]
+    fn test_jaro_winkler() {
+        assert_eq!(jaro_winkler("Joao", "João"), 1.0);
+        assert!((jaro_winkler("MARTHA", "MARHTA") - 0.961).abs() < 0.001);
+        assert!(jaro_winkler("Joao", "Joã") > 0.9);
+        assert!(jaro_winkler("Joao", "Maria") < 0.5);
+        assert_eq!(jaro_winkler("", "Maria"), 0.0);
+    }
+
+    #[test]
+    fn test_name_match_strategies() {
+        let (left, right) = ("Joao Silva", "Joa Silvaa");
+        let cosine = NameMatchStrategy::Cosine.score(left, right);
+        let jaro = NameMatchStrategy::JaroWinkler.score(left, right);
+        assert!(cosine < 0.5 && jaro > 0.9);
+        assert_eq!(NameMatchStrategy::Max.score(left, right), jaro);
+        assert!((NameMatchStrategy::Blend.score(left, right) - (cosine + jaro) / 2.0).abs() < 1e-9);
+
+        let candidates = serde_json::json!([{ "cpf": "11111111111", "nome": "JOA SILVAA" }]);
+        assert!(extract_best_candidate(
+            candidates.clone(),
+            Some(left),
+            0.5,
+            NameMatchStrategy::Cosine
+        )
+        .is_none());
+        assert!(
+            extract_best_candidate(candidates, Some(left), 0.5, NameMatchStrategy::Max).is_some()
+        );
+    }
+
+    #[test]
+    fn test_parse_name_strategy() {
+        assert_eq!(
+            parse_name_strategy(None).unwrap(),
+            NameMatchStrategy::Cosine
+        );
+        assert_eq!(
+            parse_name_strategy(Some("Jaro_Winkler")).unwrap(),
+            NameMatchStrategy::JaroWinkler
+        );
+        assert_eq!(
+            parse_name_strategy(Some("max")).unwrap(),
+            NameMatchStrategy::Max
+        );
+        assert_eq!(
+            parse_name_strategy(Some("blend")).unwrap(),
+            NameMatchStrategy::Blend
+        );
+        assert!(parse_name_strategy(Some("soundex")).is_err());
+    }
+
+    #[test]
+    fn test_cosine_similarity_ignores_connectors() {
+        // Previously 1/3: "jose" and one connector each over three tokens
+        let score = cosine_similarity("Jose de Souza", "Jose da Silva");
+        assert!(score < 1.0 / 3.0, "score was {}", score);
+        assert_eq!(cosine_similarity("de", "da"), 0.0);
+        assert!((cosine_similarity("Ana dos Santos", "Ana Santos") - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cosine_similarity_discounts_common_given_names() {
+        let shared_common = cosine_similarity("Maria Oliveira", "Maria Pereira");
+        let shared_rare = cosine_similarity("Eleonora Oliveira", "Eleonora Pereira");
+        assert!(shared_common < shared_rare);
+    }
 }
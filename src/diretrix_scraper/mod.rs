@@ -1,10 +1,112 @@
+use crate::browser::{start_session, Browser};
+use crate::diretrix_enrichment::normalize;
+use crate::scraper::ProxyConfig;
+use crate::session_manager::SessionManager;
 use anyhow::{bail, Context, Result};
-use scraper::{Html, Selector};
+use scraper::{ElementRef, Html, Selector};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use thirtyfour::prelude::*;
+use thiserror::Error;
 use tokio::time::{sleep, Duration};
 use tracing::{debug, info, warn};
 
+/// How long to wait for the post-login page before declaring the login failed
+const LOGIN_VERIFY_ATTEMPTS: u32 = 10;
+
+/// Where the Diretrix login cookies are kept between runs
+const DIRETRIX_SESSION_FILE: &str = "diretrix_session.json";
+
+#[derive(Debug, Error)]
+pub enum DiretrixError {
+    #[error("Diretrix login failed for user {username}: {reason}")]
+    LoginFailed { username: String, reason: String },
+    #[error("Diretrix did not respond within {secs}s (login and search timed out)")]
+    Timeout { secs: u64 },
+    #[error("DIRETRIX_LOGIN_MARKER '{marker}' is not a valid CSS selector: {reason}")]
+    InvalidLoginMarker { marker: String, reason: String },
+}
+
+/// Logged-in marker from `DIRETRIX_LOGIN_MARKER`, parsed once up front
+#[derive(Clone)]
+struct LoginMarker {
+    css: String,
+    selector: Selector,
+}
+
+impl LoginMarker {
+    /// `None` for an unset or blank marker; an invalid selector is a configuration
+    /// error rather than a login that can never succeed
+    fn parse(value: Option<String>) -> Result<Option<Self>> {
+        let Some(css) = value.filter(|value| !value.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let selector = Selector::parse(&css).map_err(|err| err.to_string());
+        match selector {
+            Ok(selector) => Ok(Some(Self { css, selector })),
+            Err(reason) => Err(DiretrixError::InvalidLoginMarker {
+                marker: css,
+                reason,
+            }
+            .into()),
+        }
+    }
+}
+
+/// Upper bound on result pages (or "carregar mais" clicks) read per search
+const MAX_PAGES: usize = 50;
+
+/// Clicks the control that shows more results, whether a next-page link or a
+/// "carregar mais" button, and reports whether one was clicked. Disabled or
+/// hidden controls are ignored, so `false` means this is the last page.
+const NEXT_PAGE_SCRIPT: &str = r#"
+    const usable = (el) => {
+        if (!el || el.offsetParent === null) return false;
+        if (el.disabled || el.getAttribute('aria-disabled') === 'true') return false;
+        const item = el.closest('li, .paginate_button');
+        return !(item && item.classList.contains('disabled')) && !el.classList.contains('disabled');
+    };
+
+    // Only look around the results table, not at the site navigation
+    const table = document.getElementById('Relatorio');
+    if (!table) return false;
+    const scope = table.closest('.dataTables_wrapper')
+        || (table.parentElement && table.parentElement.parentElement)
+        || document;
+
+    for (const el of scope.querySelectorAll('a, button, input[type=button]')) {
+        // Row buttons (e.g. button.enderecoDet) open details, not more results
+        if (el.closest('tbody')) continue;
+        const text = (el.textContent || el.value || '').trim().toLowerCase();
+        const isNext = el.classList.contains('next')
+            || text === '»' || text === '>' || text.startsWith('próxima') || text.startsWith('proxima')
+            || text.includes('carregar mais') || text.includes('ver mais') || text.includes('mais resultados');
+        if (isNext && usable(el)) {
+            el.click();
+            return true;
+        }
+    }
+    return false;
+"#;
+
+/// Default deadline for one login + search (`--diretrix-timeout-secs`)
+pub const DEFAULT_DIRETRIX_TIMEOUT_SECS: u64 = 300;
+
+/// Run a Diretrix step with an overall deadline, failing with `DiretrixError::Timeout`
+/// when it elapses. The caller is responsible for closing the browser afterwards.
+pub async fn with_deadline<T>(
+    timeout: Duration,
+    step: impl std::future::Future<Output = Result<T>>,
+) -> Result<T> {
+    match tokio::time::timeout(timeout, step).await {
+        Ok(result) => result,
+        Err(_) => Err(DiretrixError::Timeout {
+            secs: timeout.as_secs(),
+        }
+        .into()),
+    }
+}
+
 async fn click_if_present(driver: &WebDriver, by: By) -> bool {
     match driver.find(by).await {
         Ok(elem) => {
@@ -21,7 +123,7 @@ async fn click_if_present(driver: &WebDriver, by: By) -> bool {
 }
 
 /// Represents a property record from the Diretrix Consultoria website
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PropertyRecord {
     /// Owner name (Proprietário)
     pub owner: String,
@@ -48,41 +150,70 @@ pub struct DiretrixScraper {
     base_url: String,
     username: String,
     password: String,
+    /// CSS selector that only exists once logged in (`DIRETRIX_LOGIN_MARKER`).
+    /// When unset, login is verified by the login form disappearing.
+    login_marker: Option<LoginMarker>,
+    /// Generated proxy auth extension, removed on close
+    proxy_extension: Option<PathBuf>,
 }
 
 impl DiretrixScraper {
-    /// Create a new Diretrix scraper with credentials and WebDriver URL
+    /// Create a new Diretrix scraper with credentials, WebDriver URL, browser
+    /// and optional proxy
     pub async fn new(
         username: String,
         password: String,
         webdriver_url: &str,
         headless: bool,
+        browser: Browser,
+        proxy: Option<&ProxyConfig>,
     ) -> Result<Self> {
-        let mut caps = DesiredCapabilities::chrome();
-        if headless {
-            caps.add_chrome_arg("--headless")?;
-        }
-        caps.add_chrome_arg("--no-sandbox")?;
-        caps.add_chrome_arg("--disable-dev-shm-usage")?;
-        caps.add_chrome_arg("--disable-gpu")?;
-        caps.add_chrome_arg("--window-size=1920,1080")?;
-
-        let driver = WebDriver::new(webdriver_url, caps)
-            .await
-            .context("Failed to connect to WebDriver")?;
+        let login_marker = LoginMarker::parse(std::env::var("DIRETRIX_LOGIN_MARKER").ok())?;
+        let caps = browser.capabilities(headless)?;
+        let (driver, proxy_extension) = start_session(caps, webdriver_url, proxy).await?;
 
         Ok(Self {
             driver,
+            proxy_extension,
             base_url: "https://www.diretrixconsultoria.com.br".to_string(),
             username,
             password,
+            login_marker,
         })
     }
 
-    /// Login to the Diretrix website
+    /// Saved cookies shared by every Diretrix session, checked against the
+    /// login marker (or the login form being gone)
+    fn session_manager(&self) -> SessionManager {
+        SessionManager::with_config(
+            DIRETRIX_SESSION_FILE,
+            self.base_url.clone(),
+            self.login_marker.as_ref().map(|marker| marker.css.clone()),
+        )
+    }
+
+    /// Login to the Diretrix website, reusing the saved session when it is
+    /// still valid
     pub async fn login(&self) -> Result<()> {
         info!("Logging in to Diretrix Consultoria...");
 
+        let session_manager = self.session_manager();
+        match session_manager.load_session(&self.driver).await {
+            Ok(true) => {
+                if session_manager.is_session_valid(&self.driver).await? {
+                    info!("✅ Using saved Diretrix session - skipping login!");
+                    return Ok(());
+                }
+                info!("Saved Diretrix session expired, performing fresh login...");
+                session_manager.clear_session()?;
+            }
+            Ok(false) => {}
+            Err(e) => {
+                warn!("Ignoring unreadable Diretrix session: {:#}", e);
+                session_manager.clear_session()?;
+            }
+        }
+
         // Navigate to the base URL
         self.driver.goto(&self.base_url).await?;
 
@@ -149,10 +280,14 @@ impl DiretrixScraper {
         debug!("Clicked login button");
 
         // Wait for login to complete and dashboard to load
-        sleep(Duration::from_secs(5)).await;
+        self.verify_login().await?;
 
         info!("Login completed successfully");
 
+        if let Err(e) = session_manager.save_session(&self.driver).await {
+            warn!("Failed to save Diretrix session: {}", e);
+        }
+
         // Don't navigate directly to avoid 404 errors
         // The ensure_on_search_page method will handle navigation via menu/breadcrumb
         info!("Ready to navigate to search page via menu");
@@ -160,9 +295,43 @@ impl DiretrixScraper {
         Ok(())
     }
 
+    /// Poll until the logged-in marker appears (or the login form disappears),
+    /// returning `DiretrixError::LoginFailed` if neither happens in time
+    async fn verify_login(&self) -> Result<()> {
+        for _ in 0..LOGIN_VERIFY_ATTEMPTS {
+            sleep(Duration::from_secs(1)).await;
+
+            // An unreadable page is not proof of anything; keep polling
+            match self.driver.source().await {
+                Ok(html) => {
+                    let marker = self.login_marker.as_ref().map(|marker| &marker.selector);
+                    if login_succeeded(&html, marker) {
+                        debug!("Login verified");
+                        return Ok(());
+                    }
+                }
+                Err(err) => debug!("Could not read the page after login: {}", err),
+            }
+        }
+
+        let reason = match &self.login_marker {
+            Some(marker) => format!("logged-in marker '{}' never appeared", marker.css),
+            None => "login form is still displayed (check credentials)".to_string(),
+        };
+        Err(DiretrixError::LoginFailed {
+            username: self.username.clone(),
+            reason,
+        }
+        .into())
+    }
+
     /// Close the browser
     pub async fn close(self) -> Result<()> {
-        self.driver.quit().await?;
+        let quit = self.driver.quit().await;
+        if let Some(extension) = self.proxy_extension {
+            let _ = std::fs::remove_dir_all(extension);
+        }
+        quit?;
         Ok(())
     }
 
@@ -245,41 +414,38 @@ impl DiretrixScraper {
             }
 
             // Strategy 2: Try menu navigation via link text
-            if !navigated {
-                if click_if_present(&self.driver, By::LinkText("IP-Trix")).await {
-                    debug!("Clicked IP-Trix menu link");
-                    sleep(Duration::from_millis(800)).await;
+            if !navigated && click_if_present(&self.driver, By::LinkText("IP-Trix")).await {
+                debug!("Clicked IP-Trix menu link");
+                sleep(Duration::from_millis(800)).await;
 
-                    if click_if_present(&self.driver, By::LinkText("Por Endereço")).await {
-                        debug!("Clicked Por Endereço submenu");
-                        navigated = true;
-                    }
+                if click_if_present(&self.driver, By::LinkText("Por Endereço")).await {
+                    debug!("Clicked Por Endereço submenu");
+                    navigated = true;
                 }
             }
 
             // Strategy 3: Try direct href click (less preferred)
-            if !navigated {
-                if click_if_present(&self.driver, By::Css("a[href='/IPTU/PorEndereco']")).await {
-                    debug!("Clicked direct Por Endereço link");
-                    navigated = true;
-                }
+            if !navigated
+                && click_if_present(&self.driver, By::Css("a[href='/IPTU/PorEndereco']")).await
+            {
+                debug!("Clicked direct Por Endereço link");
+                navigated = true;
             }
 
             // Strategy 4: Try span-based navigation
-            if !navigated {
-                if click_if_present(
+            if !navigated
+                && click_if_present(
                     &self.driver,
                     By::XPath("//span[contains(.,'IP-TRIX') or contains(.,'IPTRIX')]"),
                 )
                 .await
-                {
-                    debug!("Clicked IP-TRIX span element");
-                    sleep(Duration::from_millis(800)).await;
+            {
+                debug!("Clicked IP-TRIX span element");
+                sleep(Duration::from_millis(800)).await;
 
-                    if click_if_present(&self.driver, By::LinkText("Por Endereço")).await {
-                        debug!("Clicked Por Endereço after span click");
-                        navigated = true;
-                    }
+                if click_if_present(&self.driver, By::LinkText("Por Endereço")).await {
+                    debug!("Clicked Por Endereço after span click");
+                    navigated = true;
                 }
             }
 
@@ -548,16 +714,67 @@ impl DiretrixScraper {
         // Wait for results to load (AJAX request)
         sleep(Duration::from_secs(5)).await;
 
-        // Get the page HTML
-        let html_content = self.driver.source().await?;
-        debug!("Received HTML response of {} bytes", html_content.len());
+        let records = self.extract_all_pages().await;
 
         if switched_to_frame {
             let _ = self.driver.enter_default_frame().await;
         }
 
-        // Parse the HTML and extract property records
-        self.parse_property_table(&html_content)
+        records
+    }
+
+    /// Parse the results table, then keep clicking through to the next page
+    /// (or "carregar mais") until there is none, accumulating the records
+    async fn extract_all_pages(&self) -> Result<Vec<PropertyRecord>> {
+        let mut all_records = Vec::new();
+        let mut page_num = 1;
+
+        loop {
+            let html_content = self.driver.source().await?;
+            debug!(
+                "Received HTML response of {} bytes (page {})",
+                html_content.len(),
+                page_num
+            );
+
+            let added = merge_new_records(&mut all_records, parse_property_table(&html_content)?);
+            if page_num > 1 && added == 0 {
+                info!("Page {} added no new records, stopping", page_num);
+                break;
+            }
+
+            if page_num >= MAX_PAGES {
+                warn!("Reached maximum page limit ({}), stopping", MAX_PAGES);
+                break;
+            }
+
+            if !self.click_next_page().await {
+                break;
+            }
+            page_num += 1;
+            info!("Loading results page {}...", page_num);
+            sleep(Duration::from_secs(3)).await;
+        }
+
+        if page_num > 1 {
+            info!(
+                "Collected {} property records from {} pages",
+                all_records.len(),
+                page_num
+            );
+        }
+        Ok(all_records)
+    }
+
+    /// Click the next-page / "carregar mais" control if the results have one
+    async fn click_next_page(&self) -> bool {
+        match self.driver.execute(NEXT_PAGE_SCRIPT, vec![]).await {
+            Ok(result) => result.convert::<bool>().unwrap_or(false),
+            Err(e) => {
+                debug!("Next page check failed: {}", e);
+                false
+            }
+        }
     }
 
     /// Manual search mode - wait for user to complete the search manually
@@ -587,81 +804,430 @@ impl DiretrixScraper {
         debug!("Received HTML response of {} bytes", html_content.len());
 
         // Parse the HTML and extract property records
-        self.parse_property_table(&html_content)
+        parse_property_table(&html_content)
+    }
+}
+
+/// Parse the HTML table containing property records
+fn parse_property_table(html: &str) -> Result<Vec<PropertyRecord>> {
+    let document = Html::parse_document(html);
+
+    // Check if there are no results
+    let no_results_selector = Selector::parse("#msgtab").unwrap();
+    if let Some(msg_element) = document.select(&no_results_selector).next() {
+        let display_style = msg_element.value().attr("style").unwrap_or("");
+        if !display_style.contains("display:none") {
+            warn!("No records found");
+            return Ok(Vec::new());
+        }
     }
 
-    /// Parse the HTML table containing property records
-    fn parse_property_table(&self, html: &str) -> Result<Vec<PropertyRecord>> {
-        let document = Html::parse_document(html);
+    let columns = match result_headers(&document) {
+        Some(headers) => PropertyColumns::from_headers(&headers).unwrap_or_else(|| {
+            warn!(
+                "Unrecognized Diretrix result headers {:?}, parsing columns by position",
+                headers
+            );
+            PropertyColumns::POSITIONAL
+        }),
+        None => {
+            debug!("Diretrix results have no header row, parsing columns by position");
+            PropertyColumns::POSITIONAL
+        }
+    };
 
-        // Check if there are no results
-        let no_results_selector = Selector::parse("#msgtab").unwrap();
-        if let Some(msg_element) = document.select(&no_results_selector).next() {
-            let display_style = msg_element.value().attr("style").unwrap_or("");
-            if !display_style.contains("display:none") {
-                warn!("No records found");
-                return Ok(Vec::new());
-            }
+    // Select all table rows in the tbody
+    let row_selector = Selector::parse("#Relatorio tr").unwrap();
+    let td_selector = Selector::parse("td").unwrap();
+    let button_selector = Selector::parse("button.enderecoDet").unwrap();
+
+    let mut records = Vec::new();
+
+    for row in document.select(&row_selector) {
+        let cells: Vec<_> = row.select(&td_selector).collect();
+
+        if cells.len() < columns.min_cells() {
+            warn!("Skipping row with insufficient cells");
+            continue;
         }
 
-        // Select all table rows in the tbody
-        let row_selector = Selector::parse("#Relatorio tr").unwrap();
-        let td_selector = Selector::parse("td").unwrap();
-        let button_selector = Selector::parse("button.enderecoDet").unwrap();
+        let text = |index: Option<usize>| {
+            index
+                .and_then(|index| cells.get(index))
+                .map(|cell| cell.text().collect::<String>().trim().to_string())
+                .unwrap_or_default()
+        };
 
-        let mut records = Vec::new();
+        // Documents live on the details button, wherever its column is;
+        // a plain document column is used when the button is missing
+        let button = row.select(&button_selector).next();
+        let document1 = button
+            .and_then(|b| b.value().attr("data-documento"))
+            .map(|s| s.to_string())
+            .or_else(|| Some(text(columns.document)).filter(|value| !value.is_empty()));
+        let document2 = button
+            .and_then(|b| b.value().attr("data-documento-2"))
+            .map(|s| s.to_string());
+
+        let record = PropertyRecord {
+            owner: text(Some(columns.owner)),
+            iptu: text(Some(columns.iptu)),
+            street: text(columns.street),
+            number: text(columns.number),
+            complement: text(columns.complement),
+            complement2: text(columns.complement2),
+            neighborhood: text(columns.neighborhood),
+            document1,
+            document2,
+        };
 
-        for row in document.select(&row_selector) {
-            let cells: Vec<_> = row.select(&td_selector).collect();
+        debug!("Parsed record: {:?}", record);
+        records.push(record);
+    }
 
-            if cells.len() < 8 {
-                warn!("Skipping row with insufficient cells");
-                continue;
-            }
+    info!("Parsed {} property records", records.len());
+    Ok(records)
+}
+
+/// Cell index of each field in a results row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PropertyColumns {
+    owner: usize,
+    iptu: usize,
+    street: Option<usize>,
+    number: Option<usize>,
+    complement: Option<usize>,
+    complement2: Option<usize>,
+    neighborhood: Option<usize>,
+    document: Option<usize>,
+}
+
+impl PropertyColumns {
+    /// The historical layout, used when the table has no usable header
+    const POSITIONAL: Self = Self {
+        owner: 0,
+        iptu: 1,
+        street: Some(2),
+        number: Some(3),
+        complement: Some(4),
+        complement2: Some(5),
+        neighborhood: Some(6),
+        document: Some(7),
+    };
+
+    /// Map header names to cells; `None` unless both the owner and the IPTU
+    /// columns are found
+    fn from_headers(headers: &[String]) -> Option<Self> {
+        let mut owner = None;
+        let mut iptu = None;
+        let mut columns = Self {
+            owner: 0,
+            iptu: 0,
+            street: None,
+            number: None,
+            complement: None,
+            complement2: None,
+            neighborhood: None,
+            document: None,
+        };
 
-            // Extract text from cells
-            let owner = cells[0].text().collect::<String>().trim().to_string();
-            let iptu = cells[1].text().collect::<String>().trim().to_string();
-            let street = cells[2].text().collect::<String>().trim().to_string();
-            let number = cells[3].text().collect::<String>().trim().to_string();
-            let complement = cells[4].text().collect::<String>().trim().to_string();
-            let complement2 = cells[5].text().collect::<String>().trim().to_string();
-            let neighborhood = cells[6].text().collect::<String>().trim().to_string();
-
-            // Extract document numbers from button attributes
-            let button = cells[7].select(&button_selector).next();
-            let document1 = button
-                .and_then(|b| b.value().attr("data-documento"))
-                .map(|s| s.to_string());
-            let document2 = button
-                .and_then(|b| b.value().attr("data-documento-2"))
-                .map(|s| s.to_string());
-
-            let record = PropertyRecord {
-                owner,
-                iptu,
-                street,
-                number,
-                complement,
-                complement2,
-                neighborhood,
-                document1,
-                document2,
+        for (index, header) in headers.iter().enumerate() {
+            let Some(name) = normalize(header) else {
+                continue;
+            };
+            // IPTU first: "Nº do contribuinte" would otherwise read as a number
+            let slot = if name.contains("iptu") || name.contains("contribuinte") {
+                &mut iptu
+            } else if name.starts_with("proprietario") || name == "nome" {
+                &mut owner
+            } else if name.starts_with("logradouro") || name.starts_with("endereco") {
+                &mut columns.street
+            } else if name.starts_with("numero") || matches!(name.as_str(), "n" | "nº" | "n°") {
+                &mut columns.number
+            } else if name.starts_with("complemento") {
+                if name.ends_with('2') || columns.complement.is_some() {
+                    &mut columns.complement2
+                } else {
+                    &mut columns.complement
+                }
+            } else if name.starts_with("bairro") {
+                &mut columns.neighborhood
+            } else if name.starts_with("documento")
+                || name.starts_with("cpf")
+                || name.starts_with("cnpj")
+            {
+                &mut columns.document
+            } else {
+                continue;
             };
+            slot.get_or_insert(index);
+        }
 
-            debug!("Parsed record: {:?}", record);
-            records.push(record);
+        columns.owner = owner?;
+        columns.iptu = iptu?;
+        Some(columns)
+    }
+
+    /// Rows shorter than this cannot hold the owner and the IPTU
+    fn min_cells(&self) -> usize {
+        if *self == Self::POSITIONAL {
+            8
+        } else {
+            self.owner.max(self.iptu) + 1
         }
+    }
+}
+
+/// Header names of the table holding `#Relatorio`, if it has a header row
+fn result_headers(document: &Html) -> Option<Vec<String>> {
+    let table_selector = Selector::parse("table").unwrap();
+    let body_selector = Selector::parse("#Relatorio").unwrap();
+    let header_selector = Selector::parse("thead th, thead td").unwrap();
+
+    let table = document
+        .select(&table_selector)
+        .find(|table| table.select(&body_selector).next().is_some())?;
+    let headers: Vec<String> = table
+        .select(&header_selector)
+        .map(|cell| cell.text().collect::<String>().trim().to_string())
+        .collect();
+    (!headers.is_empty()).then_some(headers)
+}
 
-        info!("Parsed {} property records", records.len());
-        Ok(records)
+/// Whether `html` shows a logged-in page: the `login_marker` selector matches,
+/// or, without a marker, no password field is displayed any more. A blank page
+/// (still loading, or nothing to read) is never logged in.
+fn login_succeeded(html: &str, login_marker: Option<&Selector>) -> bool {
+    let document = Html::parse_document(html);
+    let body = Selector::parse("body *").unwrap();
+    let blank = document.select(&body).next().is_none()
+        && document
+            .root_element()
+            .text()
+            .all(|text| text.trim().is_empty());
+    if blank {
+        return false;
     }
+    match login_marker {
+        Some(selector) => document.select(selector).next().is_some(),
+        None => {
+            let password = Selector::parse("input[type='password']").unwrap();
+            !document.select(&password).any(is_displayed)
+        }
+    }
+}
+
+/// False when the element or an ancestor is hidden with the `hidden` attribute
+/// or an inline `display: none` / `visibility: hidden`
+fn is_displayed(element: ElementRef) -> bool {
+    std::iter::once(element)
+        .chain(element.ancestors().filter_map(ElementRef::wrap))
+        .all(|element| {
+            let value = element.value();
+            let style = value
+                .attr("style")
+                .unwrap_or("")
+                .to_lowercase()
+                .replace(char::is_whitespace, "");
+            value.attr("hidden").is_none()
+                && !style.contains("display:none")
+                && !style.contains("visibility:hidden")
+        })
+}
+
+/// Append the records of a page that are not already in `all_records`, so a
+/// "carregar mais" table that keeps earlier rows is not counted twice; returns
+/// how many were added
+fn merge_new_records(all_records: &mut Vec<PropertyRecord>, page: Vec<PropertyRecord>) -> usize {
+    let before = all_records.len();
+    for record in page {
+        if !all_records.contains(&record) {
+            all_records.push(record);
+        }
+    }
+    all_records.len() - before
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn record(owner: &str, iptu: &str) -> PropertyRecord {
+        PropertyRecord {
+            owner: owner.to_string(),
+            iptu: iptu.to_string(),
+            street: "R DOMINGOS LEME".to_string(),
+            number: "440".to_string(),
+            complement: String::new(),
+            complement2: String::new(),
+            neighborhood: "VILA NOVA CONCEICAO".to_string(),
+            document1: None,
+            document2: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_property_table_by_header_name() {
+        // Columns reordered and the details button missing on the second row
+        let html = r#"
+            <table><thead><tr>
+                <th>IPTU</th><th>Proprietário</th><th>Bairro</th><th>Logradouro</th>
+                <th>Número</th><th>Complemento</th><th>Complemento 2</th><th>Documento</th>
+            </tr></thead>
+            <tbody id="Relatorio">
+                <tr><td>008.045.0123-4</td><td>MARIA</td><td>CENTRO</td><td>R A</td>
+                    <td>10</td><td>AP 1</td><td>BL 2</td>
+                    <td><button class="enderecoDet" data-documento="123" data-documento-2="456">Buscar</button></td></tr>
+                <tr><td>008.045.0124-2</td><td>JOSE</td><td>CENTRO</td><td>R A</td>
+                    <td>12</td><td></td><td></td><td>98765432100</td></tr>
+            </tbody></table>"#;
+
+        let records = parse_property_table(html).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].owner, "MARIA");
+        assert_eq!(records[0].iptu, "008.045.0123-4");
+        assert_eq!(records[0].street, "R A");
+        assert_eq!(records[0].neighborhood, "CENTRO");
+        assert_eq!(records[0].complement2, "BL 2");
+        assert_eq!(records[0].document1.as_deref(), Some("123"));
+        assert_eq!(records[0].document2.as_deref(), Some("456"));
+        assert_eq!(records[1].document1.as_deref(), Some("98765432100"));
+        assert_eq!(records[1].document2, None);
+    }
+
+    #[test]
+    fn test_parse_property_table_without_header_is_positional() {
+        let html = r#"
+            <table><tbody id="Relatorio">
+                <tr><td>MARIA</td><td>00804501234</td><td>R A</td><td>10</td>
+                    <td></td><td></td><td>CENTRO</td>
+                    <td><button class="enderecoDet" data-documento="123">Buscar</button></td></tr>
+                <tr><td>INCOMPLETA</td><td>00804501235</td></tr>
+            </tbody></table>"#;
+
+        let records = parse_property_table(html).unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].owner, "MARIA");
+        assert_eq!(records[0].neighborhood, "CENTRO");
+        assert_eq!(records[0].document1.as_deref(), Some("123"));
+    }
+
+    #[test]
+    fn test_merge_new_records_across_pages() {
+        let mut all = Vec::new();
+        assert_eq!(
+            merge_new_records(&mut all, vec![record("A", "1"), record("B", "2")]),
+            2
+        );
+        // "carregar mais" re-renders earlier rows along with the new ones
+        assert_eq!(
+            merge_new_records(
+                &mut all,
+                vec![record("A", "1"), record("B", "2"), record("C", "3")]
+            ),
+            1
+        );
+        // A next page with only known rows adds nothing
+        assert_eq!(merge_new_records(&mut all, vec![record("C", "3")]), 0);
+
+        let owners: Vec<&str> = all.iter().map(|r| r.owner.as_str()).collect();
+        assert_eq!(owners, vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_login_succeeded_without_marker_checks_password_field() {
+        let login_page = r#"<html><body><form>
+            <input type="text" name="usuario"><input type="password" name="senha">
+            </form></body></html>"#;
+        let home_page = r#"<html><body><div id="menu">IP-Trix</div>
+            <div style="display: none"><input type="password"></div></body></html>"#;
+
+        assert!(!login_succeeded(login_page, None));
+        assert!(login_succeeded(home_page, None));
+    }
+
+    #[test]
+    fn test_login_succeeded_with_marker() {
+        let marker = Selector::parse("#logout").unwrap();
+        let home_page = r#"<html><body><a id="logout" href="/Sair">Sair</a></body></html>"#;
+        let login_page = r#"<html><body><input type="password"></body></html>"#;
+        let other_page = r#"<html><body><div id="menu">IP-Trix</div></body></html>"#;
+
+        assert!(login_succeeded(home_page, Some(&marker)));
+        assert!(!login_succeeded(login_page, Some(&marker)));
+        // A marker is authoritative even when no password field is left
+        assert!(!login_succeeded(other_page, Some(&marker)));
+    }
+
+    #[test]
+    fn test_login_succeeded_rejects_blank_pages() {
+        assert!(!login_succeeded("", None));
+        assert!(!login_succeeded(
+            "<html><head></head><body> </body></html>",
+            None
+        ));
+    }
+
+    #[test]
+    fn test_login_marker_is_validated_up_front() {
+        assert!(LoginMarker::parse(None).unwrap().is_none());
+        assert!(LoginMarker::parse(Some("  ".to_string()))
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            LoginMarker::parse(Some("#iframeConteudo".to_string()))
+                .unwrap()
+                .unwrap()
+                .css,
+            "#iframeConteudo"
+        );
+
+        let err = LoginMarker::parse(Some("#menu[".to_string()))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<DiretrixError>(),
+            Some(DiretrixError::InvalidLoginMarker { .. })
+        ));
+    }
+
+    #[test]
+    fn test_login_failed_error_is_downcastable() {
+        let err: anyhow::Error = DiretrixError::LoginFailed {
+            username: "888888".to_string(),
+            reason: "login form is still displayed (check credentials)".to_string(),
+        }
+        .into();
+
+        assert!(matches!(
+            err.downcast_ref::<DiretrixError>(),
+            Some(DiretrixError::LoginFailed { .. })
+        ));
+        assert!(err.to_string().contains("888888"));
+    }
+
+    #[tokio::test]
+    async fn test_with_deadline_times_out_wedged_step() {
+        let err = with_deadline(Duration::from_millis(20), async {
+            sleep(Duration::from_secs(60)).await;
+            Ok(())
+        })
+        .await
+        .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<DiretrixError>(),
+            Some(DiretrixError::Timeout { .. })
+        ));
+
+        let value = with_deadline(Duration::from_secs(1), async { Ok(7) })
+            .await
+            .unwrap();
+        assert_eq!(value, 7);
+    }
+
     #[tokio::test]
     #[ignore] // Requires valid credentials and WebDriver
     async fn test_login() {
@@ -670,9 +1236,16 @@ mod tests {
         let webdriver_url = std::env::var("DIRETRIX_WEBDRIVER_URL")
             .unwrap_or_else(|_| "http://localhost:9515".to_string());
 
-        let scraper = DiretrixScraper::new(username, password, &webdriver_url, false)
-            .await
-            .expect("Failed to create scraper");
+        let scraper = DiretrixScraper::new(
+            username,
+            password,
+            &webdriver_url,
+            false,
+            Browser::Chrome,
+            None,
+        )
+        .await
+        .expect("Failed to create scraper");
 
         let result = scraper.login().await;
         assert!(result.is_ok());
@@ -688,9 +1261,16 @@ mod tests {
         let webdriver_url = std::env::var("DIRETRIX_WEBDRIVER_URL")
             .unwrap_or_else(|_| "http://localhost:9515".to_string());
 
-        let scraper = DiretrixScraper::new(username, password, &webdriver_url, false)
-            .await
-            .expect("Failed to create scraper");
+        let scraper = DiretrixScraper::new(
+            username,
+            password,
+            &webdriver_url,
+            false,
+            Browser::Chrome,
+            None,
+        )
+        .await
+        .expect("Failed to create scraper");
 
         scraper.login().await.expect("Login failed");
 
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::diretrix_enrichment::{normalize, GetCustomerData};
+
+/// How long entries loaded from `ENRICHMENT_CACHE_PATH` stay valid unless
+/// `ENRICHMENT_CACHE_TTL_DAYS` says otherwise
+const DEFAULT_CACHE_TTL_DAYS: i64 = 30;
+
+/// One cached answer and when it was fetched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    result: Option<GetCustomerData>,
+    cached_at: DateTime<Utc>,
+}
+
+/// Enrichment lookups already answered, so an owner who holds many properties
+/// costs one API call. `None` results remember that the backend had no data;
+/// failed calls are never cached. With `ENRICHMENT_CACHE_PATH` set, the cache
+/// is read from and written back to that JSON file so later runs reuse it too.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EnrichmentCache {
+    /// Keyed by search type and padded document, e.g. `cpf:01234567890`
+    by_document: HashMap<String, CacheEntry>,
+    /// Keyed by accent-stripped, lowercased owner name
+    by_name: HashMap<String, CacheEntry>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(skip)]
+    ttl: Option<chrono::Duration>,
+}
+
+impl EnrichmentCache {
+    /// Cache backed by `ENRICHMENT_CACHE_PATH` when set, else in-memory only.
+    /// An unreadable cache file is ignored (and replaced on save).
+    pub fn from_env() -> Self {
+        let Some(path) = std::env::var("ENRICHMENT_CACHE_PATH")
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+        else {
+            return Self::default();
+        };
+
+        let ttl_days = match std::env::var("ENRICHMENT_CACHE_TTL_DAYS") {
+            Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                warn!(
+                    "Invalid ENRICHMENT_CACHE_TTL_DAYS '{}', using {} days",
+                    value, DEFAULT_CACHE_TTL_DAYS
+                );
+                DEFAULT_CACHE_TTL_DAYS
+            }),
+            Err(_) => DEFAULT_CACHE_TTL_DAYS,
+        };
+
+        let path = PathBuf::from(path);
+        let ttl = chrono::Duration::days(ttl_days);
+        match Self::load(&path, ttl) {
+            Ok(cache) => {
+                info!(
+                    "Loaded {} cached enrichment(s) from {}",
+                    cache.by_document.len() + cache.by_name.len(),
+                    path.display()
+                );
+                cache
+            }
+            Err(err) => {
+                warn!("Ignoring enrichment cache: {:#}", err);
+                Self {
+                    path: Some(path),
+                    ttl: Some(ttl),
+                    ..Self::default()
+                }
+            }
+        }
+    }
+
+    /// Read the cache file, dropping entries older than `ttl`; a missing
+    /// file gives an empty cache
+    pub fn load(path: &Path, ttl: chrono::Duration) -> Result<Self> {
+        let mut cache = if path.exists() {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str::<Self>(&contents)
+                .with_context(|| format!("Invalid enrichment cache {}", path.display()))?
+        } else {
+            Self::default()
+        };
+        cache.path = Some(path.to_path_buf());
+        cache.ttl = Some(ttl);
+
+        let now = Utc::now();
+        cache
+            .by_document
+            .retain(|_, entry| now - entry.cached_at < ttl);
+        cache.by_name.retain(|_, entry| now - entry.cached_at < ttl);
+        Ok(cache)
+    }
+
+    /// Write the cache back to its file; does nothing for an in-memory cache
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_string(self)?;
+        // Write then rename so an interrupted save keeps the previous file
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, contents)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("Failed to write enrichment cache {}", path.display()))?;
+        info!(
+            "Saved {} enrichment(s) to {}",
+            self.by_document.len() + self.by_name.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    pub fn get_document(
+        &self,
+        search_type: &str,
+        document: &str,
+    ) -> Option<Option<GetCustomerData>> {
+        self.lookup(&self.by_document, &document_key(search_type, document))
+    }
+
+    pub fn insert_document(
+        &mut self,
+        search_type: &str,
+        document: &str,
+        result: Option<GetCustomerData>,
+    ) {
+        self.by_document
+            .insert(document_key(search_type, document), CacheEntry::new(result));
+    }
+
+    pub fn get_name(&self, name: &str) -> Option<Option<GetCustomerData>> {
+        self.lookup(&self.by_name, &normalize(name)?)
+    }
+
+    pub fn insert_name(&mut self, name: &str, result: Option<GetCustomerData>) {
+        if let Some(key) = normalize(name) {
+            self.by_name.insert(key, CacheEntry::new(result));
+        }
+    }
+
+    /// An unexpired entry; the TTL is checked again because long runs can
+    /// outlive entries that were fresh at load time
+    fn lookup(
+        &self,
+        entries: &HashMap<String, CacheEntry>,
+        key: &str,
+    ) -> Option<Option<GetCustomerData>> {
+        let entry = entries.get(key)?;
+        if let Some(ttl) = self.ttl {
+            if Utc::now() - entry.cached_at >= ttl {
+                return None;
+            }
+        }
+        Some(entry.result.clone())
+    }
+}
+
+impl CacheEntry {
+    fn new(result: Option<GetCustomerData>) -> Self {
+        Self {
+            result,
+            cached_at: Utc::now(),
+        }
+    }
+}
+
+fn document_key(search_type: &str, document: &str) -> String {
+    format!("{}:{}", search_type, document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diretrix_enrichment::CustomerBase;
+
+    fn customer(name: &str) -> GetCustomerData {
+        GetCustomerData {
+            base: CustomerBase {
+                id: name.to_string(),
+                name: name.to_string(),
+                cpf: None,
+                birth_date: None,
+                sex: None,
+                mother_name: None,
+                father_name: None,
+                rg: None,
+            },
+            emails: Vec::new(),
+            phones: Vec::new(),
+            addresses: Vec::new(),
+            match_score: None,
+            match_method: None,
+        }
+    }
+
+    #[test]
+    fn test_cache_lookups() {
+        let mut cache = EnrichmentCache::default();
+        assert!(cache.get_document("cpf", "01234567890").is_none());
+
+        cache.insert_document("cpf", "01234567890", Some(customer("JOÃO")));
+        cache.insert_document("cnpj", "12345678000195", None);
+        cache.insert_name("  João   da Silva ", Some(customer("JOÃO DA SILVA")));
+
+        let hit = cache.get_document("cpf", "01234567890").unwrap().unwrap();
+        assert_eq!(hit.base.name, "JOÃO");
+        assert!(matches!(
+            cache.get_document("cnpj", "12345678000195"),
+            Some(None)
+        ));
+        assert!(cache.get_document("cnpj", "01234567890").is_none());
+
+        let hit = cache.get_name("JOAO DA SILVA").unwrap().unwrap();
+        assert_eq!(hit.base.name, "JOÃO DA SILVA");
+        assert!(cache.get_name("   ").is_none());
+    }
+
+    #[test]
+    fn test_cache_file_round_trip_and_ttl() {
+        let dir = std::env::temp_dir().join(format!("enrichment_cache_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cache.json");
+        let ttl = chrono::Duration::days(30);
+
+        let mut cache = EnrichmentCache::load(&path, ttl).unwrap();
+        cache.insert_document("cpf", "01234567890", Some(customer("JOÃO")));
+        cache.insert_name("Maria", None);
+        cache.by_name.insert(
+            "old owner".to_string(),
+            CacheEntry {
+                result: Some(customer("OLD OWNER")),
+                cached_at: Utc::now() - chrono::Duration::days(31),
+            },
+        );
+        assert!(cache.get_name("Old Owner").is_none());
+        cache.save().unwrap();
+
+        let reloaded = EnrichmentCache::load(&path, ttl).unwrap();
+        assert_eq!(
+            reloaded
+                .get_document("cpf", "01234567890")
+                .unwrap()
+                .unwrap()
+                .base
+                .name,
+            "JOÃO"
+        );
+        assert!(matches!(reloaded.get_name("MARIA"), Some(None)));
+        assert!(!reloaded.by_name.contains_key("old owner"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,19 +1,177 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use actix_web::{
-    error::{ErrorBadGateway, ErrorBadRequest},
-    middleware::Logger,
-    web, App, HttpResponse, HttpServer, Responder,
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    error::{ErrorBadGateway, ErrorBadRequest, ErrorInternalServerError},
+    http::header::{HeaderName, HeaderValue, ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE},
+    middleware::{from_fn, Logger, Next},
+    web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer, Responder,
 };
 use anyhow::Result;
-use serde::Deserialize;
-use tracing::info;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tracing::{info, info_span, Instrument};
+use uuid::Uuid;
 
-use crate::diretrix_enrichment::{enrich_person, DiretrixClient, EnrichmentRequest};
+use crate::diretrix_enrichment::{
+    enrich_person, DiretrixClient, EnrichmentRequest, GetCustomerData,
+};
+use crate::export::{flatten_customer, CsvExporter, RecordExporter, CUSTOMER_COLUMNS};
+
+pub const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 60;
+pub const DEFAULT_KEEP_ALIVE_SECS: u64 = 75;
+
+/// Header carrying the correlation ID in both directions
+const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Longest inbound correlation ID honoured; longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Most items `/enrich/batch` accepts in one request
+const MAX_BATCH_SIZE: usize = 1000;
+/// Batch items enriched concurrently against Diretrix
+const BATCH_CONCURRENCY: usize = 8;
+
+/// Tuning for the enrichment HTTP server
+#[derive(Clone, Debug)]
+pub struct ServerOptions {
+    /// Worker threads; `None` means one per available CPU
+    pub workers: Option<usize>,
+    /// How long a client may take to send its request
+    pub request_timeout: Duration,
+    /// How long idle keep-alive connections stay open
+    pub keep_alive: Duration,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            workers: None,
+            request_timeout: Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS),
+            keep_alive: Duration::from_secs(DEFAULT_KEEP_ALIVE_SECS),
+        }
+    }
+}
+
+impl ServerOptions {
+    /// Worker count actually used, falling back to the available parallelism
+    pub fn effective_workers(&self) -> usize {
+        self.workers
+            .filter(|workers| *workers > 0)
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1)
+            })
+    }
+}
 
 #[derive(Clone)]
 struct AppState {
     client: Arc<DiretrixClient>,
+    /// Key `/enrich/*` callers must present (`ENRICHMENT_API_KEY`); open when unset
+    api_key: Option<Arc<str>>,
+}
+
+/// `ENRICHMENT_API_KEY`, ignoring an empty value
+fn api_key_from_env() -> Option<Arc<str>> {
+    std::env::var("ENRICHMENT_API_KEY")
+        .ok()
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .map(Arc::from)
+}
+
+/// Whether an `Authorization` header carries `expected`, as `Bearer <key>` or
+/// the bare key. Compares in constant time.
+fn authorized(header: Option<&str>, expected: &str) -> bool {
+    let Some(header) = header.map(str::trim) else {
+        return false;
+    };
+    let presented = match header.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => token.trim(),
+        _ => header,
+    };
+
+    let (presented, expected) = (presented.as_bytes(), expected.as_bytes());
+    presented.len() == expected.len()
+        && presented
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Correlation ID of the current request, stored in its extensions
+#[derive(Clone, Debug)]
+struct RequestId(String);
+
+/// Inbound `X-Request-Id` when it is a sane token, otherwise a fresh UUID
+fn request_id_from(header: Option<&str>) -> String {
+    header
+        .map(str::trim)
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.bytes().all(|b| b.is_ascii_graphic())
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Tags each request with a correlation ID: stored for handlers, attached to
+/// a tracing span around the request and echoed in the response headers
+async fn correlation_id(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let id = request_id_from(
+        request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok()),
+    );
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        path = %request.path()
+    );
+    let mut response = next.call(request).instrument(span).await?;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    Ok(response)
+}
+
+/// Rejects requests without the configured API key with 401
+async fn require_api_key(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let expected = request
+        .app_data::<web::Data<AppState>>()
+        .and_then(|state| state.api_key.clone());
+
+    if let Some(expected) = expected {
+        let header = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        if !authorized(header, &expected) {
+            let response = HttpResponse::Unauthorized()
+                .insert_header((WWW_AUTHENTICATE, "Bearer"))
+                .json(serde_json::json!({ "message": "Missing or invalid API key" }));
+            return Ok(request.into_response(response));
+        }
+    }
+
+    Ok(next.call(request).await?.map_into_boxed_body())
 }
 
 #[derive(Debug, Deserialize)]
@@ -35,7 +193,7 @@ impl EnrichmentPayload {
         let mut email: Option<String> = None;
         let mut phone: Option<String> = None;
 
-        for (ty, value) in self.search_types.into_iter().zip(self.searches.into_iter()) {
+        for (ty, value) in self.search_types.into_iter().zip(self.searches) {
             let trimmed = value.trim().to_string();
             if trimmed.is_empty() {
                 continue;
@@ -67,14 +225,384 @@ impl EnrichmentPayload {
     }
 }
 
+/// Outcome of one `/enrich/batch` item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum BatchItemStatus {
+    Found,
+    NotFound,
+    Invalid,
+    Error,
+}
+
+/// One element of the `/enrich/batch` response, in request order
+#[derive(Debug, Serialize)]
+struct BatchItemResult {
+    index: usize,
+    status: BatchItemStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<GetCustomerData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+impl BatchItemResult {
+    fn invalid(index: usize, err: actix_web::Error) -> Self {
+        Self {
+            index,
+            status: BatchItemStatus::Invalid,
+            data: None,
+            message: Some(err.to_string()),
+        }
+    }
+
+    fn from_outcome(index: usize, outcome: anyhow::Result<Option<GetCustomerData>>) -> Self {
+        let (status, data, message) = match outcome {
+            Ok(Some(data)) => (BatchItemStatus::Found, Some(data), None),
+            Ok(None) => (BatchItemStatus::NotFound, None, None),
+            Err(err) => (
+                BatchItemStatus::Error,
+                None,
+                Some(format!("Diretrix enrichment failed: {}", err)),
+            ),
+        };
+        Self {
+            index,
+            status,
+            data,
+            message,
+        }
+    }
+}
+
+/// OpenAPI description of the service. Hand-maintained here; the tests check the
+/// schemas against the serde shapes of `EnrichmentPayload` and `GetCustomerData`.
+fn openapi_document() -> serde_json::Value {
+    let nullable_string = serde_json::json!({ "type": "string", "nullable": true });
+    let nullable_integer =
+        serde_json::json!({ "type": "integer", "format": "int32", "nullable": true });
+    let message = serde_json::json!({
+        "type": "object",
+        "required": ["message"],
+        "properties": { "message": { "type": "string" } }
+    });
+
+    serde_json::json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "IPTU CLI enrichment service",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Enriches a person by CPF, name, email or phone using Diretrix."
+        },
+        "paths": {
+            "/enrich/person": {
+                "post": {
+                    "summary": "Enrich a person",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": { "$ref": "#/components/schemas/EnrichmentPayload" },
+                                "example": {
+                                    "search_types": ["cpf", "name"],
+                                    "searches": ["12345678901", "MARIA DOS SANTOS"]
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Best matching person. Send `Accept: text/csv` for a header plus one flattened row (base fields and the first email, phone and address).",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/GetCustomerData" }
+                                },
+                                "text/csv": {
+                                    "schema": { "type": "string" }
+                                }
+                            }
+                        },
+                        "400": {
+                            "description": "Invalid payload (mismatched lengths, unsupported or missing search types)"
+                        },
+                        "401": { "description": "ENRICHMENT_API_KEY is set and the Authorization header does not carry it" },
+                        "404": {
+                            "description": "No matching person",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/Message" }
+                                }
+                            }
+                        },
+                        "406": { "description": "Accept header allows neither application/json nor text/csv" },
+                        "502": { "description": "Diretrix request failed" }
+                    }
+                }
+            },
+            "/enrich/batch": {
+                "post": {
+                    "summary": "Enrich many people in one request",
+                    "description": "Each element is validated and enriched like a `/enrich/person` payload; failures are reported per item.",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "array",
+                                    "maxItems": MAX_BATCH_SIZE,
+                                    "items": { "$ref": "#/components/schemas/EnrichmentPayload" }
+                                },
+                                "example": [
+                                    { "search_types": ["cpf"], "searches": ["12345678901"] },
+                                    { "search_types": ["name", "email"], "searches": ["MARIA DOS SANTOS", "maria@example.com"] }
+                                ]
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "One result per request element, in request order",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "array",
+                                        "items": { "$ref": "#/components/schemas/BatchItemResult" }
+                                    }
+                                }
+                            }
+                        },
+                        "400": { "description": "Body is not an array of payloads or exceeds the batch size limit" },
+                        "401": { "description": "ENRICHMENT_API_KEY is set and the Authorization header does not carry it" }
+                    }
+                }
+            },
+            "/health": {
+                "get": {
+                    "summary": "Service health and the Diretrix backend currently in use",
+                    "responses": {
+                        "200": {
+                            "description": "Healthy",
+                            "content": {
+                                "application/json": {
+                                    "example": {
+                                        "status": "ok",
+                                        "active_backend": "https://www.diretrixconsultoria.com.br",
+                                        "backends": ["https://www.diretrixconsultoria.com.br"]
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "/openapi.json": {
+                "get": {
+                    "summary": "This document",
+                    "responses": { "200": { "description": "OpenAPI document" } }
+                }
+            }
+        },
+        "components": {
+            "schemas": {
+                "EnrichmentPayload": {
+                    "type": "object",
+                    "required": ["search_types", "searches"],
+                    "description": "Parallel arrays: search_types[i] describes searches[i].",
+                    "properties": {
+                        "search_types": {
+                            "type": "array",
+                            "items": {
+                                "type": "string",
+                                "enum": ["cpf", "name", "nome", "email", "phone", "telefone"]
+                            }
+                        },
+                        "searches": { "type": "array", "items": { "type": "string" } }
+                    }
+                },
+                "GetCustomerData": {
+                    "type": "object",
+                    "required": ["base", "emails", "phones", "addresses"],
+                    "properties": {
+                        "base": { "$ref": "#/components/schemas/CustomerBase" },
+                        "emails": { "type": "array", "items": { "$ref": "#/components/schemas/CustomerEmail" } },
+                        "phones": { "type": "array", "items": { "$ref": "#/components/schemas/CustomerPhone" } },
+                        "addresses": { "type": "array", "items": { "$ref": "#/components/schemas/CustomerAddress" } },
+                        "match_score": {
+                            "type": "number",
+                            "format": "double",
+                            "nullable": true,
+                            "description": "Name similarity (0-1) of a seed match; 1 for a CPF lookup, null when no name was compared"
+                        },
+                        "match_method": {
+                            "type": "string",
+                            "nullable": true,
+                            "enum": ["cpf", "cnpj", "email", "phone", "name"]
+                        }
+                    }
+                },
+                "CustomerBase": {
+                    "type": "object",
+                    "required": ["id", "name"],
+                    "properties": {
+                        "id": { "type": "string" },
+                        "name": { "type": "string" },
+                        "cpf": nullable_string,
+                        "birth_date": nullable_string,
+                        "sex": nullable_string,
+                        "mother_name": nullable_string,
+                        "father_name": nullable_string,
+                        "rg": nullable_string
+                    }
+                },
+                "CustomerEmail": {
+                    "type": "object",
+                    "required": ["email"],
+                    "properties": {
+                        "email": { "type": "string" },
+                        "ranking": nullable_integer
+                    }
+                },
+                "CustomerPhone": {
+                    "type": "object",
+                    "properties": {
+                        "ddd": nullable_string,
+                        "number": nullable_string,
+                        "operator_": nullable_string,
+                        "kind": nullable_string,
+                        "ranking": nullable_integer
+                    }
+                },
+                "CustomerAddress": {
+                    "type": "object",
+                    "properties": {
+                        "street": nullable_string,
+                        "number": nullable_string,
+                        "neighborhood": nullable_string,
+                        "city": nullable_string,
+                        "uf": nullable_string,
+                        "postal_code": nullable_string,
+                        "complement": nullable_string,
+                        "ranking": nullable_integer,
+                        "latitude": nullable_string,
+                        "longitude": nullable_string,
+                        "ddd": nullable_string,
+                        "street_type": nullable_string
+                    }
+                },
+                "BatchItemResult": {
+                    "type": "object",
+                    "required": ["index", "status"],
+                    "properties": {
+                        "index": { "type": "integer", "description": "Position in the request array" },
+                        "status": { "type": "string", "enum": ["found", "not_found", "invalid", "error"] },
+                        "data": { "$ref": "#/components/schemas/GetCustomerData" },
+                        "message": { "type": "string", "description": "Why the item is invalid or failed" }
+                    }
+                },
+                "Message": message
+            }
+        }
+    })
+}
+
+async fn openapi_handler() -> impl Responder {
+    HttpResponse::Ok().json(openapi_document())
+}
+
+async fn health_handler(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "ok",
+        "active_backend": state.client.active_base_url(),
+        "backends": state.client.base_urls(),
+    }))
+}
+
+/// Response representations `/enrich/person` can produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResponseFormat {
+    Json,
+    Csv,
+}
+
+/// Pick a representation from the `Accept` header, honouring q-values.
+/// A missing or empty header means JSON; `None` means nothing acceptable (406).
+fn negotiate(accept: Option<&str>) -> Option<ResponseFormat> {
+    let accept = match accept.map(str::trim) {
+        Some(value) if !value.is_empty() => value,
+        _ => return Some(ResponseFormat::Json),
+    };
+
+    let mut ranges: Vec<(f32, String)> = accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let media_type = parts.next()?.trim().to_lowercase();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (quality > 0.0).then_some((quality, media_type))
+        })
+        .collect();
+    ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    ranges
+        .iter()
+        .find_map(|(_, media_type)| match media_type.as_str() {
+            "application/json" | "application/*" | "*/*" => Some(ResponseFormat::Json),
+            "text/csv" | "text/*" => Some(ResponseFormat::Csv),
+            _ => None,
+        })
+}
+
+fn customer_csv(data: &GetCustomerData) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let mut exporter = Box::new(CsvExporter::new(&mut buffer));
+    exporter.write_header(&CUSTOMER_COLUMNS)?;
+    let row = flatten_customer(data);
+    exporter.write_row(&row.iter().map(String::as_str).collect::<Vec<_>>())?;
+    exporter.finish()?;
+    Ok(buffer)
+}
+
 async fn enrich_handler(
     state: web::Data<AppState>,
+    http_request: HttpRequest,
     payload: web::Json<EnrichmentPayload>,
 ) -> Result<impl Responder, actix_web::Error> {
+    let accept = http_request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok());
+    let format = match negotiate(accept) {
+        Some(format) => format,
+        None => {
+            return Ok(HttpResponse::NotAcceptable().json(serde_json::json!({
+                "message": "Supported response types: application/json, text/csv"
+            })))
+        }
+    };
+
     let request = payload.into_inner().into_request()?;
+    let request_id = http_request
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.clone())
+        .unwrap_or_default();
 
-    match enrich_person(&state.client, request).await {
-        Ok(Some(result)) => Ok(HttpResponse::Ok().json(result)),
+    match enrich_person(&state.client, request)
+        .instrument(info_span!("enrich_person", request_id = %request_id))
+        .await
+    {
+        Ok(Some(result)) => match format {
+            ResponseFormat::Json => Ok(HttpResponse::Ok().json(result)),
+            ResponseFormat::Csv => {
+                let body = customer_csv(&result).map_err(ErrorInternalServerError)?;
+                Ok(HttpResponse::Ok()
+                    .content_type("text/csv; charset=utf-8")
+                    .body(body))
+            }
+        },
         Ok(None) => {
             Ok(HttpResponse::NotFound().json(serde_json::json!({ "message": "Not found" })))
         }
@@ -85,23 +613,384 @@ async fn enrich_handler(
     }
 }
 
-pub async fn run_enrichment_server(addr: &str) -> Result<()> {
+async fn enrich_batch_handler(
+    state: web::Data<AppState>,
+    http_request: HttpRequest,
+    payload: web::Json<Vec<EnrichmentPayload>>,
+) -> Result<impl Responder, actix_web::Error> {
+    let items = payload.into_inner();
+    if items.len() > MAX_BATCH_SIZE {
+        return Err(ErrorBadRequest(format!(
+            "Batch of {} items exceeds the limit of {}",
+            items.len(),
+            MAX_BATCH_SIZE
+        )));
+    }
+
+    let client = state.client.clone();
+    let request_id = http_request
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.clone())
+        .unwrap_or_default();
+    let results: Vec<BatchItemResult> = futures::stream::iter(items.into_iter().enumerate())
+        .map(|(index, item)| {
+            let client = client.clone();
+            let span = info_span!("enrich_person", request_id = %request_id, index);
+            async move {
+                match item.into_request() {
+                    Ok(request) => {
+                        BatchItemResult::from_outcome(index, enrich_person(&client, request).await)
+                    }
+                    Err(err) => BatchItemResult::invalid(index, err),
+                }
+            }
+            .instrument(span)
+        })
+        .buffered(BATCH_CONCURRENCY)
+        .collect()
+        .await;
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+pub async fn run_enrichment_server(addr: &str, options: ServerOptions) -> Result<()> {
     let client = DiretrixClient::from_env()?;
     let state = AppState {
         client: Arc::new(client),
+        api_key: api_key_from_env(),
     };
 
-    info!("Starting enrichment service on {}", addr);
+    info!(
+        "Starting enrichment service on {} (Diretrix backends: {}, name match: {} >= {})",
+        addr,
+        state.client.base_urls().join(", "),
+        state.client.name_strategy().as_str(),
+        state.client.name_threshold()
+    );
+    if state.api_key.is_some() {
+        info!("API key required for /enrich/* (ENRICHMENT_API_KEY)");
+    } else {
+        info!("ENRICHMENT_API_KEY not set; /enrich/* is open");
+    }
+    let workers = options.effective_workers();
+    info!(
+        "Server settings: {} workers, request timeout {}s, keep-alive {}s",
+        workers,
+        options.request_timeout.as_secs(),
+        options.keep_alive.as_secs()
+    );
 
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(state.clone()))
-            .wrap(Logger::default())
-            .route("/enrich/person", web::post().to(enrich_handler))
+            .wrap(from_fn(correlation_id))
+            .wrap(Logger::new(
+                r#"%a "%r" %s %b %Dms request_id=%{x-request-id}o"#,
+            ))
+            .service(
+                web::scope("/enrich")
+                    .wrap(from_fn(require_api_key))
+                    .route("/person", web::post().to(enrich_handler))
+                    .route("/batch", web::post().to(enrich_batch_handler)),
+            )
+            .route("/openapi.json", web::get().to(openapi_handler))
+            .route("/health", web::get().to(health_handler))
     })
+    .workers(workers)
+    .client_request_timeout(options.request_timeout)
+    .keep_alive(options.keep_alive)
     .bind(addr)?
     .run()
     .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diretrix_enrichment::{
+        CustomerAddress, CustomerBase, CustomerEmail, CustomerPhone, GetCustomerData, MatchMethod,
+    };
+    use serde::Serialize;
+    use std::collections::BTreeSet;
+
+    fn schema_properties(name: &str) -> BTreeSet<String> {
+        openapi_document()["components"]["schemas"][name]["properties"]
+            .as_object()
+            .unwrap_or_else(|| panic!("schema {} has no properties", name))
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn serialized_keys<T: Serialize>(value: &T) -> BTreeSet<String> {
+        serde_json::to_value(value)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_server_options_workers() {
+        let options = ServerOptions {
+            workers: Some(3),
+            ..ServerOptions::default()
+        };
+        assert_eq!(options.effective_workers(), 3);
+
+        let defaults = ServerOptions::default();
+        assert!(defaults.effective_workers() >= 1);
+        let zero = ServerOptions {
+            workers: Some(0),
+            ..ServerOptions::default()
+        };
+        assert_eq!(zero.effective_workers(), defaults.effective_workers());
+    }
+
+    #[test]
+    fn test_openapi_schemas_match_serde_types() {
+        let base = CustomerBase {
+            id: String::new(),
+            name: String::new(),
+            cpf: None,
+            birth_date: None,
+            sex: None,
+            mother_name: None,
+            father_name: None,
+            rg: None,
+        };
+        let email = CustomerEmail {
+            email: String::new(),
+            ranking: None,
+        };
+        let phone = CustomerPhone {
+            ddd: None,
+            number: None,
+            operator_: None,
+            kind: None,
+            ranking: None,
+        };
+        let address = CustomerAddress {
+            street: None,
+            number: None,
+            neighborhood: None,
+            city: None,
+            uf: None,
+            postal_code: None,
+            complement: None,
+            ranking: None,
+            latitude: None,
+            longitude: None,
+            ddd: None,
+            street_type: None,
+        };
+
+        assert_eq!(schema_properties("CustomerBase"), serialized_keys(&base));
+        assert_eq!(schema_properties("CustomerEmail"), serialized_keys(&email));
+        assert_eq!(schema_properties("CustomerPhone"), serialized_keys(&phone));
+        assert_eq!(
+            schema_properties("CustomerAddress"),
+            serialized_keys(&address)
+        );
+
+        let data = GetCustomerData {
+            base,
+            emails: vec![email],
+            phones: vec![phone],
+            addresses: vec![address],
+            match_score: Some(1.0),
+            match_method: Some(MatchMethod::Cpf),
+        };
+        assert_eq!(schema_properties("GetCustomerData"), serialized_keys(&data));
+
+        let item = BatchItemResult {
+            index: 0,
+            status: BatchItemStatus::Found,
+            data: Some(data),
+            message: Some(String::new()),
+        };
+        assert_eq!(schema_properties("BatchItemResult"), serialized_keys(&item));
+    }
+
+    #[test]
+    fn test_batch_item_results() {
+        let invalid = EnrichmentPayload {
+            search_types: vec!["cpf".to_string()],
+            searches: vec![],
+        }
+        .into_request()
+        .unwrap_err();
+        let item = serde_json::to_value(BatchItemResult::invalid(2, invalid)).unwrap();
+        assert_eq!(item["index"], 2);
+        assert_eq!(item["status"], "invalid");
+        assert!(item["message"].as_str().unwrap().contains("same length"));
+        assert!(item.get("data").is_none());
+
+        let not_found = serde_json::to_value(BatchItemResult::from_outcome(0, Ok(None))).unwrap();
+        assert_eq!(
+            not_found,
+            serde_json::json!({ "index": 0, "status": "not_found" })
+        );
+
+        let failed = BatchItemResult::from_outcome(1, Err(anyhow::anyhow!("connection reset")));
+        assert_eq!(failed.status, BatchItemStatus::Error);
+        assert!(failed.message.unwrap().contains("connection reset"));
+    }
+
+    #[test]
+    fn test_openapi_batch_example_is_valid_payload() {
+        let example = openapi_document()["paths"]["/enrich/batch"]["post"]["requestBody"]
+            ["content"]["application/json"]["example"]
+            .clone();
+        let payloads: Vec<EnrichmentPayload> = serde_json::from_value(example).unwrap();
+        assert!(payloads
+            .into_iter()
+            .all(|payload| payload.into_request().is_ok()));
+    }
+
+    #[test]
+    fn test_request_id_from() {
+        assert_eq!(request_id_from(Some(" abc-123 ")), "abc-123");
+        for rejected in [None, Some(""), Some("has space"), Some("ção")] {
+            let generated = request_id_from(rejected);
+            assert!(Uuid::parse_str(&generated).is_ok(), "{:?}", rejected);
+        }
+        let too_long = "x".repeat(MAX_REQUEST_ID_LEN + 1);
+        assert_ne!(request_id_from(Some(&too_long)), too_long);
+    }
+
+    #[actix_web::test]
+    async fn test_correlation_id_is_echoed() {
+        use actix_web::test;
+
+        async fn echo(request: HttpRequest) -> HttpResponse {
+            let id = request.extensions().get::<RequestId>().unwrap().0.clone();
+            HttpResponse::Ok().body(id)
+        }
+
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(correlation_id))
+                .route("/", web::get().to(echo)),
+        )
+        .await;
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/")
+                .insert_header((REQUEST_ID_HEADER, "trace-42"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(
+            response.headers().get(REQUEST_ID_HEADER).unwrap(),
+            "trace-42"
+        );
+        assert_eq!(test::read_body(response).await, "trace-42");
+
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        let generated = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(Uuid::parse_str(generated).is_ok());
+    }
+
+    #[test]
+    fn test_authorized() {
+        assert!(authorized(Some("Bearer s3cret"), "s3cret"));
+        assert!(authorized(Some("bearer  s3cret "), "s3cret"));
+        assert!(authorized(Some("s3cret"), "s3cret"));
+        assert!(!authorized(Some("Bearer s3cre"), "s3cret"));
+        assert!(!authorized(Some("Bearer other"), "s3cret"));
+        assert!(!authorized(Some("Basic s3cret"), "s3cret"));
+        assert!(!authorized(None, "s3cret"));
+    }
+
+    #[actix_web::test]
+    async fn test_api_key_middleware() {
+        use actix_web::{http::StatusCode, test};
+
+        async fn ok() -> HttpResponse {
+            HttpResponse::Ok().finish()
+        }
+
+        let build = |api_key: Option<&str>| {
+            let state = AppState {
+                client: Arc::new(DiretrixClient::for_tests()),
+                api_key: api_key.map(Arc::from),
+            };
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(
+                    web::scope("/enrich")
+                        .wrap(from_fn(require_api_key))
+                        .route("/person", web::post().to(ok)),
+                )
+                .route("/health", web::get().to(ok))
+        };
+
+        let app = test::init_service(build(Some("s3cret"))).await;
+        let denied = test::call_service(
+            &app,
+            test::TestRequest::post().uri("/enrich/person").to_request(),
+        )
+        .await;
+        assert_eq!(denied.status(), StatusCode::UNAUTHORIZED);
+        let allowed = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/enrich/person")
+                .insert_header((AUTHORIZATION, "Bearer s3cret"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(allowed.status(), StatusCode::OK);
+        let health =
+            test::call_service(&app, test::TestRequest::get().uri("/health").to_request()).await;
+        assert_eq!(health.status(), StatusCode::OK);
+
+        let open = test::init_service(build(None)).await;
+        let response = test::call_service(
+            &open,
+            test::TestRequest::post().uri("/enrich/person").to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_negotiate_accept_header() {
+        assert_eq!(negotiate(None), Some(ResponseFormat::Json));
+        assert_eq!(negotiate(Some("")), Some(ResponseFormat::Json));
+        assert_eq!(negotiate(Some("*/*")), Some(ResponseFormat::Json));
+        assert_eq!(negotiate(Some("text/csv")), Some(ResponseFormat::Csv));
+        assert_eq!(
+            negotiate(Some("application/json;q=0.5, text/csv")),
+            Some(ResponseFormat::Csv)
+        );
+        assert_eq!(
+            negotiate(Some("text/csv;q=0, application/json")),
+            Some(ResponseFormat::Json)
+        );
+        assert_eq!(negotiate(Some("application/xml")), None);
+    }
+
+    #[test]
+    fn test_openapi_request_example_is_valid_payload() {
+        let example = openapi_document()["paths"]["/enrich/person"]["post"]["requestBody"]
+            ["content"]["application/json"]["example"]
+            .clone();
+        let payload: EnrichmentPayload = serde_json::from_value(example).unwrap();
+        assert!(payload.into_request().is_ok());
+    }
+}
@@ -0,0 +1,76 @@
+use regex::Regex;
+use thirtyfour::error::WebDriverError;
+use thiserror::Error;
+
+/// Errors with a specific, actionable message for the user
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error(
+        "ChromeDriver only supports Chrome {supported}, but the installed Chrome is {}. \
+         Download the ChromeDriver matching your Chrome version from \
+         https://googlechromelabs.github.io/chrome-for-testing/ (or update Chrome to {supported}), \
+         then restart it (start.chromedriver.sh) so the new binary is used",
+        installed.as_deref().unwrap_or("a different version")
+    )]
+    DriverVersionMismatch {
+        supported: String,
+        installed: Option<String>,
+    },
+}
+
+/// Recognise chromedriver's "This version of ChromeDriver only supports Chrome
+/// version N" session error
+pub fn driver_version_mismatch(message: &str) -> Option<AppError> {
+    let supported = Regex::new(r"only supports Chrome version (\d+)")
+        .expect("static regex is valid")
+        .captures(message)?
+        .get(1)?
+        .as_str()
+        .to_string();
+    let installed = Regex::new(r"Current browser version is ([\d.]+)")
+        .expect("static regex is valid")
+        .captures(message)
+        .and_then(|captures| captures.get(1))
+        .map(|version| version.as_str().to_string());
+
+    Some(AppError::DriverVersionMismatch {
+        supported,
+        installed,
+    })
+}
+
+/// Error for a failed `WebDriver::new`, turning a version mismatch into
+/// `AppError::DriverVersionMismatch`
+pub fn webdriver_start_error(err: WebDriverError) -> anyhow::Error {
+    match driver_version_mismatch(&err.to_string()) {
+        Some(mismatch) => mismatch.into(),
+        None => anyhow::Error::new(err).context("Failed to connect to WebDriver"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_version_mismatch() {
+        let message = "session not created: This version of ChromeDriver only supports Chrome version 114\n\
+                       Current browser version is 120.0.6099.109 with binary path /usr/bin/google-chrome";
+
+        let err = driver_version_mismatch(message).expect("mismatch should be detected");
+        let AppError::DriverVersionMismatch {
+            supported,
+            installed,
+        } = &err;
+        assert_eq!(supported, "114");
+        assert_eq!(installed.as_deref(), Some("120.0.6099.109"));
+        assert!(err.to_string().contains("Chrome 114"));
+        assert!(err.to_string().contains("120.0.6099.109"));
+    }
+
+    #[test]
+    fn test_other_errors_are_not_mismatches() {
+        assert!(driver_version_mismatch("connection refused").is_none());
+        assert!(driver_version_mismatch("session not created: Chrome failed to start").is_none());
+    }
+}
@@ -0,0 +1,1309 @@
+use crate::dbase_scraper::AddressRecord;
+use crate::diretrix_enrichment::{normalize, GetCustomerData};
+use crate::diretrix_scraper::PropertyRecord;
+use anyhow::{bail, Context, Result};
+use rust_xlsxwriter::{Format, Workbook, Worksheet};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Cursor, Seek, Write};
+use std::path::Path;
+
+/// Output format shared by every export path (`--format` flags)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+    /// Newline-delimited JSON, one object per line (also `ndjson`)
+    #[value(alias = "ndjson")]
+    Jsonl,
+    /// Excel workbook with a single sheet; every cell is text
+    Xlsx,
+}
+
+impl ExportFormat {
+    /// File extension used when generating default output names
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// Character encoding of CSV output (`--csv-encoding`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum CsvEncoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1 for legacy systems; characters it cannot represent become `?`
+    Latin1,
+}
+
+impl CsvEncoding {
+    /// Encode a single field for output
+    fn encode<'a>(&self, field: &'a str) -> Cow<'a, [u8]> {
+        match self {
+            CsvEncoding::Utf8 => Cow::Borrowed(field.as_bytes()),
+            CsvEncoding::Latin1 => Cow::Owned(encode_latin1(field)),
+        }
+    }
+
+    /// Turn the bytes of an input file into UTF-8
+    fn decode(&self, bytes: Vec<u8>) -> Vec<u8> {
+        match self {
+            CsvEncoding::Utf8 => bytes,
+            CsvEncoding::Latin1 => decode_latin1(&bytes).into_bytes(),
+        }
+    }
+}
+
+/// CSV dialect of an export or input file (`--csv-delimiter`, `--csv-encoding`
+/// and their `--input-*` counterparts); the default is comma-separated UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvExportOptions {
+    pub delimiter: u8,
+    pub encoding: CsvEncoding,
+}
+
+impl Default for CsvExportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            encoding: CsvEncoding::default(),
+        }
+    }
+}
+
+/// Parse `--csv-delimiter`: a single ASCII character or one of `comma`,
+/// `semicolon`, `tab` and `pipe`
+pub fn parse_csv_delimiter(value: &str) -> std::result::Result<u8, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "comma" => return Ok(b','),
+        "semicolon" => return Ok(b';'),
+        "tab" | "\\t" => return Ok(b'\t'),
+        "pipe" => return Ok(b'|'),
+        _ => {}
+    }
+    match value.as_bytes() {
+        [byte] if byte.is_ascii() && !matches!(byte, b'"' | b'\n' | b'\r') => Ok(*byte),
+        _ => Err(format!(
+            "invalid delimiter '{}': use a single ASCII character or comma, semicolon, tab, pipe",
+            value
+        )),
+    }
+}
+
+/// Transcode to Latin-1 (ISO-8859-1): characters up to U+00FF become the byte of
+/// the same value, every other character becomes `?`
+fn encode_latin1(field: &str) -> Vec<u8> {
+    field
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect()
+}
+
+/// Decode Latin-1 (ISO-8859-1): every byte is the character of the same value
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| char::from(byte)).collect()
+}
+
+/// CSV reader over `path` in the `options` dialect; Latin-1 input is decoded to
+/// UTF-8 up front
+fn open_csv(
+    path: &Path,
+    builder: &mut csv::ReaderBuilder,
+    options: CsvExportOptions,
+) -> Result<csv::Reader<Cursor<Vec<u8>>>> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+    Ok(builder
+        .delimiter(options.delimiter)
+        .from_reader(Cursor::new(options.encoding.decode(bytes))))
+}
+
+/// Writes flat, column-oriented records to some output format
+pub trait RecordExporter {
+    /// Write the column names. Must be called once, before any row.
+    fn write_header(&mut self, columns: &[&str]) -> Result<()>;
+    /// Write a single row; values are matched to the header by position.
+    fn write_row(&mut self, values: &[&str]) -> Result<()>;
+    /// Flush and close the output
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+/// Build the exporter for `format`, writing to `path`.
+/// With `append`, rows are added to the end of an existing file instead of replacing it
+/// (CSV and JSONL only); an existing CSV header must match the one being written.
+/// `csv` only applies to CSV; JSON output is always UTF-8.
+pub fn exporter_for(
+    format: ExportFormat,
+    path: &str,
+    append: bool,
+    csv: CsvExportOptions,
+) -> Result<Box<dyn RecordExporter>> {
+    if !append {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create file: {}", path))?;
+
+        return Ok(match format {
+            ExportFormat::Csv => Box::new(CsvExporter::configured(file, None, csv)),
+            ExportFormat::Json => Box::new(JsonExporter::new(file)),
+            ExportFormat::Jsonl => Box::new(JsonlExporter::new(file)),
+            ExportFormat::Xlsx => Box::new(XlsxExporter::new(file)),
+        });
+    }
+
+    if matches!(format, ExportFormat::Json | ExportFormat::Xlsx) {
+        bail!(
+            "--append is not supported for {} exports; use csv or jsonl",
+            format.extension()
+        );
+    }
+
+    let existing_header = match format {
+        ExportFormat::Csv => read_csv_header(Path::new(path), csv.delimiter)?,
+        _ => None,
+    };
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open file for appending: {}", path))?;
+
+    Ok(match format {
+        ExportFormat::Csv => Box::new(CsvExporter::configured(file, existing_header, csv)),
+        _ => Box::new(JsonlExporter::new(file)),
+    })
+}
+
+/// Header of an existing CSV file, or `None` if the file is missing or empty
+fn read_csv_header(path: &Path, delimiter: u8) -> Result<Option<Vec<String>>> {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.len() > 0 => {}
+        _ => return Ok(None),
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_path(path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let header = reader
+        .headers()
+        .with_context(|| format!("Failed to read CSV header of {}", path.display()))?;
+    Ok(Some(
+        header.iter().map(|column| column.to_string()).collect(),
+    ))
+}
+
+/// CSV exporter backed by `csv::Writer`
+pub struct CsvExporter<W: Write> {
+    writer: csv::Writer<W>,
+    /// Header already present in the output when appending; it is validated
+    /// instead of being written again
+    existing_header: Option<Vec<String>>,
+    encoding: CsvEncoding,
+}
+
+impl<W: Write> CsvExporter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::configured(inner, None, CsvExportOptions::default())
+    }
+
+    /// Exporter writing the `options` dialect. When appending, `existing_header`
+    /// is the header already at the start of the output.
+    pub fn configured(
+        inner: W,
+        existing_header: Option<Vec<String>>,
+        options: CsvExportOptions,
+    ) -> Self {
+        Self {
+            writer: csv::WriterBuilder::new()
+                .delimiter(options.delimiter)
+                .from_writer(inner),
+            existing_header,
+            encoding: options.encoding,
+        }
+    }
+
+    fn write_fields(&mut self, fields: &[&str]) -> Result<()> {
+        let encoding = self.encoding;
+        self.writer
+            .write_record(fields.iter().map(|field| encoding.encode(field)))?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordExporter for CsvExporter<W> {
+    fn write_header(&mut self, columns: &[&str]) -> Result<()> {
+        match self.existing_header.take() {
+            Some(existing) => {
+                if existing != columns {
+                    bail!(
+                        "Existing CSV header does not match: found [{}], expected [{}]",
+                        existing.join(","),
+                        columns.join(",")
+                    );
+                }
+            }
+            None => self.write_fields(columns)?,
+        }
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[&str]) -> Result<()> {
+        self.write_fields(values)
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Exporter that writes `EXPORT_SCHEMA_VERSION` as a leading `schema_version` column
+struct SchemaVersioned<'a> {
+    inner: Box<dyn RecordExporter + 'a>,
+    version: String,
+}
+
+impl RecordExporter for SchemaVersioned<'_> {
+    fn write_header(&mut self, columns: &[&str]) -> Result<()> {
+        let mut header = vec![SCHEMA_VERSION_COLUMN];
+        header.extend_from_slice(columns);
+        self.inner.write_header(&header)
+    }
+
+    fn write_row(&mut self, values: &[&str]) -> Result<()> {
+        let mut row = vec![self.version.as_str()];
+        row.extend_from_slice(values);
+        self.inner.write_row(&row)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// With `enabled` (`--schema-version`), lead every row of `exporter` with the
+/// schema version so loaders can tell column layouts apart
+pub fn with_schema_version<'a>(
+    exporter: Box<dyn RecordExporter + 'a>,
+    enabled: bool,
+) -> Box<dyn RecordExporter + 'a> {
+    if !enabled {
+        return exporter;
+    }
+    Box::new(SchemaVersioned {
+        inner: exporter,
+        version: EXPORT_SCHEMA_VERSION.to_string(),
+    })
+}
+
+// Column order of every exported record type. Consumers load these files by
+// position, so columns are only ever appended, and each change must update the
+// exact-header tests below and bump `EXPORT_SCHEMA_VERSION`.
+
+/// Version of the column layouts below, written by `--schema-version`
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+/// Name of the leading column holding `EXPORT_SCHEMA_VERSION`
+pub const SCHEMA_VERSION_COLUMN: &str = "schema_version";
+
+/// Columns of a Diretrix property export, produced by `diretrix_row`
+pub const DIRETRIX_COLUMNS: [&str; 10] = [
+    "Owner",
+    "IPTU",
+    "Street",
+    "Number",
+    "Complement",
+    "Complement 2",
+    "Neighborhood",
+    "Document 1",
+    "Document 2",
+    "EnrichmentJSON",
+];
+
+/// Flatten a Diretrix property and its enrichment (as JSON) into a row
+/// matching `DIRETRIX_COLUMNS`
+pub fn diretrix_row(record: &PropertyRecord, enrichment: Option<&GetCustomerData>) -> Vec<String> {
+    let enrichment_json = enrichment
+        .and_then(|data| serde_json::to_string(data).ok())
+        .unwrap_or_default();
+
+    vec![
+        record.owner.clone(),
+        record.iptu.clone(),
+        record.street.clone(),
+        record.number.clone(),
+        record.complement.clone(),
+        record.complement2.clone(),
+        record.neighborhood.clone(),
+        record.document1.clone().unwrap_or_default(),
+        record.document2.clone().unwrap_or_default(),
+        enrichment_json,
+    ]
+}
+
+/// Read the properties back from a Diretrix CSV export in the `options` dialect,
+/// matching columns by their `DIRETRIX_COLUMNS` names. `Owner` and `IPTU` are
+/// required; other missing columns are left empty, extra ones are ignored.
+pub fn read_diretrix_csv(path: &Path, options: CsvExportOptions) -> Result<Vec<PropertyRecord>> {
+    let mut reader = open_csv(path, &mut csv::ReaderBuilder::new(), options)?;
+    let header = reader
+        .headers()
+        .with_context(|| format!("Failed to read CSV header of {}", path.display()))?
+        .clone();
+    let position = |column: &str| header.iter().position(|name| name.trim() == column);
+
+    for required in ["Owner", "IPTU"] {
+        if position(required).is_none() {
+            bail!(
+                "{} is not a Diretrix export: missing column '{}'",
+                path.display(),
+                required
+            );
+        }
+    }
+    let columns = DIRETRIX_COLUMNS.map(position);
+
+    let mut records = Vec::new();
+    for row in reader.records() {
+        let row = row.with_context(|| format!("Invalid CSV row in {}", path.display()))?;
+        let field = |index: usize| -> String {
+            columns[index]
+                .and_then(|position| row.get(position))
+                .unwrap_or("")
+                .trim()
+                .to_string()
+        };
+        let document = |index: usize| Some(field(index)).filter(|value| !value.is_empty());
+
+        records.push(PropertyRecord {
+            owner: field(0),
+            iptu: field(1),
+            street: field(2),
+            number: field(3),
+            complement: field(4),
+            complement2: field(5),
+            neighborhood: field(6),
+            document1: document(7),
+            document2: document(8),
+        });
+    }
+    Ok(records)
+}
+
+/// Header names (accent-stripped, lowercased) recognised as the document column
+/// of an `enrich` input file
+const ENRICH_DOCUMENT_HEADERS: [&str; 6] = [
+    "cpf",
+    "cnpj",
+    "cpf_cnpj",
+    "cpf/cnpj",
+    "documento",
+    "document",
+];
+
+/// Header names recognised as the person name column of an `enrich` input file
+const ENRICH_NAME_HEADERS: [&str; 7] = [
+    "nome",
+    "name",
+    "owner",
+    "proprietario",
+    "nome_razao_social",
+    "razao social",
+    "razao_social",
+];
+
+/// Header names recognised as the email column of an `enrich` input file
+const ENRICH_EMAIL_HEADERS: [&str; 2] = ["email", "e-mail"];
+
+/// Header names recognised as the phone column of an `enrich` input file
+const ENRICH_PHONE_HEADERS: [&str; 4] = ["telefone", "phone", "celular", "fone"];
+
+/// People to enrich read from a CSV (`enrich --file`): every original column
+/// is kept, and the CPF/CNPJ, name, email and phone columns are located by
+/// header name
+#[derive(Debug)]
+pub struct EnrichmentInput {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    document_column: Option<usize>,
+    name_column: Option<usize>,
+    email_column: Option<usize>,
+    phone_column: Option<usize>,
+}
+
+impl EnrichmentInput {
+    /// The document (CPF or CNPJ) of a row, if the file has one and it is set
+    pub fn document<'a>(&self, row: &'a [String]) -> Option<&'a str> {
+        self.field(row, self.document_column)
+    }
+
+    /// The person name of a row, if the file has one and it is set
+    pub fn name<'a>(&self, row: &'a [String]) -> Option<&'a str> {
+        self.field(row, self.name_column)
+    }
+
+    /// The email of a row, if the file has one and it is set
+    pub fn email<'a>(&self, row: &'a [String]) -> Option<&'a str> {
+        self.field(row, self.email_column)
+    }
+
+    /// The phone of a row, if the file has one and it is set
+    pub fn phone<'a>(&self, row: &'a [String]) -> Option<&'a str> {
+        self.field(row, self.phone_column)
+    }
+
+    fn field<'a>(&self, row: &'a [String], column: Option<usize>) -> Option<&'a str> {
+        column
+            .and_then(|index| row.get(index))
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// The input columns followed by `CUSTOMER_COLUMNS`, prefixed with
+    /// `enriched_` so they never clash with an input column
+    pub fn output_columns(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .cloned()
+            .chain(
+                CUSTOMER_COLUMNS
+                    .iter()
+                    .map(|column| format!("enriched_{}", column)),
+            )
+            .collect()
+    }
+
+    /// A row of the input followed by its flattened enrichment (empty when
+    /// nothing was found), matching `output_columns`
+    pub fn enriched_row(&self, row: &[String], data: Option<&GetCustomerData>) -> Vec<String> {
+        let mut values = row.to_vec();
+        values.resize(self.columns.len(), String::new());
+        match data {
+            Some(data) => values.extend(flatten_customer(data)),
+            None => values.extend(CUSTOMER_COLUMNS.iter().map(|_| String::new())),
+        }
+        values
+    }
+}
+
+/// Read a CSV of people to enrich in the `options` dialect; it needs at least
+/// one CPF/CNPJ, name, email or phone column (see the `ENRICH_*_HEADERS` lists)
+pub fn read_enrichment_input(path: &Path, options: CsvExportOptions) -> Result<EnrichmentInput> {
+    let mut reader = open_csv(path, csv::ReaderBuilder::new().flexible(true), options)?;
+    let columns: Vec<String> = reader
+        .headers()
+        .with_context(|| format!("Failed to read CSV header of {}", path.display()))?
+        .iter()
+        .map(|column| column.to_string())
+        .collect();
+
+    let position = |names: &[&str]| {
+        columns.iter().position(|column| {
+            normalize(column).is_some_and(|column| names.contains(&column.as_str()))
+        })
+    };
+    let document_column = position(&ENRICH_DOCUMENT_HEADERS);
+    let name_column = position(&ENRICH_NAME_HEADERS);
+    let email_column = position(&ENRICH_EMAIL_HEADERS);
+    let phone_column = position(&ENRICH_PHONE_HEADERS);
+    if document_column.is_none()
+        && name_column.is_none()
+        && email_column.is_none()
+        && phone_column.is_none()
+    {
+        bail!(
+            "{} has no column to search by: expected a CPF/CNPJ ({}), name ({}), email ({}) or phone ({}) column",
+            path.display(),
+            ENRICH_DOCUMENT_HEADERS.join(", "),
+            ENRICH_NAME_HEADERS.join(", "),
+            ENRICH_EMAIL_HEADERS.join(", "),
+            ENRICH_PHONE_HEADERS.join(", ")
+        );
+    }
+
+    let mut rows = Vec::new();
+    for row in reader.records() {
+        let row = row.with_context(|| format!("Invalid CSV row in {}", path.display()))?;
+        rows.push(row.iter().map(|value| value.to_string()).collect());
+    }
+
+    Ok(EnrichmentInput {
+        columns,
+        rows,
+        document_column,
+        name_column,
+        email_column,
+        phone_column,
+    })
+}
+
+/// Columns of a Dbase address export, produced by `address_row`
+pub const ADDRESS_COLUMNS: [&str; 7] = [
+    "cpf_cnpj",
+    "nome_razao_social",
+    "logradouro",
+    "numero",
+    "complemento",
+    "bairro",
+    "cep",
+];
+
+/// Flatten a Dbase address into a row matching `ADDRESS_COLUMNS`
+pub fn address_row(record: &AddressRecord) -> Vec<String> {
+    vec![
+        record.cpf_cnpj.clone(),
+        record.nome_razao_social.clone(),
+        record.logradouro.clone(),
+        record.numero.clone(),
+        record.complemento.clone(),
+        record.bairro.clone(),
+        record.cep.clone(),
+    ]
+}
+
+/// Columns produced by `flatten_customer`
+pub const CUSTOMER_COLUMNS: [&str; 21] = [
+    "id",
+    "name",
+    "cpf",
+    "birth_date",
+    "sex",
+    "mother_name",
+    "father_name",
+    "rg",
+    "email",
+    "phone_ddd",
+    "phone_number",
+    "phone_kind",
+    "address_street",
+    "address_number",
+    "address_complement",
+    "address_neighborhood",
+    "address_city",
+    "address_uf",
+    "address_postal_code",
+    "match_score",
+    "match_method",
+];
+
+/// Flatten an enrichment result into a single row matching `CUSTOMER_COLUMNS`:
+/// the base fields plus the first email, phone and address, and how it matched
+pub fn flatten_customer(data: &GetCustomerData) -> Vec<String> {
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    let base = &data.base;
+    let email = data.emails.first();
+    let phone = data.phones.first();
+    let address = data.addresses.first();
+
+    vec![
+        base.id.clone(),
+        base.name.clone(),
+        text(&base.cpf),
+        text(&base.birth_date),
+        text(&base.sex),
+        text(&base.mother_name),
+        text(&base.father_name),
+        text(&base.rg),
+        email.map(|e| e.email.clone()).unwrap_or_default(),
+        phone.map(|p| text(&p.ddd)).unwrap_or_default(),
+        phone.map(|p| text(&p.number)).unwrap_or_default(),
+        phone.map(|p| text(&p.kind)).unwrap_or_default(),
+        address.map(|a| text(&a.street)).unwrap_or_default(),
+        address.map(|a| text(&a.number)).unwrap_or_default(),
+        address.map(|a| text(&a.complement)).unwrap_or_default(),
+        address.map(|a| text(&a.neighborhood)).unwrap_or_default(),
+        address.map(|a| text(&a.city)).unwrap_or_default(),
+        address.map(|a| text(&a.uf)).unwrap_or_default(),
+        address.map(|a| text(&a.postal_code)).unwrap_or_default(),
+        data.match_score
+            .map(|score| format!("{:.2}", score))
+            .unwrap_or_default(),
+        data.match_method
+            .map(|method| method.as_str().to_string())
+            .unwrap_or_default(),
+    ]
+}
+
+/// Maps a row onto the header columns as a JSON object
+fn row_to_object(columns: &[String], values: &[&str]) -> Result<Value> {
+    if columns.is_empty() {
+        bail!("write_header must be called before write_row");
+    }
+    if columns.len() != values.len() {
+        bail!(
+            "Row has {} values but header has {} columns",
+            values.len(),
+            columns.len()
+        );
+    }
+
+    let object = columns
+        .iter()
+        .zip(values)
+        .map(|(column, value)| (column.clone(), Value::String(value.to_string())))
+        .collect::<Map<_, _>>();
+    Ok(Value::Object(object))
+}
+
+/// JSON exporter writing a single array of objects
+pub struct JsonExporter<W: Write> {
+    writer: BufWriter<W>,
+    columns: Vec<String>,
+    rows_written: usize,
+}
+
+impl<W: Write> JsonExporter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            writer: BufWriter::new(inner),
+            columns: Vec::new(),
+            rows_written: 0,
+        }
+    }
+}
+
+impl<W: Write> RecordExporter for JsonExporter<W> {
+    fn write_header(&mut self, columns: &[&str]) -> Result<()> {
+        self.columns = columns.iter().map(|c| c.to_string()).collect();
+        self.writer.write_all(b"[")?;
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[&str]) -> Result<()> {
+        let object = row_to_object(&self.columns, values)?;
+        if self.rows_written > 0 {
+            self.writer.write_all(b",")?;
+        }
+        self.writer.write_all(b"\n  ")?;
+        serde_json::to_writer(&mut self.writer, &object)?;
+        self.rows_written += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        if self.rows_written > 0 {
+            self.writer.write_all(b"\n")?;
+        }
+        self.writer.write_all(b"]\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Longest text Excel stores in a cell; longer values are truncated
+const XLSX_MAX_CELL_CHARS: usize = 32_767;
+
+/// XLSX exporter. Rows are held in memory and the workbook is written on
+/// `finish`. Values stay text so CPFs, CEPs and IPTU numbers keep their
+/// leading zeros.
+pub struct XlsxExporter<W: Write + Seek + Send> {
+    inner: W,
+    worksheet: Worksheet,
+    next_row: u32,
+}
+
+impl<W: Write + Seek + Send> XlsxExporter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            worksheet: Worksheet::new(),
+            next_row: 0,
+        }
+    }
+
+    fn write_cells(&mut self, values: &[&str], format: Option<&Format>) -> Result<()> {
+        for (col, value) in values.iter().enumerate() {
+            let col = u16::try_from(col).context("Too many columns for an XLSX sheet")?;
+            let value = match value.char_indices().nth(XLSX_MAX_CELL_CHARS) {
+                Some((end, _)) => &value[..end],
+                None => value,
+            };
+            match format {
+                Some(format) => {
+                    self.worksheet
+                        .write_string_with_format(self.next_row, col, value, format)?
+                }
+                None => self.worksheet.write_string(self.next_row, col, value)?,
+            };
+        }
+        self.next_row += 1;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek + Send> RecordExporter for XlsxExporter<W> {
+    fn write_header(&mut self, columns: &[&str]) -> Result<()> {
+        self.write_cells(columns, Some(&Format::new().set_bold()))?;
+        self.worksheet.set_freeze_panes(1, 0)?;
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[&str]) -> Result<()> {
+        self.write_cells(values, None)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        let XlsxExporter {
+            mut inner,
+            mut worksheet,
+            ..
+        } = *self;
+        worksheet.autofit();
+        let mut workbook = Workbook::new();
+        workbook.push_worksheet(worksheet);
+        workbook
+            .save_to_writer(&mut inner)
+            .context("Failed to write XLSX workbook")?;
+        inner.flush()?;
+        Ok(())
+    }
+}
+
+/// Newline-delimited JSON exporter (one object per line)
+pub struct JsonlExporter<W: Write> {
+    writer: BufWriter<W>,
+    columns: Vec<String>,
+}
+
+impl<W: Write> JsonlExporter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            writer: BufWriter::new(inner),
+            columns: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> RecordExporter for JsonlExporter<W> {
+    fn write_header(&mut self, columns: &[&str]) -> Result<()> {
+        self.columns = columns.iter().map(|c| c.to_string()).collect();
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[&str]) -> Result<()> {
+        let object = row_to_object(&self.columns, values)?;
+        serde_json::to_writer(&mut self.writer, &object)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export_sample(exporter: Box<dyn RecordExporter + '_>) -> Result<()> {
+        let mut exporter = exporter;
+        exporter.write_header(&["name", "cep"])?;
+        exporter.write_row(&["MARIA", "01455040"])?;
+        exporter.write_row(&["JOSE", "04538080"])?;
+        exporter.finish()
+    }
+
+    #[test]
+    fn test_csv_exporter() {
+        let mut buffer = Vec::new();
+        export_sample(Box::new(CsvExporter::new(&mut buffer))).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "name,cep\nMARIA,01455040\nJOSE,04538080\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_appending_skips_matching_header() {
+        let mut buffer = Vec::new();
+        let existing = Some(vec!["name".to_string(), "cep".to_string()]);
+        export_sample(Box::new(CsvExporter::configured(
+            &mut buffer,
+            existing,
+            CsvExportOptions::default(),
+        )))
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "MARIA,01455040\nJOSE,04538080\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_appending_rejects_different_header() {
+        let mut buffer = Vec::new();
+        let existing = Some(vec!["name".to_string(), "phone".to_string()]);
+        assert!(export_sample(Box::new(CsvExporter::configured(
+            &mut buffer,
+            existing,
+            CsvExportOptions::default(),
+        )))
+        .is_err());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_exporter_for_append_accumulates_rows() {
+        let path = std::env::temp_dir().join(format!("iptu_export_{}.csv", uuid::Uuid::new_v4()));
+        let path_str = path.to_str().unwrap();
+
+        export_sample(
+            exporter_for(
+                ExportFormat::Csv,
+                path_str,
+                true,
+                CsvExportOptions::default(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        export_sample(
+            exporter_for(
+                ExportFormat::Csv,
+                path_str,
+                true,
+                CsvExportOptions::default(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            contents.lines().filter(|line| *line == "name,cep").count(),
+            1
+        );
+        assert_eq!(contents.lines().count(), 5);
+        assert!(exporter_for(
+            ExportFormat::Json,
+            path_str,
+            true,
+            CsvExportOptions::default()
+        )
+        .is_err());
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_xlsx_exporter_writes_workbook() {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        {
+            let mut exporter: Box<dyn RecordExporter + '_> =
+                Box::new(XlsxExporter::new(&mut buffer));
+            exporter.write_header(&["owner", "EnrichmentJSON"]).unwrap();
+            exporter
+                .write_row(&["JOSÉ DA CONCEIÇÃO", &"x".repeat(XLSX_MAX_CELL_CHARS + 10)])
+                .unwrap();
+            exporter.finish().unwrap();
+        }
+
+        // An XLSX file is a zip archive
+        assert!(buffer.get_ref().starts_with(b"PK\x03\x04"));
+    }
+
+    #[test]
+    fn test_exporter_for_rejects_xlsx_append() {
+        let path = std::env::temp_dir().join(format!("iptu_export_{}.xlsx", uuid::Uuid::new_v4()));
+        let path_str = path.to_str().unwrap();
+        assert!(exporter_for(
+            ExportFormat::Xlsx,
+            path_str,
+            true,
+            CsvExportOptions::default()
+        )
+        .is_err());
+
+        export_sample(
+            exporter_for(
+                ExportFormat::Xlsx,
+                path_str,
+                false,
+                CsvExportOptions::default(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() > 0);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_parse_csv_delimiter() {
+        assert_eq!(parse_csv_delimiter(";"), Ok(b';'));
+        assert_eq!(parse_csv_delimiter("Semicolon"), Ok(b';'));
+        assert_eq!(parse_csv_delimiter("tab"), Ok(b'\t'));
+        assert_eq!(parse_csv_delimiter("\\t"), Ok(b'\t'));
+        assert_eq!(parse_csv_delimiter("|"), Ok(b'|'));
+        assert!(parse_csv_delimiter(";;").is_err());
+        assert!(parse_csv_delimiter("\"").is_err());
+        assert!(parse_csv_delimiter("§").is_err());
+    }
+
+    #[test]
+    fn test_csv_semicolon_latin1_for_excel() {
+        let options = CsvExportOptions {
+            delimiter: b';',
+            encoding: CsvEncoding::Latin1,
+        };
+        let mut buffer = Vec::new();
+        {
+            let mut exporter: Box<dyn RecordExporter + '_> =
+                Box::new(CsvExporter::configured(&mut buffer, None, options));
+            exporter.write_header(&["owner", "street"]).unwrap();
+            exporter.write_row(&["JOSÉ", "RUA A; FUNDOS"]).unwrap();
+            exporter.finish().unwrap();
+        }
+
+        let decoded = decode_latin1(&buffer);
+        assert_eq!(decoded, "owner;street\nJOSÉ;\"RUA A; FUNDOS\"\n");
+    }
+
+    #[test]
+    fn test_exporter_for_append_reads_header_with_delimiter() {
+        let path = std::env::temp_dir().join(format!("iptu_export_{}.csv", uuid::Uuid::new_v4()));
+        let path_str = path.to_str().unwrap();
+        let options = CsvExportOptions {
+            delimiter: b';',
+            ..CsvExportOptions::default()
+        };
+
+        export_sample(exporter_for(ExportFormat::Csv, path_str, true, options).unwrap()).unwrap();
+        export_sample(exporter_for(ExportFormat::Csv, path_str, true, options).unwrap()).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            contents.lines().filter(|line| *line == "name;cep").count(),
+            1
+        );
+        assert_eq!(contents.lines().count(), 5);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_csv_latin1_round_trip() {
+        let owners = ["JOSÉ DA CONCEIÇÃO", "MÁRCIA GONÇALVES", "JOÃO ÁVILA"];
+        let mut buffer = Vec::new();
+        {
+            let mut exporter: Box<dyn RecordExporter + '_> = Box::new(CsvExporter::configured(
+                &mut buffer,
+                None,
+                CsvExportOptions {
+                    encoding: CsvEncoding::Latin1,
+                    ..CsvExportOptions::default()
+                },
+            ));
+            exporter.write_header(&["owner"]).unwrap();
+            for owner in owners {
+                exporter.write_row(&[owner]).unwrap();
+            }
+            exporter.finish().unwrap();
+        }
+
+        // Accented letters are single bytes, so the output is not valid UTF-8
+        assert!(String::from_utf8(buffer.clone()).is_err());
+        let decoded = decode_latin1(&buffer);
+        assert_eq!(
+            decoded,
+            "owner\nJOSÉ DA CONCEIÇÃO\nMÁRCIA GONÇALVES\nJOÃO ÁVILA\n"
+        );
+    }
+
+    #[test]
+    fn test_latin1_replaces_unrepresentable_characters() {
+        assert_eq!(encode_latin1("SÃO PAULO ✓ 東京"), b"S\xc3O PAULO ? ??");
+        assert_eq!(encode_latin1("plain"), b"plain");
+    }
+
+    #[test]
+    fn test_latin1_has_no_windows_1252_extras() {
+        // Both have windows-1252 bytes (0x80, 0x97) but are outside Latin-1
+        assert_eq!(encode_latin1("R$ 10 € — ok"), b"R$ 10 ? ? ok");
+        assert_eq!(encode_latin1("\u{a0}ÿ"), b"\xa0\xff");
+    }
+
+    #[test]
+    fn test_json_exporter() {
+        let mut buffer = Vec::new();
+        export_sample(Box::new(JsonExporter::new(&mut buffer))).unwrap();
+        let parsed: Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 2);
+        assert_eq!(parsed[1]["name"], "JOSE");
+    }
+
+    #[test]
+    fn test_json_exporter_empty() {
+        let mut buffer = Vec::new();
+        let mut exporter: Box<dyn RecordExporter + '_> = Box::new(JsonExporter::new(&mut buffer));
+        exporter.write_header(&["name"]).unwrap();
+        exporter.finish().unwrap();
+        let parsed: Value = serde_json::from_slice(&buffer).unwrap();
+        assert!(parsed.as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_jsonl_exporter() {
+        let mut buffer = Vec::new();
+        export_sample(Box::new(JsonlExporter::new(&mut buffer))).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let first: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["cep"], "01455040");
+    }
+
+    #[test]
+    fn test_flatten_customer_uses_first_contact_entries() {
+        use crate::diretrix_enrichment::{CustomerBase, CustomerEmail, CustomerPhone, MatchMethod};
+
+        let data = GetCustomerData {
+            base: CustomerBase {
+                id: "12345678901".to_string(),
+                name: "MARIA DOS SANTOS".to_string(),
+                cpf: Some("12345678901".to_string()),
+                birth_date: None,
+                sex: Some("F".to_string()),
+                mother_name: None,
+                father_name: None,
+                rg: None,
+            },
+            emails: vec![
+                CustomerEmail {
+                    email: "maria@example.com".to_string(),
+                    ranking: Some(1),
+                },
+                CustomerEmail {
+                    email: "other@example.com".to_string(),
+                    ranking: Some(2),
+                },
+            ],
+            phones: vec![CustomerPhone {
+                ddd: Some("11".to_string()),
+                number: Some("999990000".to_string()),
+                operator_: None,
+                kind: Some("MOVEL".to_string()),
+                ranking: None,
+            }],
+            addresses: Vec::new(),
+            match_score: Some(0.8333),
+            match_method: Some(MatchMethod::Name),
+        };
+
+        let row = flatten_customer(&data);
+        assert_eq!(row.len(), CUSTOMER_COLUMNS.len());
+        assert_eq!(row[1], "MARIA DOS SANTOS");
+        assert_eq!(row[8], "maria@example.com");
+        assert_eq!(row[10], "999990000");
+        assert!(row[12..19].iter().all(|value| value.is_empty()));
+        assert_eq!(row[19], "0.83");
+        assert_eq!(row[20], "name");
+    }
+
+    #[test]
+    fn test_export_headers_are_stable() {
+        assert_eq!(
+            DIRETRIX_COLUMNS.join(","),
+            "Owner,IPTU,Street,Number,Complement,Complement 2,Neighborhood,Document 1,Document 2,EnrichmentJSON"
+        );
+        assert_eq!(
+            ADDRESS_COLUMNS.join(","),
+            "cpf_cnpj,nome_razao_social,logradouro,numero,complemento,bairro,cep"
+        );
+        assert_eq!(
+            CUSTOMER_COLUMNS.join(","),
+            "id,name,cpf,birth_date,sex,mother_name,father_name,rg,email,phone_ddd,phone_number,\
+             phone_kind,address_street,address_number,address_complement,address_neighborhood,\
+             address_city,address_uf,address_postal_code,match_score,match_method"
+        );
+    }
+
+    #[test]
+    fn test_schema_version_is_a_leading_column() {
+        let mut buffer = Vec::new();
+        {
+            let exporter: Box<dyn RecordExporter + '_> = Box::new(CsvExporter::new(&mut buffer));
+            let mut exporter = with_schema_version(exporter, true);
+            exporter.write_header(&["name", "cep"]).unwrap();
+            exporter.write_row(&["Ana", "01001000"]).unwrap();
+            exporter.finish().unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "schema_version,name,cep\n1,Ana,01001000\n"
+        );
+
+        let mut buffer = Vec::new();
+        {
+            let exporter: Box<dyn RecordExporter + '_> = Box::new(CsvExporter::new(&mut buffer));
+            let mut exporter = with_schema_version(exporter, false);
+            exporter.write_header(&["name"]).unwrap();
+            exporter.finish().unwrap();
+        }
+        assert_eq!(String::from_utf8(buffer).unwrap(), "name\n");
+    }
+
+    #[test]
+    fn test_rows_match_their_headers() {
+        let property = PropertyRecord {
+            owner: "MARIA".to_string(),
+            iptu: "001.123.4567-8".to_string(),
+            street: "RUA A".to_string(),
+            number: "10".to_string(),
+            complement: String::new(),
+            complement2: String::new(),
+            neighborhood: "CENTRO".to_string(),
+            document1: Some("12345678901".to_string()),
+            document2: None,
+        };
+        let row = diretrix_row(&property, None);
+        assert_eq!(row.len(), DIRETRIX_COLUMNS.len());
+        assert_eq!(row[1], "001.123.4567-8");
+        assert_eq!(row[7], "12345678901");
+        assert_eq!(row[9], "");
+
+        let address = AddressRecord {
+            cpf_cnpj: "12345678901".to_string(),
+            nome_razao_social: "MARIA".to_string(),
+            logradouro: "RUA A".to_string(),
+            numero: "10".to_string(),
+            complemento: String::new(),
+            bairro: "CENTRO".to_string(),
+            cep: "01001000".to_string(),
+        };
+        let row = address_row(&address);
+        assert_eq!(row.len(), ADDRESS_COLUMNS.len());
+        assert_eq!(row[6], "01001000");
+    }
+
+    #[test]
+    fn test_read_diretrix_csv_round_trip() {
+        let path = std::env::temp_dir().join(format!("iptu_diretrix_{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "Owner,IPTU,Street,Number,Complement,Complement 2,Neighborhood,Document 1,Document 2,EnrichmentJSON\n\
+             MARIA DOS SANTOS,001.123.4567-8,RUA A,10,AP 1,,CENTRO,12345678901,,\n",
+        )
+        .unwrap();
+
+        let records = read_diretrix_csv(&path, CsvExportOptions::default()).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].owner, "MARIA DOS SANTOS");
+        assert_eq!(records[0].complement, "AP 1");
+        assert_eq!(records[0].document1.as_deref(), Some("12345678901"));
+        assert!(records[0].document2.is_none());
+
+        std::fs::write(&path, "name,cep\nMARIA,01455040\n").unwrap();
+        assert!(read_diretrix_csv(&path, CsvExportOptions::default()).is_err());
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_read_diretrix_csv_in_export_dialect() {
+        let path = std::env::temp_dir().join(format!("iptu_diretrix_{}.csv", uuid::Uuid::new_v4()));
+        let options = CsvExportOptions {
+            delimiter: b';',
+            encoding: CsvEncoding::Latin1,
+        };
+        let property = PropertyRecord {
+            owner: "JOSÉ DA CONCEIÇÃO".to_string(),
+            iptu: "001.123.4567-8".to_string(),
+            street: "RUA SÃO BENTO; FUNDOS".to_string(),
+            number: "10".to_string(),
+            complement: String::new(),
+            complement2: String::new(),
+            neighborhood: "SÉ".to_string(),
+            document1: Some("12345678901".to_string()),
+            document2: None,
+        };
+        {
+            let exporter =
+                exporter_for(ExportFormat::Csv, path.to_str().unwrap(), false, options).unwrap();
+            let mut exporter = with_schema_version(exporter, true);
+            exporter.write_header(&DIRETRIX_COLUMNS).unwrap();
+            let row = diretrix_row(&property, None);
+            exporter
+                .write_row(&row.iter().map(String::as_str).collect::<Vec<_>>())
+                .unwrap();
+            exporter.finish().unwrap();
+        }
+
+        let records = read_diretrix_csv(&path, options).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(records, vec![property]);
+    }
+
+    #[test]
+    fn test_read_enrichment_input() {
+        let path = std::env::temp_dir().join(format!("iptu_enrich_{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "id,CPF,Nome,E-mail\n1,123.456.789-01,MARIA,\n2,,JOSÉ,jose@example.com\n3,98765432100,,\n",
+        )
+        .unwrap();
+
+        let input = read_enrichment_input(&path, CsvExportOptions::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(input.rows.len(), 3);
+        assert_eq!(input.document(&input.rows[0]), Some("123.456.789-01"));
+        assert_eq!(input.name(&input.rows[0]), Some("MARIA"));
+        assert_eq!(input.document(&input.rows[1]), None);
+        assert_eq!(input.name(&input.rows[2]), None);
+        assert_eq!(input.email(&input.rows[1]), Some("jose@example.com"));
+        assert_eq!(input.phone(&input.rows[1]), None);
+
+        let columns = input.output_columns();
+        assert_eq!(columns.len(), 4 + CUSTOMER_COLUMNS.len());
+        assert_eq!(columns[4], "enriched_id");
+        assert_eq!(
+            input.enriched_row(&input.rows[1], None).len(),
+            columns.len()
+        );
+    }
+
+    #[test]
+    fn test_read_enrichment_input_latin1_semicolon() {
+        let path = std::env::temp_dir().join(format!("iptu_enrich_{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(&path, encode_latin1("cpf;nome\n12345678901;JOSÉ; FILHO\n")).unwrap();
+
+        let options = CsvExportOptions {
+            delimiter: b';',
+            encoding: CsvEncoding::Latin1,
+        };
+        let input = read_enrichment_input(&path, options).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(input.rows.len(), 1);
+        assert_eq!(input.name(&input.rows[0]), Some("JOSÉ"));
+    }
+
+    #[test]
+    fn test_read_enrichment_input_requires_a_known_column() {
+        let path = std::env::temp_dir().join(format!("iptu_enrich_{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "id,cidade\n1,SAO PAULO\n").unwrap();
+
+        let err = read_enrichment_input(&path, CsvExportOptions::default()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(err.to_string().contains("no column to search by"));
+    }
+
+    #[test]
+    fn test_row_length_mismatch() {
+        let mut buffer = Vec::new();
+        let mut exporter = JsonlExporter::new(&mut buffer);
+        exporter.write_header(&["name", "cep"]).unwrap();
+        assert!(exporter.write_row(&["only-one"]).is_err());
+    }
+}
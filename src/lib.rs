@@ -1,3 +1,9 @@
+pub mod browser;
 pub mod dbase_scraper;
+pub mod diretrix_enrichment;
 pub mod diretrix_scraper;
+pub mod error;
+pub mod export;
 pub mod scraper;
+pub mod session_manager;
+pub mod validation;
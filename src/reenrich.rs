@@ -0,0 +1,282 @@
+use anyhow::{Context, Result};
+use futures::stream::{self, StreamExt};
+use reqwest::{Client as HttpClient, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::diretrix_enrichment::GetCustomerData;
+use crate::supabase::{IPTUResult, SupabaseClient, MAX_ROWS_PER_REQUEST};
+use crate::{
+    enrichment_request, parse_enrichment_payload, resolve_enrichment_backend, EnrichmentBudget,
+};
+
+/// Table the enrichment rows are upserted into unless `SUPABASE_ENRICHMENT_TABLE` is set
+pub const DEFAULT_ENRICHMENT_TABLE: &str = "iptu_enrichments";
+
+/// Progress of a `reenrich` run, saved after every batch so an interrupted
+/// backfill picks up where it stopped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReenrichCheckpoint {
+    /// `id` of the last `iptus` row that was processed
+    pub last_id: Option<String>,
+    pub processed: usize,
+    pub enriched: usize,
+    pub updated_at: Option<String>,
+}
+
+impl ReenrichCheckpoint {
+    /// Load a saved checkpoint; a missing file means starting from the first result
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read checkpoint {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid checkpoint {}", path.display()))
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        self.updated_at = Some(chrono::Utc::now().to_rfc3339());
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write checkpoint {}", path.display()))
+    }
+}
+
+pub struct ReenrichOptions {
+    pub batch_size: usize,
+    pub concurrency: usize,
+    pub max_calls: Option<usize>,
+    pub checkpoint_path: PathBuf,
+    pub table: String,
+}
+
+/// Page through successful results after the checkpoint, enrich each owner
+/// with bounded concurrency and upsert every batch in one request. Stops when
+/// the results run out or the call budget is spent.
+pub async fn run(
+    client: &SupabaseClient,
+    options: &ReenrichOptions,
+    mut checkpoint: ReenrichCheckpoint,
+) -> Result<ReenrichCheckpoint> {
+    let (base_url, token) = resolve_enrichment_backend()?;
+    let http = HttpClient::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+    let mut budget = EnrichmentBudget::new(options.max_calls);
+    // Past the server's row cap a batch comes back short anyway
+    let batch_size = options.batch_size.clamp(1, MAX_ROWS_PER_REQUEST);
+
+    loop {
+        let rows = client
+            .fetch_successful_results_after(checkpoint.last_id.as_deref(), batch_size)
+            .await?;
+        if rows.is_empty() {
+            break;
+        }
+
+        let affordable = affordable_rows(&rows, &mut budget);
+        let batch = &rows[..affordable];
+
+        let lookups = stream::iter(batch)
+            .map(|row| lookup_owner(&http, &base_url, token.as_deref(), row))
+            .buffered(options.concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        let enrichments = dedupe_enrichments(
+            batch
+                .iter()
+                .zip(lookups)
+                .filter_map(|(row, data)| Some(enrichment_row(row, &data?)))
+                .collect(),
+        );
+        let stored = client
+            .upsert_enrichments(&options.table, &enrichments)
+            .await?;
+
+        if let Some(last_id) = batch.iter().rev().find_map(|row| row.id.clone()) {
+            checkpoint.last_id = Some(last_id);
+        }
+        checkpoint.processed += batch.len();
+        checkpoint.enriched += stored;
+        checkpoint.save(&options.checkpoint_path)?;
+
+        info!(
+            "Batch done: {} results, {} enriched ({} processed, {} enriched in total)",
+            batch.len(),
+            stored,
+            checkpoint.processed,
+            checkpoint.enriched
+        );
+
+        if affordable < rows.len() {
+            warn!(
+                "Enrichment call budget exhausted - run again to continue after {}",
+                checkpoint.last_id.as_deref().unwrap_or("the first result")
+            );
+            break;
+        }
+    }
+
+    Ok(checkpoint)
+}
+
+/// How many leading rows fit in the budget; rows without an owner name need no call
+fn affordable_rows(rows: &[IPTUResult], budget: &mut EnrichmentBudget) -> usize {
+    rows.iter()
+        .position(|row| owner_name(row).is_some() && !budget.try_spend())
+        .unwrap_or(rows.len())
+}
+
+fn owner_name(row: &IPTUResult) -> Option<&str> {
+    row.nome_proprietario
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+async fn lookup_owner(
+    http: &HttpClient,
+    base_url: &str,
+    token: Option<&str>,
+    row: &IPTUResult,
+) -> Option<GetCustomerData> {
+    let name = owner_name(row)?;
+    let response = match enrichment_request(http, base_url, token, "name", name)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(err) => {
+            warn!(
+                "Failed to call enrichment service for {}: {}",
+                row.contributor_number, err
+            );
+            return None;
+        }
+    };
+
+    let status = response.status();
+    if status == StatusCode::NOT_FOUND {
+        return None;
+    }
+    if !status.is_success() {
+        warn!(
+            "Enrichment service error for {} (status {})",
+            row.contributor_number, status
+        );
+        return None;
+    }
+
+    match parse_enrichment_payload(response, token.is_some()).await {
+        Ok(data) => data,
+        Err(err) => {
+            warn!(
+                "Failed to parse enrichment response for {}: {}",
+                row.contributor_number, err
+            );
+            None
+        }
+    }
+}
+
+/// Keep the last enrichment of each contributor number: `iptus` can hold several
+/// results for one number, and an upsert may not touch the same row twice
+fn dedupe_enrichments(rows: Vec<serde_json::Value>) -> Vec<serde_json::Value> {
+    let mut seen = HashSet::new();
+    let mut unique: Vec<serde_json::Value> = rows
+        .into_iter()
+        .rev()
+        .filter(|row| seen.insert(row["contributor_number"].to_string()))
+        .collect();
+    unique.reverse();
+    unique
+}
+
+fn enrichment_row(row: &IPTUResult, data: &GetCustomerData) -> serde_json::Value {
+    json!({
+        "contributor_number": row.contributor_number,
+        "owner_name": row.nome_proprietario,
+        "enriched_at": chrono::Utc::now().to_rfc3339(),
+        "data": data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result_row(id: &str, owner: Option<&str>) -> IPTUResult {
+        serde_json::from_value(json!({
+            "id": id,
+            "contributor_number": format!("000.000.{}", id),
+            "nome_proprietario": owner,
+            "sucesso": true,
+            "timestamp": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_affordable_rows_stops_at_budget() {
+        let rows = vec![
+            result_row("1", Some("MARIA")),
+            result_row("2", None),
+            result_row("3", Some("JOSE")),
+            result_row("4", Some("ANA")),
+        ];
+
+        assert_eq!(
+            affordable_rows(&rows, &mut EnrichmentBudget::new(Some(2))),
+            3
+        );
+        assert_eq!(
+            affordable_rows(&rows, &mut EnrichmentBudget::new(Some(0))),
+            0
+        );
+        assert_eq!(affordable_rows(&rows, &mut EnrichmentBudget::new(None)), 4);
+    }
+
+    #[test]
+    fn test_dedupe_enrichments_keeps_last_per_number() {
+        let rows = vec![
+            json!({ "contributor_number": "001", "owner_name": "MARIA" }),
+            json!({ "contributor_number": "002", "owner_name": "JOSE" }),
+            json!({ "contributor_number": "001", "owner_name": "MARIA S" }),
+        ];
+
+        let unique = dedupe_enrichments(rows);
+        assert_eq!(unique.len(), 2);
+        assert_eq!(unique[0]["contributor_number"], "002");
+        assert_eq!(unique[1]["owner_name"], "MARIA S");
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let path =
+            std::env::temp_dir().join(format!("reenrich_checkpoint_{}.json", uuid::Uuid::new_v4()));
+        assert!(ReenrichCheckpoint::load(&path).unwrap().last_id.is_none());
+
+        let mut checkpoint = ReenrichCheckpoint {
+            last_id: Some("row-42".to_string()),
+            processed: 42,
+            enriched: 30,
+            updated_at: None,
+        };
+        checkpoint.save(&path).unwrap();
+
+        let loaded = ReenrichCheckpoint::load(&path).unwrap();
+        assert_eq!(loaded.last_id.as_deref(), Some("row-42"));
+        assert_eq!(loaded.processed, 42);
+        assert_eq!(loaded.enriched, 30);
+        assert!(loaded.updated_at.is_some());
+
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Summary of a `process` run, written to `manifest.json` in the run directory
+/// so a run can be inspected (or its batch completed by hand) afterwards.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    /// Supabase batch created for this run, if any
+    pub batch_id: Option<String>,
+    /// Whether the batch was marked `completed` in Supabase
+    pub batch_completed: bool,
+    pub total_processed: usize,
+    pub total_success: usize,
+    /// Numbers the site reported as not found; not part of `total_error`
+    #[serde(default)]
+    pub total_not_found: usize,
+    pub total_error: usize,
+}
+
+impl RunManifest {
+    pub fn new() -> Self {
+        Self {
+            started_at: chrono::Utc::now().to_rfc3339(),
+            finished_at: None,
+            batch_id: None,
+            batch_completed: false,
+            total_processed: 0,
+            total_success: 0,
+            total_not_found: 0,
+            total_error: 0,
+        }
+    }
+
+    pub fn path(run_dir: &Path) -> PathBuf {
+        run_dir.join(MANIFEST_FILE)
+    }
+
+    pub fn save(&self, run_dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(run_dir)
+            .with_context(|| format!("Failed to create run directory {}", run_dir.display()))?;
+        let path = Self::path(run_dir);
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write run manifest {}", path.display()))?;
+        Ok(path)
+    }
+
+    #[allow(dead_code)]
+    pub fn load(run_dir: &Path) -> Result<Self> {
+        let path = Self::path(run_dir);
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read run manifest {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid run manifest {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("iptu_manifest_{}", uuid::Uuid::new_v4()));
+        let mut manifest = RunManifest::new();
+        manifest.batch_id = Some("batch-123".to_string());
+        manifest.total_processed = 12;
+
+        let path = manifest.save(&dir).unwrap();
+        assert_eq!(path, dir.join("manifest.json"));
+
+        let loaded = RunManifest::load(&dir).unwrap();
+        assert_eq!(loaded.batch_id.as_deref(), Some("batch-123"));
+        assert_eq!(loaded.total_processed, 12);
+        assert!(!loaded.batch_completed);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
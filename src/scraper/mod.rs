@@ -1,11 +1,26 @@
-use anyhow::Result;
+mod proxy;
+mod selectors;
+mod session;
+
+pub use proxy::ProxyConfig;
+pub use selectors::{
+    ConsentFailurePolicy, CookieConsentConfig, FormFieldsConfig, ResultFieldNames, ScraperSelectors,
+};
+use session::CookieSessionStore;
+
+use crate::browser::{Browser, BrowserCapabilities};
+use anyhow::{Context, Result};
 use rand::seq::SliceRandom;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
-use thirtyfour::{By, DesiredCapabilities, WebDriver, WebElement};
+use thirtyfour::{By, CapabilitiesHelper, WebDriver, WebElement};
+use thiserror::Error;
 use tokio::sync::Mutex;
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
 #[derive(Clone)]
 enum DelayPattern {
@@ -43,7 +58,7 @@ impl DelayPattern {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ScraperResult {
     pub contributor_number: String,
     pub numero_cadastro: Option<String>,
@@ -54,8 +69,49 @@ pub struct ScraperResult {
     pub complemento: Option<String>,
     pub bairro: Option<String>,
     pub cep: Option<String>,
+    pub valor_venal: Option<String>,
+    pub area_terreno: Option<String>,
+    pub area_construida: Option<String>,
+    pub uso: Option<String>,
     pub success: bool,
+    /// The SP site reported the contributor number as not found/invalid
+    pub not_found: bool,
+    /// The cookie consent banner could not be dismissed
+    pub consent_failed: bool,
     pub error: Option<String>,
+    /// Page source the data was parsed from, when `store_raw_html` is enabled
+    #[serde(skip)]
+    pub raw_html: Option<String>,
+}
+
+/// `ScraperResult::error` of a number the site reports as not found
+pub const NOT_FOUND_ERROR: &str = "iptu-not-found";
+
+#[derive(Debug, Error)]
+pub enum ScrapeError {
+    #[error("IPTU not found: {0}")]
+    NotFound(String),
+    #[error("Cookie consent failed after {attempts} attempts")]
+    ConsentFailed { attempts: u32 },
+    #[error("Número de cadastro inválido: {0}")]
+    InvalidNumber(String),
+}
+
+/// How the cookie consent step ended for one page load
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsentOutcome {
+    /// Banner dismissed after this many attempts
+    Accepted { attempts: u32 },
+    /// Restored cookies already carried the consent
+    Restored,
+    /// Banner still present after this many attempts
+    Failed { attempts: u32 },
+}
+
+impl ConsentOutcome {
+    pub fn is_accepted(&self) -> bool {
+        !matches!(self, Self::Failed { .. })
+    }
 }
 
 #[derive(Debug, Default)]
@@ -68,25 +124,252 @@ struct IPTUData {
     complemento: Option<String>,
     bairro: Option<String>,
     cep: Option<String>,
+    valor_venal: Option<String>,
+    area_terreno: Option<String>,
+    area_construida: Option<String>,
+    uso: Option<String>,
+}
+
+impl IPTUData {
+    /// Fill any field still missing with the value from `other`
+    fn fill_missing(&mut self, other: IPTUData) {
+        fn merge(target: &mut Option<String>, value: Option<String>) {
+            if target.is_none() {
+                *target = value;
+            }
+        }
+
+        merge(&mut self.numero_cadastro, other.numero_cadastro);
+        merge(&mut self.nome_proprietario, other.nome_proprietario);
+        merge(&mut self.nome_compromissario, other.nome_compromissario);
+        merge(&mut self.endereco, other.endereco);
+        merge(&mut self.numero, other.numero);
+        merge(&mut self.complemento, other.complemento);
+        merge(&mut self.bairro, other.bairro);
+        merge(&mut self.cep, other.cep);
+        merge(&mut self.valor_venal, other.valor_venal);
+        merge(&mut self.area_terreno, other.area_terreno);
+        merge(&mut self.area_construida, other.area_construida);
+        merge(&mut self.uso, other.uso);
+    }
+}
+
+/// What the SP results page turned out to be
+#[derive(Debug)]
+enum IptuPage {
+    Data(Box<IPTUData>),
+    /// The site reported the number as not found/invalid; holds the matched phrase
+    NotFound(String),
 }
 
+/// Parse the SP IPTU results page from its HTML source.
+/// A page without the number and owner fields whose visible text contains any of
+/// `not_found_patterns` (case-insensitive) is `NotFound`. Otherwise fields are looked up by their name in `fields`; inputs use their `value`
+/// attribute and any other element its text. Empty fields come back as `None`.
+fn parse_iptu_html(
+    html: &str,
+    not_found_patterns: &[String],
+    fields: &ResultFieldNames,
+) -> IptuPage {
+    let document = ::scraper::Html::parse_document(html);
+
+    if !has_result_fields(&document, fields) {
+        if let Some(pattern) = not_found_match(&document, not_found_patterns) {
+            return IptuPage::NotFound(pattern.clone());
+        }
+    }
+
+    let field = |name: &str| -> Option<String> {
+        let selector = ::scraper::Selector::parse(&format!("[name='{}']", name)).ok()?;
+        let element = document.select(&selector).next()?;
+        let value = match element.value().attr("value") {
+            Some(value) => value.to_string(),
+            None => element.text().collect::<String>(),
+        };
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    };
+
+    let mut data = IPTUData::default();
+    for (name, value) in result_field_slots(fields, &mut data) {
+        *value = field(name);
+    }
+    IptuPage::Data(Box::new(data))
+}
+
+/// Whether the page has the number or owner field of a results page
+fn has_result_fields(document: &::scraper::Html, fields: &ResultFieldNames) -> bool {
+    [&fields.numero_cadastro, &fields.nome_proprietario]
+        .iter()
+        .any(|name| {
+            ::scraper::Selector::parse(&format!("[name='{}']", name))
+                .map(|selector| document.select(&selector).next().is_some())
+                .unwrap_or(false)
+        })
+}
+
+/// The first of `not_found_patterns` (case-insensitive) found in the visible page text
+fn not_found_match<'a>(
+    document: &::scraper::Html,
+    not_found_patterns: &'a [String],
+) -> Option<&'a String> {
+    let mut page_text = String::new();
+    visible_text(document.root_element(), &mut page_text);
+    let page_text = page_text.to_lowercase();
+    not_found_patterns
+        .iter()
+        .find(|pattern| !pattern.is_empty() && page_text.contains(&pattern.to_lowercase()))
+}
+
+/// Append the text a user would see: skips `<head>`, scripts, styles and
+/// elements hidden with the `hidden` attribute or an inline `display: none`
+fn visible_text(element: ::scraper::ElementRef, out: &mut String) {
+    for child in element.children() {
+        if let Some(text) = child.value().as_text() {
+            out.push_str(text);
+            out.push(' ');
+        } else if let Some(child) = ::scraper::ElementRef::wrap(child) {
+            let value = child.value();
+            let style = value
+                .attr("style")
+                .unwrap_or("")
+                .to_lowercase()
+                .replace(char::is_whitespace, "");
+            let hidden = matches!(
+                value.name(),
+                "head" | "script" | "style" | "noscript" | "template"
+            ) || value.attr("hidden").is_some()
+                || style.contains("display:none")
+                || style.contains("visibility:hidden");
+            if !hidden {
+                visible_text(child, out);
+            }
+        }
+    }
+}
+
+/// Attribute the submit script sets on `<html>` of the form page; a page without
+/// it is the response to the submit
+const SUBMIT_MARKER: &str = "data-iptu-submitted";
+
+/// Whether the submitted form has turned into a results page: either the number
+/// or owner field is present, or a new page (without `SUBMIT_MARKER`) reports the
+/// number as not found
+fn results_ready(html: &str, selectors: &ScraperSelectors) -> bool {
+    let document = ::scraper::Html::parse_document(html);
+    if has_result_fields(&document, &selectors.site.result_fields) {
+        return true;
+    }
+
+    let still_on_form = document
+        .root_element()
+        .value()
+        .attr(SUBMIT_MARKER)
+        .is_some();
+    !still_on_form && not_found_match(&document, &selectors.not_found_patterns).is_some()
+}
+
+/// Each configured result field name paired with the `IPTUData` field it fills
+fn result_field_slots<'a>(
+    fields: &'a ResultFieldNames,
+    data: &'a mut IPTUData,
+) -> [(&'a str, &'a mut Option<String>); 12] {
+    [
+        (&fields.numero_cadastro, &mut data.numero_cadastro),
+        (&fields.nome_proprietario, &mut data.nome_proprietario),
+        (&fields.nome_compromissario, &mut data.nome_compromissario),
+        (&fields.endereco, &mut data.endereco),
+        (&fields.numero, &mut data.numero),
+        (&fields.complemento, &mut data.complemento),
+        (&fields.bairro, &mut data.bairro),
+        (&fields.cep, &mut data.cep),
+        (&fields.valor_venal, &mut data.valor_venal),
+        (&fields.area_terreno, &mut data.area_terreno),
+        (&fields.area_construida, &mut data.area_construida),
+        (&fields.uso, &mut data.uso),
+    ]
+}
+
+/// Failures older than this no longer count towards a cooldown (`--failure-window-secs`)
+pub const DEFAULT_FAILURE_WINDOW_SECS: u64 = 300;
+
+/// Failures within the window that trigger a cooldown (`--failure-threshold`)
+pub const DEFAULT_FAILURE_THRESHOLD: usize = 3;
+
+/// Length of a cooldown (`--cooldown-secs`)
+pub const DEFAULT_COOLDOWN_SECS: u64 = 600;
+
+/// How long to wait for the results page after submitting (`--result-wait-secs`)
+pub const DEFAULT_RESULT_WAIT_SECS: u64 = 30;
+
+/// How often the results page is polled while waiting for it
+const RESULT_POLL_MS: u64 = 500;
+
+/// Consecutive failures a cooldown also needs, capped at the threshold
+const COOLDOWN_CONSECUTIVE_FAILURES: usize = 2;
+
+/// How often a running cooldown logs the time left
+const COOLDOWN_PROGRESS_SECS: u64 = 120;
+
+/// Where `--persist-failure-state` keeps the failure history between runs
+pub const FAILURE_STATE_FILE: &str = "failure_state.json";
+
+/// The part of a `FailureTracker` saved by `--persist-failure-state`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FailureState {
+    failure_timestamps: Vec<u64>,
+    consecutive_failures: usize,
+    /// When the last cooldown started (seconds since the Unix epoch)
+    last_cooldown: Option<u64>,
+}
+
+/// Read-only snapshot of a `FailureTracker`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailureStats {
+    /// Failures since the last success
+    pub total_failures: usize,
+    /// Failures within the failure window
+    pub recent_failures: usize,
+    pub consecutive_failures: usize,
+    pub cooldown_active: bool,
+}
+
+/// Tracks scrape failures to decide when to back off from the SP site
 #[derive(Debug, Clone)]
-struct FailureTracker {
+pub struct FailureTracker {
     failure_count: usize,
     failure_timestamps: Vec<u64>,
     consecutive_failures: usize,
     last_cooldown: Option<u64>,
     cooldown_active: bool,
+    window_secs: u64,
+    threshold: usize,
+    cooldown_secs: u64,
+}
+
+impl Default for FailureTracker {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_FAILURE_WINDOW_SECS,
+            DEFAULT_FAILURE_THRESHOLD,
+            DEFAULT_COOLDOWN_SECS,
+        )
+    }
 }
 
 impl FailureTracker {
-    fn new() -> Self {
+    /// Cool down for `cooldown_secs` once `threshold` failures fall within
+    /// `window_secs`, the last of them consecutive
+    pub fn new(window_secs: u64, threshold: usize, cooldown_secs: u64) -> Self {
         Self {
             failure_count: 0,
             failure_timestamps: Vec::new(),
             consecutive_failures: 0,
             last_cooldown: None,
             cooldown_active: false,
+            window_secs,
+            threshold: threshold.max(1),
+            cooldown_secs,
         }
     }
 
@@ -97,40 +380,105 @@ impl FailureTracker {
             .as_secs()
     }
 
-    fn should_cooldown(&mut self) -> bool {
-        let now = Self::get_current_timestamp();
+    pub fn stats(&self) -> FailureStats {
+        self.stats_at(Self::get_current_timestamp())
+    }
+
+    /// Snapshot as of `now` (seconds since the Unix epoch)
+    pub fn stats_at(&self, now: u64) -> FailureStats {
+        FailureStats {
+            total_failures: self.failure_count,
+            recent_failures: self
+                .failure_timestamps
+                .iter()
+                .filter(|&&ts| now.saturating_sub(ts) < self.window_secs)
+                .count(),
+            consecutive_failures: self.consecutive_failures,
+            cooldown_active: self.cooldown_active,
+        }
+    }
+
+    pub fn should_cooldown(&mut self) -> bool {
+        self.should_cooldown_at(Self::get_current_timestamp())
+    }
+
+    /// `should_cooldown` as of `now` (seconds since the Unix epoch)
+    pub fn should_cooldown_at(&mut self, now: u64) -> bool {
+        // Clean up failures that left the window
+        let window_secs = self.window_secs;
+        self.failure_timestamps
+            .retain(|&ts| now.saturating_sub(ts) < window_secs);
+
+        self.threshold_reached_at(now)
+    }
+
+    /// `threshold` failures in the window as of `now`, and enough of them
+    /// consecutive; read-only, unlike `should_cooldown_at`
+    fn threshold_reached_at(&self, now: u64) -> bool {
+        self.stats_at(now).recent_failures >= self.threshold
+            && self.consecutive_failures >= COOLDOWN_CONSECUTIVE_FAILURES.min(self.threshold)
+    }
 
-        // Clean up old failures (older than 5 minutes instead of 10)
-        self.failure_timestamps.retain(|&ts| now - ts < 300);
+    /// The failure window as `5 min` or `90s` for log messages
+    fn window_label(&self) -> String {
+        if self.window_secs.is_multiple_of(60) {
+            format!("{} min", self.window_secs / 60)
+        } else {
+            format!("{}s", self.window_secs)
+        }
+    }
 
-        // Only cooldown if we have 3+ failures in 5 minutes AND 2+ consecutive failures
-        self.failure_timestamps.len() >= 3 && self.consecutive_failures >= 2
+    pub fn record_failure(&mut self, is_rate_limit: bool) {
+        self.record_failure_at(is_rate_limit, Self::get_current_timestamp());
     }
 
-    fn record_failure(&mut self, is_rate_limit: bool) {
-        let now = Self::get_current_timestamp();
+    /// `record_failure` at `now` (seconds since the Unix epoch)
+    pub fn record_failure_at(&mut self, is_rate_limit: bool, now: u64) {
         self.failure_timestamps.push(now);
         self.failure_count += 1;
         self.consecutive_failures += 1;
 
         if is_rate_limit {
             tracing::warn!(
-                "📊 Rate limit failure recorded. Total: {}, Recent (5 min): {}, Consecutive: {}",
+                "📊 Rate limit failure recorded. Total: {}, Recent ({}): {}, Consecutive: {}",
                 self.failure_count,
+                self.window_label(),
                 self.failure_timestamps.len(),
                 self.consecutive_failures
             );
         } else {
             tracing::warn!(
-                "📊 Failure recorded. Total: {}, Recent (5 min): {}, Consecutive: {}",
+                "📊 Failure recorded. Total: {}, Recent ({}): {}, Consecutive: {}",
                 self.failure_count,
+                self.window_label(),
                 self.failure_timestamps.len(),
                 self.consecutive_failures
             );
         }
     }
 
-    fn record_success(&mut self) {
+    /// Record the outcome of one job; returns whether it counted as a failure.
+    /// A "not found" page answers for a bad number, not for the site, so it
+    /// neither counts as a failure nor clears the failures before it.
+    pub fn record_result(&mut self, result: &ScraperResult) -> bool {
+        if result.success {
+            self.record_success();
+            return false;
+        }
+        if result.not_found {
+            return false;
+        }
+
+        let is_rate_limit = result.error.as_deref().is_some_and(|e| {
+            e.contains("rate limiting")
+                || e.contains("did not load results correctly")
+                || e.contains("Critical elements not found")
+        });
+        self.record_failure(is_rate_limit);
+        true
+    }
+
+    pub fn record_success(&mut self) {
         if self.failure_count > 0 {
             tracing::info!(
                 "✅ Success after {} failures ({} consecutive) - resetting counters",
@@ -145,51 +493,428 @@ impl FailureTracker {
         self.last_cooldown = None;
     }
 
-    async fn apply_cooldown_if_needed(&mut self) -> bool {
-        if self.should_cooldown() {
-            self.cooldown_active = true;
-            let cooldown_duration = 600; // Reduced from 1800 (30min) to 600 (10min)
+    /// Enter a cooldown if the threshold is reached; returns how long to wait.
+    /// The caller waits without holding the tracker, then calls `end_cooldown`.
+    fn begin_cooldown(&mut self) -> Option<Duration> {
+        if !self.should_cooldown() {
+            return None;
+        }
+        self.cooldown_active = true;
+        self.last_cooldown = Some(Self::get_current_timestamp());
+
+        let duration = Duration::from_secs(self.cooldown_secs);
+        let resume_at = chrono::Local::now()
+            + chrono::Duration::from_std(duration).unwrap_or(chrono::Duration::zero());
+        tracing::error!(
+            "🚫 Multiple failures detected ({}+ in {} with {}+ consecutive)!",
+            self.threshold,
+            self.window_label(),
+            COOLDOWN_CONSECUTIVE_FAILURES.min(self.threshold)
+        );
+        tracing::warn!(
+            "⏸️  Cooling down for {}s to avoid rate limiting - resuming at {} (Ctrl-C to stop)",
+            self.cooldown_secs,
+            resume_at.format("%Y-%m-%d %H:%M:%S")
+        );
+        Some(duration)
+    }
 
-            tracing::error!("🚫 Multiple failures detected (3+ in 5 minutes with 2+ consecutive)!");
-            tracing::warn!("⏸️  Initiating 10-minute cooldown period to avoid rate limiting...");
-            tracing::info!("💤 Sleeping for {} seconds", cooldown_duration);
+    /// Leave the cooldown; an interrupted one keeps the failures so the next
+    /// run still sees them
+    fn end_cooldown(&mut self, completed: bool) {
+        self.cooldown_active = false;
+        if completed {
+            tracing::info!("✅ Cooldown period complete - resuming operations");
+            self.failure_timestamps.clear();
+            self.consecutive_failures = 0;
+        } else {
+            tracing::warn!("Cooldown interrupted - stopping");
+        }
+    }
 
-            self.last_cooldown = Some(Self::get_current_timestamp());
+    /// Write the failure history and last cooldown to `path`
+    pub fn save_state(&self, path: &Path) -> Result<()> {
+        let state = FailureState {
+            failure_timestamps: self.failure_timestamps.clone(),
+            consecutive_failures: self.consecutive_failures,
+            last_cooldown: self.last_cooldown,
+        };
+        let json = serde_json::to_string_pretty(&state)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write failure state {}", path.display()))
+    }
 
-            // Sleep in 2-minute intervals for progress updates
-            for i in 0..5 {
-                sleep(Duration::from_secs(120)).await;
-                let remaining = (5 - i - 1) * 2;
-                if remaining > 0 {
-                    tracing::info!("⏳ Cooldown in progress: {} minutes remaining", remaining);
-                }
+    /// Restore what `save_state` wrote; a missing file leaves the tracker empty
+    pub fn load_state(&mut self, path: &Path) -> Result<()> {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read failure state {}", path.display()))
             }
+        };
+        let state: FailureState = serde_json::from_str(&json)
+            .with_context(|| format!("Invalid failure state {}", path.display()))?;
+        self.failure_count = state.failure_timestamps.len();
+        self.failure_timestamps = state.failure_timestamps;
+        self.consecutive_failures = state.consecutive_failures;
+        self.last_cooldown = state.last_cooldown;
+        Ok(())
+    }
 
-            tracing::info!("✅ Cooldown period complete - resuming operations");
+    /// Time left as of `now` of a cooldown started earlier, e.g. by a previous run
+    pub fn remaining_cooldown_at(&self, now: u64) -> Option<Duration> {
+        let ends_at = self.last_cooldown? + self.cooldown_secs;
+        (ends_at > now).then(|| Duration::from_secs(ends_at - now))
+    }
 
-            self.failure_timestamps.clear();
-            self.consecutive_failures = 0;
-            self.cooldown_active = false;
-            return true;
+    /// Re-enter a cooldown that is still running; returns how long is left
+    fn resume_cooldown(&mut self) -> Option<Duration> {
+        let remaining = self.remaining_cooldown_at(Self::get_current_timestamp())?;
+        self.cooldown_active = true;
+        tracing::warn!(
+            "⏸️  A previous run's cooldown has {}s left - waiting before scraping (Ctrl-C to stop)",
+            remaining.as_secs()
+        );
+        Some(remaining)
+    }
+
+    /// Whether the next `begin_cooldown` will start one; does not touch the
+    /// failure window, so only `begin_cooldown` evaluates (and prunes) it
+    fn is_cooldown_pending(&self) -> bool {
+        self.threshold_reached_at(Self::get_current_timestamp())
+    }
+}
+
+/// Sleep out a cooldown, logging the time left every 2 minutes. Returns `false`
+/// as soon as `stop` or `cancel` fires, `true` once the full duration has passed.
+async fn wait_out_cooldown(
+    duration: Duration,
+    stop: &StopHandle,
+    cancel: Option<&CancellationToken>,
+) -> bool {
+    let cancelled = async {
+        match cancel {
+            Some(token) => token.cancelled().await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(cancelled);
+
+    let mut remaining = duration.as_secs();
+    while remaining > 0 {
+        let step = remaining.min(COOLDOWN_PROGRESS_SECS);
+        tokio::select! {
+            _ = sleep(Duration::from_secs(step)) => {}
+            _ = stop.stopped() => return false,
+            _ = &mut cancelled => return false,
+        }
+        remaining -= step;
+        if remaining > 0 {
+            tracing::info!(
+                "⏳ Cooldown in progress: {} remaining",
+                format_remaining(remaining)
+            );
+        }
+    }
+    true
+}
+
+/// `3 minutes` or `45s` for cooldown progress messages
+fn format_remaining(secs: u64) -> String {
+    if secs >= 60 {
+        format!("{} minutes", secs.div_ceil(60))
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// A form control found on a page, as reported by `inspect-sp`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormElement {
+    pub tag: String,
+    /// The `type` attribute, if any
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub id: Option<String>,
+    /// The `value` attribute, or the text of a button
+    pub label: Option<String>,
+}
+
+impl std::fmt::Display for FormElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.tag)?;
+        if let Some(kind) = &self.kind {
+            write!(f, "[type={}]", kind)?;
+        }
+        write!(
+            f,
+            " name={} id={}",
+            self.name.as_deref().unwrap_or("-"),
+            self.id.as_deref().unwrap_or("-")
+        )?;
+        if let Some(label) = &self.label {
+            write!(f, " {:?}", label)?;
+        }
+        Ok(())
+    }
+}
+
+/// Form structure of the SP IPTU page before and after cookie consent
+#[derive(Debug, Clone)]
+pub struct FormInspection {
+    pub before_consent: Vec<FormElement>,
+    pub after_consent: Vec<FormElement>,
+    pub consent_dismissed: bool,
+}
+
+/// Every input, button, select and textarea in the page, in document order
+fn list_form_elements(html: &str) -> Vec<FormElement> {
+    let document = ::scraper::Html::parse_document(html);
+    let selector = ::scraper::Selector::parse("input, button, select, textarea")
+        .expect("static selector is valid");
+    let attr = |element: &::scraper::ElementRef, name: &str| {
+        element
+            .value()
+            .attr(name)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+
+    document
+        .select(&selector)
+        .map(|element| {
+            let tag = element.value().name().to_string();
+            let label = attr(&element, "value").or_else(|| {
+                let text = element.text().collect::<String>();
+                let text = text.trim();
+                (tag == "button" && !text.is_empty()).then(|| text.to_string())
+            });
+            FormElement {
+                kind: attr(&element, "type"),
+                name: attr(&element, "name"),
+                id: attr(&element, "id"),
+                label,
+                tag,
+            }
+        })
+        .collect()
+}
+
+/// Save the results page source under the run directory; failures are only logged
+fn save_debug_html(dir: &Path, contributor_number: &str, attempt: u32, page_content: &str) {
+    let debug_file = debug_html_path(dir, contributor_number, attempt);
+    let written =
+        std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&debug_file, page_content));
+    match written {
+        Ok(_) => tracing::info!("Debug HTML saved to: {}", debug_file.display()),
+        Err(e) => tracing::debug!(
+            "Could not save debug HTML to {}: {}",
+            debug_file.display(),
+            e
+        ),
+    }
+}
+
+/// Create the per-run directory for debug artifacts (`<debug_dir>/run_<timestamp>`);
+/// `None`, so nothing gets written, when that fails
+fn create_run_dir(debug_dir: &Path) -> Option<PathBuf> {
+    let run_id = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let run_dir = debug_dir.join(format!("run_{}", run_id));
+    match std::fs::create_dir_all(&run_dir) {
+        Ok(()) => {
+            tracing::info!("Debug artifacts will be saved to {}", run_dir.display());
+            Some(run_dir)
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Could not create debug directory {}: {} - debug HTML disabled",
+                run_dir.display(),
+                e
+            );
+            None
         }
-        false
     }
+}
 
-    fn is_cooldown_needed(&mut self) -> bool {
-        let now = Self::get_current_timestamp();
-        self.failure_timestamps.retain(|&ts| now - ts < 300);
+/// Builds a debug artifact path that is unique per contributor number, attempt and call,
+/// so retries and concurrent jobs never overwrite each other's dumps.
+fn debug_artifact_path(
+    run_dir: &Path,
+    contributor_number: &str,
+    attempt: u32,
+    extension: &str,
+) -> PathBuf {
+    let number = contributor_number.replace(['.', '-'], "");
+    let timestamp = chrono::Local::now().format("%H%M%S");
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    run_dir.join(format!(
+        "iptu_debug_{}_attempt{}_{}_{}.{}",
+        number,
+        attempt,
+        timestamp,
+        &suffix[..8],
+        extension
+    ))
+}
+
+fn debug_html_path(run_dir: &Path, contributor_number: &str, attempt: u32) -> PathBuf {
+    debug_artifact_path(run_dir, contributor_number, attempt, "html")
+}
+
+/// Random jitter added on top of each job's stagger delay
+const STAGGER_JITTER_MS: u64 = 3000;
 
-        // Quick check without full cooldown
-        self.failure_timestamps.len() >= 3 && self.consecutive_failures >= 2
+/// Delay window before launching the `index`-th job of a chunk.
+/// The first job starts immediately; job `i` waits between
+/// `base + i * step` and that plus `STAGGER_JITTER_MS`.
+fn stagger_range(index: usize, base_ms: u64, step_ms: u64) -> Option<(u64, u64)> {
+    if index == 0 {
+        return None;
     }
+    let min = base_ms + index as u64 * step_ms;
+    Some((min, min + STAGGER_JITTER_MS))
+}
+
+/// Log target of the per-job lines, which `process` hides behind `--verbose`
+pub const JOB_LOG_TARGET: &str = "iptu_cli::jobs";
+
+/// Base wait before retrying a failed scrape; grows with each attempt
+const RETRY_BACKOFF_MS: u64 = 2000;
+
+/// Wait before attempt `attempt + 1`: `attempt * RETRY_BACKOFF_MS` plus up to 1s of jitter
+fn retry_backoff(attempt: u32) -> Duration {
+    let jitter = rand::thread_rng().gen_range(0..=1000);
+    Duration::from_millis(RETRY_BACKOFF_MS * attempt as u64 + jitter)
 }
 
+/// A "not found" answer or a malformed number is final; anything else may be transient
+fn is_retryable(error: &anyhow::Error) -> bool {
+    !matches!(
+        error.downcast_ref::<ScrapeError>(),
+        Some(ScrapeError::NotFound(_) | ScrapeError::InvalidNumber(_))
+    )
+}
+
+/// Strip the dots, dashes and spaces of a contributor number and require the
+/// 11 digits the SP form expects; returns the bare digits
+pub fn validate_contributor_number(contributor_number: &str) -> Result<String> {
+    let digits: String = contributor_number
+        .chars()
+        .filter(|c| !matches!(c, '.' | '-') && !c.is_whitespace())
+        .collect();
+    if digits.len() != 11 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(ScrapeError::InvalidNumber(contributor_number.trim().to_string()).into());
+    }
+    Ok(digits)
+}
+
+/// Asks a running engine to stop once the jobs in flight are done (e.g. on Ctrl-C).
+/// Cloned handles share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle(CancellationToken);
+
+impl StopHandle {
+    pub fn request_stop(&self) {
+        self.0.cancel();
+    }
+
+    pub fn is_stop_requested(&self) -> bool {
+        self.0.is_cancelled()
+    }
+
+    /// Resolves once a stop is requested
+    pub async fn stopped(&self) {
+        self.0.cancelled().await
+    }
+}
+
+/// Spaces job starts so they never average more than `rate_limit_per_hour`.
+/// Each job reserves the next free slot; slots are `interval` apart.
+#[derive(Debug)]
+struct RatePacer {
+    interval: Duration,
+    next_start: Option<Instant>,
+}
+
+impl RatePacer {
+    /// `0` means no limit
+    fn per_hour(rate_limit_per_hour: usize) -> Self {
+        let interval = if rate_limit_per_hour > 0 {
+            Duration::from_millis(3600 * 1000 / rate_limit_per_hour as u64)
+        } else {
+            Duration::ZERO
+        };
+        Self {
+            interval,
+            next_start: None,
+        }
+    }
+
+    /// Reserve the next start slot; returns how long to wait from `now`
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let start = self.next_start.map_or(now, |next| next.max(now));
+        self.next_start = Some(start + self.interval);
+        start - now
+    }
+}
+
+/// User agents rotated across the driver pool
+const USER_AGENTS: [&str; 5] = [
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Safari/605.1.15",
+];
+
+/// Local chromedriver started by `start.chromedriver.sh`
+pub const DEFAULT_WEBDRIVER_URL: &str = "http://localhost:9515";
+
+/// Log types requested from chromedriver when console capture is enabled
+const CONSOLE_LOG_TYPES: [&str; 2] = ["browser", "performance"];
+
 pub struct ScraperConfig {
     pub max_concurrent: usize,
     pub headless: bool,
     pub timeout_secs: u64,
     pub retry_attempts: u32,
     pub rate_limit_per_hour: usize,
+    /// Enable chromedriver browser/performance logging and dump the logs for failed jobs
+    pub capture_console: bool,
+    pub selectors: ScraperSelectors,
+    /// Stagger before the 2nd+ concurrent job in a chunk (`--stagger-base`, ms)
+    pub stagger_base_ms: u64,
+    /// Extra stagger per job position in a chunk (`--stagger-step`, ms)
+    pub stagger_step_ms: u64,
+    /// Keep the page source of successful scrapes in `ScraperResult::raw_html`
+    pub store_raw_html: bool,
+    /// Save the SP cookies (accepted consent) after a run and restore them into
+    /// the pooled browsers on the next one (`--reuse-cookies`)
+    pub reuse_cookies: bool,
+    /// Proxies the pooled browsers go through (`--proxy` / `IPTU_PROXY`), assigned
+    /// round-robin so each browser can use a different one
+    pub proxies: Vec<ProxyConfig>,
+    /// WebDriver (chromedriver, geckodriver or a Selenium grid) every pooled browser is started on
+    pub webdriver_url: String,
+    /// Browser the pool runs (`--browser`)
+    pub browser: Browser,
+    /// Failures older than this no longer count towards a cooldown (`--failure-window-secs`)
+    pub failure_window_secs: u64,
+    /// Failures within the window that trigger a cooldown (`--failure-threshold`)
+    pub failure_threshold: usize,
+    /// Length of a cooldown in seconds (`--cooldown-secs`)
+    pub cooldown_secs: u64,
+    /// Load the failure history from this file on start and save it on shutdown
+    /// (`--persist-failure-state`)
+    pub failure_state_file: Option<PathBuf>,
+    /// Base directory for debug HTML, console logs and the run manifest
+    /// (`--debug-dir` / `IPTU_DEBUG_DIR`); nothing is written when unset
+    pub debug_dir: Option<PathBuf>,
+    /// Seconds to wait for the results page after submitting (`--result-wait-secs`)
+    pub result_wait_secs: u64,
 }
 
 #[allow(dead_code)]
@@ -207,6 +932,15 @@ pub struct ScraperEngine {
     config: ScraperConfig,
     driver_pool: Vec<WebDriver>,
     failure_tracker: Arc<Mutex<FailureTracker>>,
+    run_dir: Option<PathBuf>,
+    /// Every pooled browser started with a saved session that skips cookie consent
+    cookies_restored: bool,
+    /// Generated auth extension of each entry in `config.proxies`, removed on shutdown
+    proxy_extensions: Vec<Option<PathBuf>>,
+    /// Enforces `rate_limit_per_hour` across every batch of this engine
+    pacer: Mutex<RatePacer>,
+    /// Set to stop launching chunks; checked between chunks
+    stop: StopHandle,
 }
 
 // Helper functions for human-like behavior
@@ -245,72 +979,118 @@ impl ScraperEngine {
 
 impl ScraperEngine {
     pub async fn new(config: ScraperConfig) -> Result<Self> {
-        let mut driver_pool = Vec::new();
+        if config.browser == Browser::Firefox && config.capture_console {
+            tracing::warn!("Console capture is only supported on Chrome - no logs will be saved");
+        }
 
-        let user_agents = vec![
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
-            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
-            "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.0.0 Safari/537.36",
-            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Safari/605.1.15",
-        ];
+        for proxy in &config.proxies {
+            tracing::info!("Using proxy {}", proxy);
+            if config.browser == Browser::Firefox && proxy.has_credentials() {
+                anyhow::bail!("Authenticated proxies require --browser chrome ({})", proxy);
+            }
+        }
+        let proxy_extensions = config
+            .proxies
+            .iter()
+            .map(ProxyConfig::temp_auth_extension)
+            .collect::<Result<Vec<_>>>()?;
 
+        let mut driver_pool = Vec::new();
+        let mut errors = Vec::new();
         for i in 0..config.max_concurrent {
-            let mut caps = DesiredCapabilities::chrome();
-            if config.headless {
-                caps.add_chrome_arg("--headless")?;
+            // Rotate through the proxies so the pool spreads over all of them
+            let proxy = (!config.proxies.is_empty()).then(|| {
+                let slot = i % config.proxies.len();
+                (&config.proxies[slot], proxy_extensions[slot].as_deref())
+            });
+            match Self::create_driver(&config, i, &config.webdriver_url, proxy).await {
+                Ok(driver) => driver_pool.push(driver),
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to start browser {}/{}: {}",
+                        i + 1,
+                        config.max_concurrent,
+                        e
+                    );
+                    errors.push(e);
+                }
             }
-            caps.add_chrome_arg("--no-sandbox")?;
-            caps.add_chrome_arg("--disable-dev-shm-usage")?;
-            caps.add_chrome_arg("--disable-gpu")?;
-            caps.add_chrome_arg("--window-size=1920,1080")?;
-
-            let user_agent = &user_agents[i % user_agents.len()];
-            caps.add_chrome_arg(&format!("--user-agent={}", user_agent))?;
-
-            caps.add_chrome_arg("--disable-blink-features=AutomationControlled")?;
+        }
 
-            let driver = WebDriver::new("http://localhost:9515", caps).await?;
+        if driver_pool.is_empty() {
+            for extension in proxy_extensions.iter().flatten() {
+                let _ = std::fs::remove_dir_all(extension);
+            }
+            // A version mismatch affects every browser; report it as is
+            if let Some(pos) = errors
+                .iter()
+                .position(|e| e.downcast_ref::<crate::error::AppError>().is_some())
+            {
+                return Err(errors.swap_remove(pos));
+            }
+            let reasons = errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join("; ");
+            anyhow::bail!(
+                "Failed to start any of {} browser(s): {}",
+                config.max_concurrent,
+                reasons
+            );
+        }
+        if !errors.is_empty() {
+            tracing::warn!(
+                "⚠️  Only {}/{} browsers started - running with reduced concurrency",
+                driver_pool.len(),
+                config.max_concurrent
+            );
+        }
 
-            let _ = driver
-                .execute(
-                    r#"
-                Object.defineProperty(navigator, 'webdriver', {
-                    get: () => undefined
-                });
-                Object.defineProperty(navigator, 'plugins', {
-                    get: () => [1, 2, 3, 4, 5]
-                });
-                Object.defineProperty(navigator, 'languages', {
-                    get: () => ['en-US', 'en']
-                });
-                window.chrome = {
-                    runtime: {}
-                };
-                Object.defineProperty(navigator, 'permissions', {
-                    get: () => ({
-                        query: () => Promise.resolve({ state: 'granted' })
-                    })
-                });
-            "#,
-                    vec![],
-                )
-                .await;
+        let cookies_restored = if config.reuse_cookies {
+            Self::restore_cookie_session(&driver_pool, &config.selectors).await
+        } else {
+            false
+        };
 
-            driver_pool.push(driver);
+        let mut failure_tracker = FailureTracker::new(
+            config.failure_window_secs,
+            config.failure_threshold,
+            config.cooldown_secs,
+        );
+        if let Some(path) = &config.failure_state_file {
+            match failure_tracker.load_state(path) {
+                Ok(()) => tracing::info!(
+                    "Loaded failure history from {} ({} recent failure(s))",
+                    path.display(),
+                    failure_tracker.stats().recent_failures
+                ),
+                Err(e) => tracing::warn!("{:#} - starting with an empty history", e),
+            }
         }
+        let failure_tracker = Arc::new(Mutex::new(failure_tracker));
+        let run_dir = config.debug_dir.as_deref().and_then(create_run_dir);
 
         Ok(Self {
+            cookies_restored,
+            pacer: Mutex::new(RatePacer::per_hour(config.rate_limit_per_hour)),
+            stop: StopHandle::default(),
             config,
             driver_pool,
-            failure_tracker: Arc::new(Mutex::new(FailureTracker::new())),
+            failure_tracker,
+            run_dir,
+            proxy_extensions,
         })
     }
 
+    /// Scrape `jobs` in chunks of the pool size, calling `callback` for every result.
+    /// Cancelling `cancel` (or a stop request) ends the batch after the chunk in
+    /// flight, returning the results gathered so far.
     pub async fn process_batch_with_callback<F>(
         &self,
         jobs: Vec<String>,
         mut callback: F,
+        cancel: Option<&CancellationToken>,
     ) -> Vec<ScraperResult>
     where
         F: FnMut(&ScraperResult, usize, usize) + Send + 'static,
@@ -321,37 +1101,60 @@ impl ScraperEngine {
 
         tracing::info!("Processing {} jobs total", total);
         for (idx, job) in jobs.iter().enumerate() {
-            tracing::info!("Job {}: {}", idx + 1, job);
+            tracing::info!(target: JOB_LOG_TARGET, "Job {}: {}", idx + 1, job);
         }
 
-        let _delay_ms = if self.config.rate_limit_per_hour > 0 {
-            (3600 * 1000) / self.config.rate_limit_per_hour as u64
-        } else {
-            0
-        };
-
         use futures::future::join_all;
 
-        for chunk in jobs.chunks(self.config.max_concurrent) {
+        let pool_size = self.pool_size();
+        let cancelled =
+            || self.stop.is_stop_requested() || cancel.is_some_and(CancellationToken::is_cancelled);
+
+        // A cooldown left running by a previous run (`--persist-failure-state`)
+        let resumed = self.failure_tracker.lock().await.resume_cooldown();
+        if let Some(duration) = resumed {
+            let completed = wait_out_cooldown(duration, &self.stop, cancel).await;
+            self.failure_tracker.lock().await.end_cooldown(completed);
+        }
+
+        for chunk in jobs.chunks(pool_size) {
+            if cancelled() {
+                tracing::warn!(
+                    "Batch stopped - skipping the remaining {} job(s)",
+                    total - completed
+                );
+                break;
+            }
+
             let mut tasks = Vec::new();
 
             for (i, contributor_number) in chunk.iter().enumerate() {
                 let driver = self.driver_pool[i].clone();
                 let number = contributor_number.clone();
+                let run_dir = self.run_dir.clone();
+                let capture_console = self.config.capture_console;
+                let webdriver_url = self.config.webdriver_url.clone();
+                let selectors = self.config.selectors.clone();
+                let store_raw_html = self.config.store_raw_html;
+                let cookies_restored = self.cookies_restored;
+                let retry_attempts = self.config.retry_attempts.max(1);
+                let result_wait = Duration::from_secs(self.config.result_wait_secs);
 
                 tracing::info!("Launching concurrent job for: {}", number);
 
                 let mut rng = rand::thread_rng();
 
-                let base_delay = match i {
-                    0 => 0,
-                    _ => {
-                        let min = 2000 + (i as u64 * 2000);
-                        let max = 5000 + (i as u64 * 2000);
-                        rng.gen_range(min..=max)
-                    }
+                let stagger_delay = match stagger_range(
+                    i,
+                    self.config.stagger_base_ms,
+                    self.config.stagger_step_ms,
+                ) {
+                    Some((min, max)) => rng.gen_range(min..=max),
+                    None => 0,
                 };
-                let stagger_delay = base_delay;
+                // The rate limit slot and the stagger both delay the start; the longer one wins
+                let rate_delay = self.pacer.lock().await.reserve(Instant::now()).as_millis() as u64;
+                let stagger_delay = stagger_delay.max(rate_delay);
 
                 let task = async move {
                     if stagger_delay > 0 {
@@ -363,10 +1166,70 @@ impl ScraperEngine {
                         sleep(Duration::from_millis(stagger_delay)).await;
                     }
 
-                    tracing::info!("Processing job: {}", number);
+                    let mut attempt = 1;
+                    let result = loop {
+                        tracing::info!(
+                            "Processing job: {} (attempt {}/{})",
+                            number,
+                            attempt,
+                            retry_attempts
+                        );
 
-                    let result = Self::scrape_iptu_static(&driver, &number).await;
+                        let result = Self::scrape_iptu_static(
+                            &driver,
+                            &number,
+                            attempt,
+                            run_dir.as_deref(),
+                            &selectors,
+                            cookies_restored,
+                            result_wait,
+                        )
+                        .await;
+
+                        let Err(e) = &result else {
+                            break result;
+                        };
+                        if capture_console {
+                            if let Some(dir) = run_dir.as_deref() {
+                                Self::dump_console_logs(
+                                    &driver,
+                                    &webdriver_url,
+                                    &number,
+                                    attempt,
+                                    dir,
+                                )
+                                .await;
+                            }
+                        }
+                        if attempt >= retry_attempts || !is_retryable(e) {
+                            break result;
+                        }
 
+                        let backoff = retry_backoff(attempt);
+                        tracing::warn!(
+                            "Attempt {}/{} for {} failed: {} - retrying in {}ms",
+                            attempt,
+                            retry_attempts,
+                            number,
+                            e,
+                            backoff.as_millis()
+                        );
+                        sleep(backoff).await;
+                        attempt += 1;
+                    };
+
+                    let raw_html = if store_raw_html && result.is_ok() {
+                        driver.source().await.ok()
+                    } else {
+                        None
+                    };
+
+                    let not_found = result.as_ref().err().is_some_and(|e| {
+                        matches!(
+                            e.downcast_ref::<ScrapeError>(),
+                            Some(ScrapeError::NotFound(_))
+                        )
+                    });
                     let scraper_result = ScraperResult {
                         contributor_number: number.clone(),
                         numero_cadastro: result
@@ -386,8 +1249,29 @@ impl ScraperEngine {
                         complemento: result.as_ref().ok().and_then(|r| r.complemento.clone()),
                         bairro: result.as_ref().ok().and_then(|r| r.bairro.clone()),
                         cep: result.as_ref().ok().and_then(|r| r.cep.clone()),
+                        valor_venal: result.as_ref().ok().and_then(|r| r.valor_venal.clone()),
+                        area_terreno: result.as_ref().ok().and_then(|r| r.area_terreno.clone()),
+                        area_construida: result
+                            .as_ref()
+                            .ok()
+                            .and_then(|r| r.area_construida.clone()),
+                        uso: result.as_ref().ok().and_then(|r| r.uso.clone()),
                         success: result.is_ok(),
-                        error: result.err().map(|e| e.to_string()),
+                        not_found,
+                        consent_failed: result.as_ref().err().is_some_and(|e| {
+                            matches!(
+                                e.downcast_ref::<ScrapeError>(),
+                                Some(ScrapeError::ConsentFailed { .. })
+                            )
+                        }),
+                        error: result.err().map(|e| {
+                            if not_found {
+                                NOT_FOUND_ERROR.to_string()
+                            } else {
+                                e.to_string()
+                            }
+                        }),
+                        raw_html,
                     };
 
                     (number, scraper_result)
@@ -400,32 +1284,23 @@ impl ScraperEngine {
 
             for (number, scraper_result) in chunk_results {
                 completed += 1;
-                tracing::info!("Completed job {}/{}: {}", completed, total, number);
+                tracing::info!(
+                    target: JOB_LOG_TARGET,
+                    "Completed job {}/{}: {}",
+                    completed,
+                    total,
+                    number
+                );
 
                 let mut tracker = self.failure_tracker.lock().await;
-                if scraper_result.success {
-                    tracker.record_success();
-                } else {
-                    // Detect if this is a rate limit error
-                    let is_rate_limit = scraper_result
-                        .error
-                        .as_ref()
-                        .map(|e| {
-                            e.contains("rate limiting")
-                                || e.contains("did not load results correctly")
-                                || e.contains("Critical elements not found")
-                        })
-                        .unwrap_or(false);
-
-                    tracker.record_failure(is_rate_limit);
-
-                    // Only apply cooldown if it's needed, don't block unnecessarily
-                    if tracker.is_cooldown_needed() {
-                        tracing::warn!("⚠️  Cooldown may be triggered soon. Current failures: {} recent, {} consecutive",
-                            tracker.failure_timestamps.len(),
-                            tracker.consecutive_failures
-                        );
-                    }
+                // The cooldown itself starts once per chunk, after every result is in
+                if tracker.record_result(&scraper_result) && tracker.is_cooldown_pending() {
+                    let stats = tracker.stats();
+                    tracing::warn!(
+                        "⚠️  Cooldown will start after this chunk. Current failures: {} recent, {} consecutive",
+                        stats.recent_failures,
+                        stats.consecutive_failures
+                    );
                 }
                 drop(tracker);
 
@@ -434,29 +1309,239 @@ impl ScraperEngine {
                 results.push(scraper_result);
             }
 
-            // Check if we need cooldown AFTER processing the chunk
-            {
-                let mut tracker = self.failure_tracker.lock().await;
-                tracker.apply_cooldown_if_needed().await;
+            // The single cooldown check of the chunk, AFTER all its results are
+            // recorded; the tracker stays unlocked while waiting so
+            // `failure_stats` keeps answering
+            let cooldown = self.failure_tracker.lock().await.begin_cooldown();
+            if let Some(duration) = cooldown {
+                let completed = wait_out_cooldown(duration, &self.stop, cancel).await;
+                self.failure_tracker.lock().await.end_cooldown(completed);
             }
 
-            if chunk.len() == self.config.max_concurrent && completed < total {
+            if chunk.len() == pool_size && completed < total && !cancelled() {
                 let mut rng = rand::thread_rng();
-                let chunk_delay = rng.gen_range(8000..=12000);
-                tracing::info!("Waiting {}ms before processing next chunk", chunk_delay);
-                sleep(Duration::from_millis(chunk_delay)).await;
+                let chunk_delay = Duration::from_millis(rng.gen_range(8000..=12000));
+                tracing::info!(
+                    "Waiting {}ms before processing next chunk",
+                    chunk_delay.as_millis()
+                );
+                match cancel {
+                    Some(token) => {
+                        tokio::select! {
+                            _ = sleep(chunk_delay) => {}
+                            _ = token.cancelled() => {}
+                        }
+                    }
+                    None => sleep(chunk_delay).await,
+                }
             }
         }
 
+        if self.config.reuse_cookies {
+            self.save_cookie_session().await;
+        }
+
         results
     }
 
-    async fn scrape_iptu_static(driver: &WebDriver, contributor_number: &str) -> Result<IPTUData> {
+    /// Fetch the buffered browser/performance logs through chromedriver's legacy log endpoint.
+    /// Reading a log type clears chromedriver's buffer for it.
+    async fn fetch_console_logs(
+        driver: &WebDriver,
+        webdriver_url: &str,
+    ) -> Result<serde_json::Value> {
+        let session_id = driver.session_id().await?;
+        let client = reqwest::Client::new();
+        let mut logs = serde_json::Map::new();
+
+        for log_type in CONSOLE_LOG_TYPES {
+            let url = format!(
+                "{}/session/{}/se/log",
+                webdriver_url.trim_end_matches('/'),
+                session_id
+            );
+            let response: serde_json::Value = client
+                .post(&url)
+                .json(&serde_json::json!({ "type": log_type }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            logs.insert(
+                log_type.to_string(),
+                response
+                    .get("value")
+                    .cloned()
+                    .unwrap_or(serde_json::Value::Null),
+            );
+        }
+
+        Ok(serde_json::Value::Object(logs))
+    }
+
+    async fn dump_console_logs(
+        driver: &WebDriver,
+        webdriver_url: &str,
+        contributor_number: &str,
+        attempt: u32,
+        run_dir: &Path,
+    ) {
+        let logs = match Self::fetch_console_logs(driver, webdriver_url).await {
+            Ok(logs) => logs,
+            Err(e) => {
+                tracing::warn!(
+                    "Could not fetch console logs for {}: {}",
+                    contributor_number,
+                    e
+                );
+                return;
+            }
+        };
+
+        let log_file = debug_artifact_path(run_dir, contributor_number, attempt, "console.json");
+        let written = std::fs::create_dir_all(run_dir).and_then(|_| {
+            let contents = serde_json::to_vec_pretty(&logs).unwrap_or_default();
+            std::fs::write(&log_file, contents)
+        });
+        match written {
+            Ok(_) => tracing::info!("Console logs saved to: {}", log_file.display()),
+            Err(e) => tracing::debug!(
+                "Could not save console logs to {}: {}",
+                log_file.display(),
+                e
+            ),
+        }
+    }
+
+    /// Start one pooled browser; `index` selects its user agent. `proxy` comes
+    /// with its auth extension, if it needs one.
+    async fn create_driver(
+        config: &ScraperConfig,
+        index: usize,
+        webdriver_url: &str,
+        proxy: Option<(&ProxyConfig, Option<&Path>)>,
+    ) -> Result<WebDriver> {
+        let mut caps = config.browser.capabilities(config.headless)?;
+        if let Some((proxy, auth_extension)) = proxy {
+            caps.set_proxy(proxy, auth_extension)?;
+        }
+        if let BrowserCapabilities::Chrome(caps) = &mut caps {
+            let user_agent = USER_AGENTS[index % USER_AGENTS.len()];
+            caps.add_chrome_arg(&format!("--user-agent={}", user_agent))?;
+
+            caps.add_chrome_arg("--disable-blink-features=AutomationControlled")?;
+
+            if config.capture_console {
+                let prefs = CONSOLE_LOG_TYPES
+                    .iter()
+                    .map(|log_type| (log_type.to_string(), serde_json::json!("ALL")))
+                    .collect::<serde_json::Map<_, _>>();
+                caps.add("goog:loggingPrefs", prefs)?;
+            }
+        }
+
+        let driver = WebDriver::new(webdriver_url, caps)
+            .await
+            .map_err(crate::error::webdriver_start_error)?;
+
+        let _ = driver
+            .execute(
+                r#"
+            Object.defineProperty(navigator, 'webdriver', {
+                get: () => undefined
+            });
+            Object.defineProperty(navigator, 'plugins', {
+                get: () => [1, 2, 3, 4, 5]
+            });
+            Object.defineProperty(navigator, 'languages', {
+                get: () => ['en-US', 'en']
+            });
+            Object.defineProperty(navigator, 'permissions', {
+                get: () => ({
+                    query: () => Promise.resolve({ state: 'granted' })
+                })
+            });
+        "#,
+                vec![],
+            )
+            .await;
+        // Firefox has no `window.chrome`; faking one would only stand out
+        if config.browser == Browser::Chrome {
+            let _ = driver
+                .execute("window.chrome = { runtime: {} };", vec![])
+                .await;
+        }
+
+        Ok(driver)
+    }
+
+    /// Restore the saved SP cookies into every pooled browser; `true` only if the
+    /// consent banner is gone in all of them
+    async fn restore_cookie_session(
+        driver_pool: &[WebDriver],
+        selectors: &ScraperSelectors,
+    ) -> bool {
+        let store = CookieSessionStore::for_url(&selectors.site.form_url);
+        for driver in driver_pool {
+            match store.restore(driver, &selectors.cookie_consent).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::info!("No usable saved SP session - cookie consent will be handled");
+                    return false;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to restore saved SP session: {}", e);
+                    if let Err(e) = store.clear() {
+                        tracing::warn!("{}", e);
+                    }
+                    return false;
+                }
+            }
+        }
+        tracing::info!("✅ Restored SP cookies - skipping cookie consent");
+        true
+    }
+
+    /// Save the SP cookies of the first pooled browser for `--reuse-cookies`
+    async fn save_cookie_session(&self) {
+        let Some(driver) = self.driver_pool.first() else {
+            return;
+        };
+        if let Err(e) = CookieSessionStore::for_url(&self.config.selectors.site.form_url)
+            .save(driver)
+            .await
+        {
+            tracing::warn!("Failed to save SP session cookies: {}", e);
+        }
+    }
+
+    /// Handle that stops this engine after the chunk in flight
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stop.is_stop_requested()
+    }
+
+    /// Number of browsers actually running (may be below `max_concurrent`)
+    pub fn pool_size(&self) -> usize {
+        self.driver_pool.len()
+    }
+
+    async fn scrape_iptu_static(
+        driver: &WebDriver,
+        contributor_number: &str,
+        attempt: u32,
+        run_dir: Option<&Path>,
+        selectors: &ScraperSelectors,
+        cookies_restored: bool,
+        result_wait: Duration,
+    ) -> Result<IPTUData> {
         tracing::info!("Starting scrape for: {}", contributor_number);
 
-        driver
-            .goto("https://www3.prefeitura.sp.gov.br/sf8663/formsinternet/principal.aspx")
-            .await?;
+        driver.goto(&selectors.site.form_url).await?;
 
         DelayPattern::random().wait().await;
 
@@ -465,70 +1550,141 @@ impl ScraperEngine {
             let _ = Self::random_mouse_movements(driver).await;
         }
 
-        let _page_content = Self::handle_cookie_and_fill_form(driver, contributor_number).await?;
+        // With restored cookies the banner should not show up at all
+        let consent_accepted = cookies_restored
+            && driver
+                .source()
+                .await
+                .map(|html| !selectors.cookie_consent.banner_present(&html))
+                .unwrap_or(false);
+
+        Self::ensure_cookie_consent(
+            driver,
+            &selectors.cookie_consent,
+            &selectors.site.form_url,
+            consent_accepted,
+        )
+        .await?;
+
+        Self::fill_and_submit_form(
+            driver,
+            contributor_number,
+            &selectors.form_fields,
+            &selectors.site.submit_button_id,
+        )
+        .await?;
+
+        tracing::info!("Waiting for results page to load...");
+        let loaded = Self::wait_for_results(driver, selectors, result_wait).await;
+
+        let page_content = driver.source().await?;
+        let current_url = driver.current_url().await?;
+        tracing::info!("Current URL after form submit: {}", current_url);
+
+        if let Some(dir) = run_dir {
+            save_debug_html(dir, contributor_number, attempt, &page_content);
+        }
+
+        if !loaded {
+            tracing::error!(
+                "Critical elements not found after {}s - page failed to load properly",
+                result_wait.as_secs()
+            );
+            tracing::warn!("⏸️  Pausing for 120 seconds to avoid rate limiting...");
+            sleep(Duration::from_secs(120)).await;
+            anyhow::bail!("Page did not load results correctly - server may be rate limiting");
+        }
+
+        // Only a page the wait accepted can be a not-found answer
+        if let IptuPage::NotFound(pattern) = parse_iptu_html(
+            &page_content,
+            &selectors.not_found_patterns,
+            &selectors.site.result_fields,
+        ) {
+            tracing::info!(
+                "SP reported {} as not found (matched '{}')",
+                contributor_number,
+                pattern
+            );
+            return Err(ScrapeError::NotFound(pattern).into());
+        }
+
+        tracing::info!("Results page loaded successfully");
 
         if rng.gen_bool(0.4) {
             let _ = Self::random_scroll(driver).await;
         }
 
-        Self::extract_data_static(driver).await
+        Self::extract_data_static(driver, &selectors.site.result_fields).await
     }
 
-    async fn handle_cookie_and_fill_form(
+    /// Run the consent step for the freshly loaded form, applying `on_failure`
+    /// when the banner cannot be dismissed
+    async fn ensure_cookie_consent(
         driver: &WebDriver,
-        contributor_number: &str,
-    ) -> Result<String> {
+        consent: &CookieConsentConfig,
+        form_url: &str,
+        consent_accepted: bool,
+    ) -> Result<ConsentOutcome> {
+        if consent_accepted {
+            tracing::info!("Cookie consent already accepted (restored session)");
+            return Ok(ConsentOutcome::Restored);
+        }
+
+        let mut outcome = Self::dismiss_cookie_consent(driver, consent).await;
+        if let ConsentOutcome::Failed { attempts } = outcome {
+            match consent.on_failure {
+                ConsentFailurePolicy::Continue => {}
+                ConsentFailurePolicy::Abort => {
+                    return Err(ScrapeError::ConsentFailed { attempts }.into());
+                }
+                ConsentFailurePolicy::Retry => {
+                    tracing::warn!("Reloading the form to retry cookie consent...");
+                    driver.goto(form_url).await?;
+                    DelayPattern::random().wait().await;
+                    outcome = Self::dismiss_cookie_consent(driver, consent).await;
+                    if let ConsentOutcome::Failed { attempts: retried } = outcome {
+                        return Err(ScrapeError::ConsentFailed {
+                            attempts: attempts + retried,
+                        }
+                        .into());
+                    }
+                }
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Try to dismiss the cookie consent banner
+    async fn dismiss_cookie_consent(
+        driver: &WebDriver,
+        consent: &CookieConsentConfig,
+    ) -> ConsentOutcome {
         tracing::info!("Looking for cookie consent modal...");
 
         sleep(Duration::from_secs(4)).await;
 
         let mut cookie_handled = false;
-        let max_attempts = 3;
+        let mut attempts = 0;
+        let max_attempts = consent.max_attempts.max(1);
+        let script_args = vec![
+            serde_json::json!(consent.button_selectors),
+            serde_json::json!(consent.text_patterns),
+        ];
 
         for attempt in 1..=max_attempts {
             tracing::info!("Cookie consent attempt {}/{}", attempt, max_attempts);
+            attempts = attempt;
 
-            let js_direct_click = r#"
-                var buttons = document.querySelectorAll('input[type="button"], button');
-                var clicked = false;
-                for (var i = 0; i < buttons.length; i++) {
-                    var btn = buttons[i];
-                    var text = (btn.value || btn.textContent || '').toLowerCase();
-                    if (text.includes('autorizo') && text.includes('cookies')) {
-                        console.log('Found cookie button:', btn);
-                        btn.click();
-                        clicked = true;
-                        break;
-                    }
-                }
-                if (!clicked) {
-                    var cookieBtn = document.querySelector('input.cc__button__autorizacao--all');
-                    if (cookieBtn) {
-                        cookieBtn.click();
-                        clicked = true;
-                    }
-                }
-                return clicked;
-            "#;
-
-            if let Ok(result) = driver.execute(js_direct_click, vec![]).await {
+            if let Ok(result) = driver
+                .execute(CookieConsentConfig::CLICK_SCRIPT, script_args.clone())
+                .await
+            {
                 tracing::info!("JavaScript cookie consent result: {:?}", result);
                 sleep(Duration::from_secs(3)).await; // Increased from 2 to 3 seconds
 
-                let check_modal = r#"
-                    var buttons = document.querySelectorAll('input[type="button"]');
-                    for (var i = 0; i < buttons.length; i++) {
-                        var text = (buttons[i].value || '').toLowerCase();
-                        if (text.includes('autorizo') && text.includes('cookies')) {
-                            return true;
-                        }
-                    }
-                    return false;
-                "#;
-
-                if let Ok(modal_present) = driver.execute(check_modal, vec![]).await {
-                    let modal_gone = format!("{:?}", modal_present).contains("false");
-                    if modal_gone {
+                if let Ok(page_source) = driver.source().await {
+                    if !consent.banner_present(&page_source) {
                         tracing::info!("Cookie modal successfully dismissed!");
                         cookie_handled = true;
                         break;
@@ -544,98 +1700,108 @@ impl ScraperEngine {
         if cookie_handled {
             tracing::info!("Cookie consent handled successfully");
         } else {
-            tracing::warn!("Could not dismiss cookie modal, continuing anyway");
+            tracing::warn!(
+                "⚠️  Could not dismiss cookie consent banner after {} attempts (selectors: {:?}, text patterns: {:?}) - form submit may be blocked; update cookie_consent in the selectors file if the banner changed",
+                max_attempts,
+                consent.button_selectors,
+                consent.text_patterns
+            );
         }
 
-        let parts = contributor_number
-            .replace(".", "")
-            .replace("-", "")
-            .trim()
-            .to_string();
-        if parts.len() < 11 {
-            anyhow::bail!("Número de cadastro inválido");
+        if cookie_handled {
+            ConsentOutcome::Accepted { attempts }
+        } else {
+            ConsentOutcome::Failed { attempts }
         }
+    }
+
+    async fn fill_and_submit_form(
+        driver: &WebDriver,
+        contributor_number: &str,
+        form: &FormFieldsConfig,
+        submit_button_id: &str,
+    ) -> Result<()> {
+        let parts = validate_contributor_number(contributor_number)?;
 
         tracing::info!("Looking for form input fields...");
-        let inputs = driver.find_all(By::Css("input[type='text']")).await?;
+        let inputs = driver.find_all(By::Css(&form.input_selector)).await?;
         tracing::info!("Found {} input fields", inputs.len());
 
-        if inputs.len() < 4 {
+        if inputs.is_empty() {
             anyhow::bail!("Campos de entrada não encontrados");
         }
+        let values = form.split_number(&parts, inputs.len())?;
 
         tracing::info!("Filling contributor number: {}", parts);
 
         let mut rng = rand::thread_rng();
 
-        inputs[0].clear().await?;
-        sleep(Duration::from_millis(rng.gen_range(300..700))).await;
-        inputs[0].send_keys(&parts[0..3]).await?;
-        tracing::info!("Filled field 1: {}", &parts[0..3]);
-        sleep(Duration::from_millis(rng.gen_range(400..900))).await;
-
-        inputs[1].clear().await?;
-        sleep(Duration::from_millis(rng.gen_range(300..700))).await;
-        inputs[1].send_keys(&parts[3..6]).await?;
-        tracing::info!("Filled field 2: {}", &parts[3..6]);
-        sleep(Duration::from_millis(rng.gen_range(400..900))).await;
-
-        inputs[2].clear().await?;
-        sleep(Duration::from_millis(rng.gen_range(300..700))).await;
-        inputs[2].send_keys(&parts[6..10]).await?;
-        tracing::info!("Filled field 3: {}", &parts[6..10]);
-        sleep(Duration::from_millis(rng.gen_range(400..900))).await;
-
-        inputs[3].clear().await?;
-        sleep(Duration::from_millis(rng.gen_range(300..700))).await;
-        inputs[3].send_keys(&parts[10..11]).await?;
-        tracing::info!("Filled field 4: {}", &parts[10..11]);
+        for (index, (input, value)) in inputs.iter().zip(&values).enumerate() {
+            if index > 0 {
+                sleep(Duration::from_millis(rng.gen_range(400..900))).await;
+            }
+            input.clear().await?;
+            sleep(Duration::from_millis(rng.gen_range(300..700))).await;
+            input.send_keys(value).await?;
+            tracing::info!("Filled field {}: {}", index + 1, value);
+        }
 
         sleep(Duration::from_secs(3)).await;
 
         tracing::info!("Submitting form...");
 
         let click_script = r#"
-            var btn = document.getElementById('_BtnAvancarDasii');
+            var btn = document.getElementById(arguments[0]);
             if (btn) {
+                document.documentElement.setAttribute(arguments[1], '1');
                 btn.click();
                 return true;
             }
             return false;
         "#;
 
-        if let Ok(result) = driver.execute(click_script, vec![]).await {
+        if let Ok(result) = driver
+            .execute(
+                click_script,
+                vec![
+                    serde_json::json!(submit_button_id),
+                    serde_json::json!(SUBMIT_MARKER),
+                ],
+            )
+            .await
+        {
             tracing::info!("Form submitted via JavaScript click: {:?}", result);
         }
 
-        tracing::info!("Waiting for results page to load...");
-        sleep(Duration::from_secs(12)).await;
-
-        let page_content = driver.source().await?;
-        let current_url = driver.current_url().await?;
-        tracing::info!("Current URL after form submit: {}", current_url);
+        Ok(())
+    }
 
-        if let Ok(home) = std::env::var("HOME") {
-            let debug_file = format!(
-                "{}/Desktop/iptus/iptu_debug_{}.html",
-                home,
-                contributor_number.replace(".", "")
-            );
-            if let Ok(_) = std::fs::write(&debug_file, &page_content) {
-                tracing::info!("Debug HTML saved to: {}", debug_file);
+    /// Poll the page until `results_ready` or `timeout` runs out; `false` on timeout
+    async fn wait_for_results(
+        driver: &WebDriver,
+        selectors: &ScraperSelectors,
+        timeout: Duration,
+    ) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Ok(html) = driver.source().await {
+                if results_ready(&html, selectors) {
+                    return true;
+                }
             }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            sleep(Duration::from_millis(RESULT_POLL_MS)).await;
         }
-
-        tracing::info!("Results page loaded successfully");
-        Ok(page_content)
     }
 
-    async fn extract_data_static(driver: &WebDriver) -> Result<IPTUData> {
+    async fn extract_data_static(
+        driver: &WebDriver,
+        fields: &ResultFieldNames,
+    ) -> Result<IPTUData> {
         let mut data = IPTUData::default();
 
-        tracing::info!("Waiting for page content to stabilize...");
-        sleep(Duration::from_secs(5)).await;
-
         async fn get_element_value(elem: &WebElement) -> Option<String> {
             if let Ok(Some(value)) = elem.prop("value").await {
                 if !value.is_empty() {
@@ -655,82 +1821,82 @@ impl ScraperEngine {
             None
         }
 
-        let has_iptu = driver.find(By::Name("txtNumIPTU")).await.is_ok();
-        let has_proprietario = driver.find(By::Name("txtProprietarioNome")).await.is_ok();
-
-        if !has_iptu && !has_proprietario {
-            tracing::error!("Critical elements not found - page failed to load properly");
-            tracing::warn!("⏸️  Pausing for 120 seconds to avoid rate limiting...");
-            sleep(Duration::from_secs(120)).await;
-            anyhow::bail!("Page did not load results correctly - server may be rate limiting");
+        for (name, value) in result_field_slots(fields, &mut data) {
+            if let Ok(elem) = driver.find(By::Name(name)).await {
+                *value = get_element_value(&elem).await;
+                tracing::debug!("Found {}: {:?}", name, value);
+            } else {
+                tracing::debug!("{} element not found (may be empty)", name);
+            }
         }
 
-        if let Ok(elem) = driver.find(By::Name("txtNumIPTU")).await {
-            data.numero_cadastro = get_element_value(&elem).await;
-            tracing::debug!("Found txtNumIPTU: {:?}", data.numero_cadastro);
-        } else {
-            tracing::debug!("txtNumIPTU element not found (empty)");
+        // Fall back to the static page source for anything the live DOM lookups missed
+        if let Ok(source) = driver.source().await {
+            if let IptuPage::Data(parsed) = parse_iptu_html(&source, &[], fields) {
+                data.fill_missing(*parsed);
+            }
         }
 
-        if let Ok(elem) = driver.find(By::Name("txtProprietarioNome")).await {
-            data.nome_proprietario = get_element_value(&elem).await;
-            tracing::debug!("Found txtProprietarioNome: {:?}", data.nome_proprietario);
-        } else {
-            tracing::debug!("txtProprietarioNome element not found (empty)");
-        }
+        Ok(data)
+    }
 
-        if let Ok(elem) = driver.find(By::Name("txtCompromissarioNome")).await {
-            data.nome_compromissario = get_element_value(&elem).await;
-            tracing::debug!(
-                "Found txtCompromissarioNome: {:?}",
-                data.nome_compromissario
-            );
-        } else {
-            tracing::debug!("No txtCompromissarioNome element (may be empty)");
-        }
+    /// Open the SP form in the first pooled browser and list its form controls
+    /// before and after cookie consent, to see which selectors need updating
+    pub async fn inspect_form(&self) -> Result<FormInspection> {
+        let driver = self
+            .driver_pool
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("No browser session available"))?;
 
-        if let Ok(elem) = driver.find(By::Name("txtEndereco")).await {
-            data.endereco = get_element_value(&elem).await;
-            tracing::debug!("Found txtEndereco: {:?}", data.endereco);
-        } else {
-            tracing::debug!("txtEndereco element not found (empty)");
-        }
+        driver.goto(&self.config.selectors.site.form_url).await?;
+        sleep(Duration::from_secs(3)).await;
+        let before_consent = list_form_elements(&driver.source().await?);
+
+        let consent_dismissed =
+            Self::dismiss_cookie_consent(driver, &self.config.selectors.cookie_consent)
+                .await
+                .is_accepted();
+        let after_consent = list_form_elements(&driver.source().await?);
+
+        Ok(FormInspection {
+            before_consent,
+            after_consent,
+            consent_dismissed,
+        })
+    }
 
-        if let Ok(elem) = driver.find(By::Name("txtNumero")).await {
-            data.numero = get_element_value(&elem).await;
-            tracing::debug!("Found txtNumero: {:?}", data.numero);
-        } else {
-            tracing::debug!("txtNumero element not found (empty)");
-        }
+    /// Directory where this run's debug artifacts and manifest are written
+    pub fn run_dir(&self) -> Option<&Path> {
+        self.run_dir.as_deref()
+    }
 
-        if let Ok(elem) = driver.find(By::Name("txtComplemento")).await {
-            data.complemento = get_element_value(&elem).await;
-            tracing::debug!("Found txtComplemento: {:?}", data.complemento);
-        } else {
-            tracing::debug!("No txtComplemento element (may be empty)");
-        }
+    /// Current failure/cooldown state of this engine
+    pub async fn failure_stats(&self) -> FailureStats {
+        self.failure_tracker.lock().await.stats()
+    }
 
-        if let Ok(elem) = driver.find(By::Name("txtBairro")).await {
-            data.bairro = get_element_value(&elem).await;
-            tracing::debug!("Found txtBairro: {:?}", data.bairro);
-        } else {
-            tracing::debug!("txtBairro element not found (empty)");
+    pub async fn shutdown(self) {
+        if let Some(path) = &self.config.failure_state_file {
+            if let Err(e) = self.failure_tracker.lock().await.save_state(path) {
+                tracing::warn!("{:#}", e);
+            }
         }
 
-        if let Ok(elem) = driver.find(By::Name("txtCepImovel")).await {
-            data.cep = get_element_value(&elem).await;
-            tracing::debug!("Found txtCepImovel: {:?}", data.cep);
-        } else {
-            tracing::debug!("txtCepImovel element not found (empty)");
+        // Quit every session so no browser or chromedriver child is left behind
+        let total = self.driver_pool.len();
+        let quits = futures::future::join_all(self.driver_pool.into_iter().map(WebDriver::quit));
+        let errors: Vec<_> = quits.await.into_iter().filter_map(Result::err).collect();
+        for e in &errors {
+            tracing::warn!("Failed to close browser session: {}", e);
         }
-
-        Ok(data)
-    }
-
-    pub async fn shutdown(self) {
-        // Clean shutdown of all drivers
-        for driver in self.driver_pool {
-            let _ = driver.quit().await;
+        tracing::info!(
+            "Closed {}/{} browser session(s)",
+            total - errors.len(),
+            total
+        );
+
+        for extension in self.proxy_extensions.into_iter().flatten() {
+            let _ = std::fs::remove_dir_all(extension);
         }
     }
 }
@@ -741,16 +1907,16 @@ mod tests {
 
     #[test]
     fn test_failure_tracker_new() {
-        let tracker = FailureTracker::new();
+        let tracker = FailureTracker::default();
         assert_eq!(tracker.failure_count, 0);
         assert_eq!(tracker.failure_timestamps.len(), 0);
-        assert_eq!(tracker.cooldown_active, false);
+        assert!(!tracker.cooldown_active);
         assert!(tracker.last_cooldown.is_none());
     }
 
     #[test]
     fn test_failure_tracker_record_failure() {
-        let mut tracker = FailureTracker::new();
+        let mut tracker = FailureTracker::default();
 
         tracker.record_failure(false);
         assert_eq!(tracker.failure_count, 1);
@@ -765,7 +1931,7 @@ mod tests {
 
     #[test]
     fn test_failure_tracker_record_success() {
-        let mut tracker = FailureTracker::new();
+        let mut tracker = FailureTracker::default();
 
         tracker.record_failure(false);
         tracker.record_failure(false);
@@ -775,12 +1941,12 @@ mod tests {
         assert_eq!(tracker.failure_count, 0);
         assert_eq!(tracker.failure_timestamps.len(), 0);
         assert_eq!(tracker.consecutive_failures, 0);
-        assert_eq!(tracker.cooldown_active, false);
+        assert!(!tracker.cooldown_active);
     }
 
     #[test]
     fn test_should_cooldown_with_three_recent_failures() {
-        let mut tracker = FailureTracker::new();
+        let mut tracker = FailureTracker::default();
 
         tracker.record_failure(false);
         tracker.record_failure(false);
@@ -791,7 +1957,7 @@ mod tests {
 
     #[test]
     fn test_should_not_cooldown_with_two_failures() {
-        let mut tracker = FailureTracker::new();
+        let mut tracker = FailureTracker::default();
 
         tracker.record_failure(false);
         tracker.record_failure(false);
@@ -801,7 +1967,7 @@ mod tests {
 
     #[test]
     fn test_old_failures_are_cleaned_up() {
-        let mut tracker = FailureTracker::new();
+        let mut tracker = FailureTracker::default();
 
         // Old timestamps (older than 5 minutes = 300 seconds)
         let old_timestamp = FailureTracker::get_current_timestamp() - 400;
@@ -814,9 +1980,72 @@ mod tests {
         assert_eq!(tracker.failure_timestamps.len(), 0);
     }
 
+    #[test]
+    fn test_failure_tracker_custom_thresholds() {
+        let start = 1_700_000_000;
+
+        // A threshold of 1 cools down on the first failure
+        let mut tracker = FailureTracker::new(60, 1, 30);
+        tracker.record_failure_at(false, start);
+        assert!(tracker.should_cooldown_at(start + 59));
+        assert!(!tracker.should_cooldown_at(start + 60));
+
+        let mut tracker = FailureTracker::new(600, 5, 1200);
+        for offset in 0..4 {
+            tracker.record_failure_at(false, start + offset * 100);
+        }
+        assert!(!tracker.should_cooldown_at(start + 400));
+        tracker.record_failure_at(false, start + 500);
+        assert!(tracker.should_cooldown_at(start + 500));
+        assert_eq!(tracker.stats_at(start + 599).recent_failures, 5);
+        assert_eq!(tracker.stats_at(start + 600).recent_failures, 4);
+    }
+
+    #[test]
+    fn test_failures_leave_window_after_five_minutes() {
+        let mut tracker = FailureTracker::default();
+        let start = 1_700_000_000;
+
+        tracker.record_failure_at(false, start);
+        tracker.record_failure_at(false, start + 60);
+        tracker.record_failure_at(true, start + 120);
+        assert_eq!(tracker.stats_at(start + 120).recent_failures, 3);
+        assert!(tracker.should_cooldown_at(start + 120));
+
+        // The first failure falls out of the window exactly 300s after it was recorded
+        assert_eq!(tracker.stats_at(start + 299).recent_failures, 3);
+        assert_eq!(tracker.stats_at(start + 300).recent_failures, 2);
+        assert!(!tracker.should_cooldown_at(start + 300));
+        assert_eq!(tracker.failure_timestamps.len(), 2);
+
+        // Eviction only affects the window; totals keep counting until a success
+        let stats = tracker.stats_at(start + 300);
+        assert_eq!(stats.total_failures, 3);
+        assert_eq!(stats.consecutive_failures, 3);
+        assert!(!stats.cooldown_active);
+    }
+
+    #[test]
+    fn test_stats_reset_on_success() {
+        let mut tracker = FailureTracker::default();
+        tracker.record_failure(false);
+        tracker.record_failure(false);
+
+        tracker.record_success();
+        assert_eq!(
+            tracker.stats(),
+            FailureStats {
+                total_failures: 0,
+                recent_failures: 0,
+                consecutive_failures: 0,
+                cooldown_active: false,
+            }
+        );
+    }
+
     #[tokio::test]
     async fn test_apply_cooldown_if_needed() {
-        let mut tracker = FailureTracker::new();
+        let mut tracker = FailureTracker::default();
 
         tracker.record_failure(false);
         tracker.record_failure(false);
@@ -825,6 +2054,98 @@ mod tests {
         assert!(tracker.should_cooldown());
     }
 
+    #[test]
+    fn test_failure_state_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("failure_state_{}.json", uuid::Uuid::new_v4()));
+        let now = FailureTracker::get_current_timestamp();
+
+        let mut tracker = FailureTracker::new(300, 2, 600);
+        tracker.record_failure_at(true, now - 20);
+        tracker.record_failure_at(true, now - 10);
+        assert!(tracker.begin_cooldown().is_some());
+        tracker.save_state(&path).unwrap();
+
+        let mut restored = FailureTracker::new(300, 2, 600);
+        restored.load_state(&path).unwrap();
+        assert_eq!(restored.failure_timestamps, vec![now - 20, now - 10]);
+        assert_eq!(restored.consecutive_failures, 2);
+        let remaining = restored.remaining_cooldown_at(now).unwrap();
+        assert!(remaining <= Duration::from_secs(600) && remaining >= Duration::from_secs(590));
+        assert!(restored.resume_cooldown().is_some());
+        assert!(restored.stats().cooldown_active);
+
+        let last_cooldown = restored.last_cooldown.unwrap();
+        assert_eq!(restored.remaining_cooldown_at(last_cooldown + 600), None);
+
+        std::fs::remove_file(&path).ok();
+
+        // No file yet: nothing to restore
+        let mut fresh = FailureTracker::default();
+        fresh.load_state(&path).unwrap();
+        assert_eq!(fresh.stats().total_failures, 0);
+        assert_eq!(fresh.remaining_cooldown_at(now), None);
+    }
+
+    #[test]
+    fn test_failure_spike_fires_one_cooldown() {
+        let mut tracker = FailureTracker::new(300, 2, 600);
+
+        // Two rapid failures within one chunk
+        tracker.record_failure(true);
+        assert!(!tracker.is_cooldown_pending());
+        tracker.record_failure(true);
+        assert!(tracker.is_cooldown_pending());
+        // Checking the pending state leaves the window alone
+        assert!(tracker.is_cooldown_pending());
+        assert_eq!(tracker.failure_timestamps.len(), 2);
+
+        let mut cooldowns = 0;
+        for _ in 0..3 {
+            if tracker.begin_cooldown().is_some() {
+                cooldowns += 1;
+                tracker.end_cooldown(true);
+            }
+        }
+        assert_eq!(cooldowns, 1);
+
+        // A single failure after the cooldown is not a new spike
+        tracker.record_failure(true);
+        assert!(tracker.begin_cooldown().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_ends_early_on_stop() {
+        let mut tracker = FailureTracker::new(300, 1, 600);
+        tracker.record_failure(true);
+        let duration = tracker.begin_cooldown().expect("threshold reached");
+        assert!(tracker.stats().cooldown_active);
+
+        let stop = StopHandle::default();
+        let handle = stop.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(50)).await;
+            handle.request_stop();
+        });
+        let completed = tokio::time::timeout(
+            Duration::from_secs(5),
+            wait_out_cooldown(duration, &stop, None),
+        )
+        .await
+        .expect("cooldown returns promptly after a stop");
+        assert!(!completed);
+
+        tracker.end_cooldown(completed);
+        let stats = tracker.stats();
+        assert!(!stats.cooldown_active);
+        assert_eq!(stats.recent_failures, 1);
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert!(!wait_out_cooldown(duration, &StopHandle::default(), Some(&cancel)).await);
+        assert!(wait_out_cooldown(Duration::ZERO, &StopHandle::default(), None).await);
+    }
+
     #[test]
     fn test_scraper_result_creation() {
         let result = ScraperResult {
@@ -837,8 +2158,15 @@ mod tests {
             complemento: None,
             bairro: Some("Centro".to_string()),
             cep: Some("12345-678".to_string()),
+            valor_venal: None,
+            area_terreno: None,
+            area_construida: None,
+            uso: None,
             success: true,
+            not_found: false,
+            consent_failed: false,
             error: None,
+            raw_html: None,
         };
 
         assert_eq!(result.contributor_number, "123.456.789-0");
@@ -846,6 +2174,43 @@ mod tests {
         assert!(result.error.is_none());
     }
 
+    #[test]
+    fn test_not_found_results_leave_the_failure_tracker_alone() {
+        let result = |success: bool, not_found: bool, error: Option<&str>| ScraperResult {
+            contributor_number: "00804501234".to_string(),
+            numero_cadastro: None,
+            nome_proprietario: None,
+            nome_compromissario: None,
+            endereco: None,
+            numero: None,
+            complemento: None,
+            bairro: None,
+            cep: None,
+            valor_venal: None,
+            area_terreno: None,
+            area_construida: None,
+            uso: None,
+            success,
+            not_found,
+            consent_failed: false,
+            error: error.map(str::to_string),
+            raw_html: None,
+        };
+        let mut tracker = FailureTracker::default();
+
+        assert!(tracker.record_result(&result(false, false, Some("Failed to load page"))));
+        assert!(!tracker.record_result(&result(false, true, Some(NOT_FOUND_ERROR))));
+        assert!(!tracker.record_result(&result(false, true, Some(NOT_FOUND_ERROR))));
+        assert_eq!(tracker.failure_count, 1);
+        assert_eq!(tracker.consecutive_failures, 1);
+
+        assert!(tracker.record_result(&result(false, false, Some("Failed to load page"))));
+        assert_eq!(tracker.consecutive_failures, 2);
+
+        assert!(!tracker.record_result(&result(true, false, None)));
+        assert_eq!(tracker.failure_count, 0);
+    }
+
     #[test]
     fn test_scraper_result_with_error() {
         let result = ScraperResult {
@@ -858,8 +2223,15 @@ mod tests {
             complemento: None,
             bairro: None,
             cep: None,
+            valor_venal: None,
+            area_terreno: None,
+            area_construida: None,
+            uso: None,
             success: false,
+            not_found: false,
+            consent_failed: false,
             error: Some("Failed to load page".to_string()),
+            raw_html: None,
         };
 
         assert!(!result.success);
@@ -873,7 +2245,6 @@ mod tests {
         match pattern {
             DelayPattern::Quick | DelayPattern::Normal | DelayPattern::Slow => {
                 // Valid pattern
-                assert!(true);
             }
         }
     }
@@ -886,18 +2257,97 @@ mod tests {
             timeout_secs: 30,
             retry_attempts: 3,
             rate_limit_per_hour: 100,
+            capture_console: false,
+            selectors: ScraperSelectors::default(),
+            stagger_base_ms: 2000,
+            stagger_step_ms: 2000,
+            store_raw_html: false,
+            reuse_cookies: false,
+            proxies: Vec::new(),
+            browser: Browser::Chrome,
+            webdriver_url: DEFAULT_WEBDRIVER_URL.to_string(),
+            failure_window_secs: DEFAULT_FAILURE_WINDOW_SECS,
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            cooldown_secs: DEFAULT_COOLDOWN_SECS,
+            failure_state_file: None,
+            debug_dir: None,
+            result_wait_secs: DEFAULT_RESULT_WAIT_SECS,
         };
 
         assert_eq!(config.max_concurrent, 5);
-        assert_eq!(config.headless, true);
+        assert!(config.headless);
         assert_eq!(config.timeout_secs(), 30);
         assert_eq!(config.retry_attempts(), 3);
         assert_eq!(config.rate_limit_per_hour, 100);
     }
 
+    #[test]
+    fn test_stop_handle_clones_share_the_flag() {
+        let stop = StopHandle::default();
+        let handle = stop.clone();
+        assert!(!stop.is_stop_requested());
+
+        handle.request_stop();
+        assert!(stop.is_stop_requested());
+    }
+
+    #[test]
+    fn test_rate_pacer_spaces_job_starts() {
+        let now = Instant::now();
+        let mut pacer = RatePacer::per_hour(120);
+        assert_eq!(pacer.reserve(now), Duration::ZERO);
+        assert_eq!(pacer.reserve(now), Duration::from_secs(30));
+        assert_eq!(pacer.reserve(now), Duration::from_secs(60));
+        // Slots already in the past are not made up for
+        assert_eq!(
+            pacer.reserve(now + Duration::from_secs(600)),
+            Duration::ZERO
+        );
+
+        let mut unlimited = RatePacer::per_hour(0);
+        assert_eq!(unlimited.reserve(now), Duration::ZERO);
+        assert_eq!(unlimited.reserve(now), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_validate_contributor_number() {
+        assert_eq!(
+            validate_contributor_number(" 001.123.4567-8 ").unwrap(),
+            "00112345678"
+        );
+        assert_eq!(
+            validate_contributor_number("00112345678").unwrap(),
+            "00112345678"
+        );
+        assert!(validate_contributor_number("001.123.4567").is_err());
+        assert!(validate_contributor_number("001123456789").is_err());
+        assert!(validate_contributor_number("001.123.456A-8").is_err());
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        assert!(is_retryable(&anyhow::anyhow!(
+            "Page did not load results correctly - server may be rate limiting"
+        )));
+        assert!(is_retryable(
+            &ScrapeError::ConsentFailed { attempts: 3 }.into()
+        ));
+        assert!(!is_retryable(
+            &ScrapeError::NotFound("não encontrado".to_string()).into()
+        ));
+        assert!(!is_retryable(
+            &validate_contributor_number("123").unwrap_err()
+        ));
+
+        let first = retry_backoff(1).as_millis();
+        let third = retry_backoff(3).as_millis();
+        assert!((2000..=3000).contains(&first));
+        assert!((6000..=7000).contains(&third));
+    }
+
     #[tokio::test]
     async fn test_scraper_engine_failure_tracking() {
-        let tracker = Arc::new(Mutex::new(FailureTracker::new()));
+        let tracker = Arc::new(Mutex::new(FailureTracker::default()));
 
         {
             let mut t = tracker.lock().await;
@@ -915,6 +2365,188 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_debug_html_path_is_unique_per_attempt() {
+        let dir = Path::new("/tmp/iptus/run_test");
+        let first = debug_html_path(dir, "123.456.7890-1", 1);
+        let second = debug_html_path(dir, "123.456.7890-1", 1);
+        let retry = debug_html_path(dir, "123.456.7890-1", 2);
+
+        assert!(first.starts_with(dir));
+        let name = first.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("iptu_debug_12345678901_attempt1_"));
+        assert!(name.ends_with(".html"));
+        assert_ne!(first, second);
+        assert!(retry
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .contains("_attempt2_"));
+    }
+
+    #[test]
+    fn test_stagger_range_defaults_match_previous_curve() {
+        assert_eq!(stagger_range(0, 2000, 2000), None);
+        assert_eq!(stagger_range(1, 2000, 2000), Some((4000, 7000)));
+        assert_eq!(stagger_range(3, 2000, 2000), Some((8000, 11000)));
+    }
+
+    #[test]
+    fn test_stagger_range_can_be_flattened() {
+        assert_eq!(stagger_range(7, 0, 0), Some((0, STAGGER_JITTER_MS)));
+        assert_eq!(stagger_range(2, 500, 250), Some((1000, 4000)));
+    }
+
+    #[test]
+    fn test_parse_iptu_html_fixture() {
+        let html = include_str!("../../tests/fixtures/sp_iptu_result.html");
+        let IptuPage::Data(data) = parse_iptu_html(
+            html,
+            &ScraperSelectors::default().not_found_patterns,
+            &ResultFieldNames::default(),
+        ) else {
+            panic!("result fixture must not be detected as not found");
+        };
+
+        assert_eq!(data.numero_cadastro.as_deref(), Some("008.045.0123-4"));
+        assert_eq!(
+            data.nome_proprietario.as_deref(),
+            Some("MARIA APARECIDA DOS SANTOS")
+        );
+        assert_eq!(data.nome_compromissario, None);
+        assert_eq!(data.endereco.as_deref(), Some("R DOMINGOS LEME"));
+        assert_eq!(data.numero.as_deref(), Some("440"));
+        assert_eq!(data.complemento.as_deref(), Some("AP 52"));
+        assert_eq!(data.bairro.as_deref(), Some("VILA NOVA CONCEICAO"));
+        assert_eq!(data.cep.as_deref(), Some("04510-000"));
+        assert_eq!(data.valor_venal.as_deref(), Some("R$ 1.234.567,89"));
+        assert_eq!(data.area_terreno.as_deref(), Some("1.250"));
+        assert_eq!(data.area_construida.as_deref(), Some("98"));
+        assert_eq!(data.uso.as_deref(), Some("RESIDENCIAL VERTICAL"));
+    }
+
+    #[test]
+    fn test_parse_iptu_html_not_found_fixture() {
+        let html = include_str!("../../tests/fixtures/sp_iptu_not_found.html");
+
+        match parse_iptu_html(
+            html,
+            &ScraperSelectors::default().not_found_patterns,
+            &ResultFieldNames::default(),
+        ) {
+            IptuPage::NotFound(pattern) => assert_eq!(pattern, "contribuinte não encontrado"),
+            IptuPage::Data(data) => panic!("expected NotFound, got {:?}", data),
+        }
+        assert!(matches!(
+            parse_iptu_html(html, &[], &ResultFieldNames::default()),
+            IptuPage::Data(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_iptu_html_ignores_notices_on_data_page() {
+        let html = include_str!("../../tests/fixtures/sp_iptu_result_with_notice.html");
+        let selectors = ScraperSelectors::default();
+
+        // Script and hidden validator text never count, visible text does
+        let document = ::scraper::Html::parse_document(html);
+        assert_eq!(
+            not_found_match(&document, &selectors.not_found_patterns).map(String::as_str),
+            Some("contribuinte inexistente")
+        );
+
+        match parse_iptu_html(
+            html,
+            &selectors.not_found_patterns,
+            &ResultFieldNames::default(),
+        ) {
+            IptuPage::Data(data) => {
+                assert_eq!(data.numero_cadastro.as_deref(), Some("008.045.0123-4"))
+            }
+            IptuPage::NotFound(pattern) => panic!("data page taken as not found ({})", pattern),
+        }
+    }
+
+    #[test]
+    fn test_results_ready() {
+        let selectors = ScraperSelectors::default();
+
+        assert!(results_ready(
+            include_str!("../../tests/fixtures/sp_iptu_result.html"),
+            &selectors
+        ));
+        assert!(results_ready(
+            include_str!("../../tests/fixtures/sp_iptu_not_found.html"),
+            &selectors
+        ));
+        assert!(!results_ready(
+            include_str!("../../tests/fixtures/sp_cookie_banner.html"),
+            &selectors
+        ));
+        // The form page right after the click still shows an old message
+        assert!(!results_ready(
+            include_str!("../../tests/fixtures/sp_iptu_form_submitting.html"),
+            &selectors
+        ));
+    }
+
+    #[test]
+    fn test_list_form_elements_from_fixtures() {
+        let banner = list_form_elements(include_str!("../../tests/fixtures/sp_cookie_banner.html"));
+        assert!(banner
+            .iter()
+            .any(|element| element.kind.as_deref() == Some("button")
+                && element
+                    .label
+                    .as_deref()
+                    .is_some_and(|label| label.contains("cookies"))));
+
+        let form = list_form_elements(include_str!("../../tests/fixtures/sp_iptu_not_found.html"));
+        let names: Vec<_> = form.iter().filter_map(|e| e.name.as_deref()).collect();
+        assert_eq!(
+            names,
+            [
+                "txtNumContribuinte1",
+                "txtNumContribuinte2",
+                "txtNumContribuinte3",
+                "txtNumContribuinte4",
+                "_BtnAvancarDasii"
+            ]
+        );
+        assert_eq!(
+            form[4].to_string(),
+            "input[type=submit] name=_BtnAvancarDasii id=_BtnAvancarDasii \"Avançar\""
+        );
+    }
+
+    #[test]
+    fn test_fill_missing_keeps_live_values() {
+        let mut live = IPTUData {
+            nome_proprietario: Some("LIVE OWNER".to_string()),
+            ..Default::default()
+        };
+        live.fill_missing(IPTUData {
+            nome_proprietario: Some("STATIC OWNER".to_string()),
+            uso: Some("COMERCIAL".to_string()),
+            ..Default::default()
+        });
+
+        assert_eq!(live.nome_proprietario.as_deref(), Some("LIVE OWNER"));
+        assert_eq!(live.uso.as_deref(), Some("COMERCIAL"));
+    }
+
+    #[test]
+    fn test_console_log_path_sits_next_to_debug_html() {
+        let dir = Path::new("/tmp/iptus/run_test");
+        let html = debug_html_path(dir, "123.456.7890-1", 1);
+        let console = debug_artifact_path(dir, "123.456.7890-1", 1, "console.json");
+
+        assert_eq!(html.parent(), console.parent());
+        let name = console.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("iptu_debug_12345678901_attempt1_"));
+        assert!(name.ends_with(".console.json"));
+    }
+
     #[test]
     fn test_get_current_timestamp() {
         let timestamp = FailureTracker::get_current_timestamp();
@@ -924,7 +2556,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_concurrent_failure_tracking() {
-        let tracker = Arc::new(Mutex::new(FailureTracker::new()));
+        let tracker = Arc::new(Mutex::new(FailureTracker::default()));
 
         // Simulate concurrent access
         let tracker1 = tracker.clone();
@@ -951,7 +2583,7 @@ mod tests {
 
     #[test]
     fn test_mixed_success_failure_scenarios() {
-        let mut tracker = FailureTracker::new();
+        let mut tracker = FailureTracker::default();
 
         tracker.record_failure(false);
         assert_eq!(tracker.failure_count, 1);
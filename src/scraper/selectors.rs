@@ -0,0 +1,363 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Site-specific selectors for the SP IPTU page, loadable from a JSON file
+/// (`--selectors-file` / `IPTU_SELECTORS_FILE`) so markup changes don't need a rebuild.
+/// Missing keys fall back to the built-in defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScraperSelectors {
+    pub cookie_consent: CookieConsentConfig,
+    pub form_fields: FormFieldsConfig,
+    /// Phrases (case-insensitive) that mark a page without result fields as
+    /// "number not found"; only visible text is searched
+    pub not_found_patterns: Vec<String>,
+    pub site: ScraperSiteConfig,
+}
+
+impl Default for ScraperSelectors {
+    fn default() -> Self {
+        Self {
+            cookie_consent: CookieConsentConfig::default(),
+            form_fields: FormFieldsConfig::default(),
+            not_found_patterns: [
+                "contribuinte não encontrado",
+                "contribuinte nao encontrado",
+                "contribuinte inexistente",
+                "contribuinte inválido",
+                "contribuinte invalido",
+            ]
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect(),
+            site: ScraperSiteConfig::default(),
+        }
+    }
+}
+
+impl ScraperSelectors {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read selectors file: {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid selectors file: {}", path.display()))
+    }
+}
+
+/// Where the SP form lives and how its submit button and result fields are named
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScraperSiteConfig {
+    /// URL of the IPTU lookup form
+    pub form_url: String,
+    /// `id` of the button that submits the form
+    pub submit_button_id: String,
+    pub result_fields: ResultFieldNames,
+}
+
+impl Default for ScraperSiteConfig {
+    fn default() -> Self {
+        Self {
+            form_url: "https://www3.prefeitura.sp.gov.br/sf8663/formsinternet/principal.aspx"
+                .to_string(),
+            submit_button_id: "_BtnAvancarDasii".to_string(),
+            result_fields: ResultFieldNames::default(),
+        }
+    }
+}
+
+/// `name` attribute of the results page element holding each `IPTUData` field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResultFieldNames {
+    pub numero_cadastro: String,
+    pub nome_proprietario: String,
+    pub nome_compromissario: String,
+    pub endereco: String,
+    pub numero: String,
+    pub complemento: String,
+    pub bairro: String,
+    pub cep: String,
+    pub valor_venal: String,
+    pub area_terreno: String,
+    pub area_construida: String,
+    pub uso: String,
+}
+
+impl Default for ResultFieldNames {
+    fn default() -> Self {
+        Self {
+            numero_cadastro: "txtNumIPTU".to_string(),
+            nome_proprietario: "txtProprietarioNome".to_string(),
+            nome_compromissario: "txtCompromissarioNome".to_string(),
+            endereco: "txtEndereco".to_string(),
+            numero: "txtNumero".to_string(),
+            complemento: "txtComplemento".to_string(),
+            bairro: "txtBairro".to_string(),
+            cep: "txtCepImovel".to_string(),
+            valor_venal: "txtValorVenal".to_string(),
+            area_terreno: "txtAreaTerreno".to_string(),
+            area_construida: "txtAreaConstruida".to_string(),
+            uso: "txtUso".to_string(),
+        }
+    }
+}
+
+/// How the cookie consent banner is found and dismissed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CookieConsentConfig {
+    /// How many times to try dismissing the banner before giving up
+    pub max_attempts: u32,
+    /// CSS selectors of the "accept" button, tried in order
+    pub button_selectors: Vec<String>,
+    /// Keyword sets matched against button text/value (case-insensitive);
+    /// a button matches when it contains every keyword of any one set
+    pub text_patterns: Vec<Vec<String>>,
+    /// What to do when the banner is still there after `max_attempts`
+    pub on_failure: ConsentFailurePolicy,
+}
+
+/// Reaction to a cookie consent banner that could not be dismissed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsentFailurePolicy {
+    /// Fill the form anyway (the submit is likely to be blocked)
+    Continue,
+    /// Fail the job right away with `ConsentFailed`
+    Abort,
+    /// Reload the form once and try again, then fail with `ConsentFailed`
+    #[default]
+    Retry,
+}
+
+impl Default for CookieConsentConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            button_selectors: vec!["input.cc__button__autorizacao--all".to_string()],
+            text_patterns: vec![vec!["autorizo".to_string(), "cookies".to_string()]],
+            on_failure: ConsentFailurePolicy::default(),
+        }
+    }
+}
+
+impl CookieConsentConfig {
+    /// Whether the given button text matches any of the configured keyword sets
+    pub fn matches_text(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.text_patterns.iter().any(|keywords| {
+            !keywords.is_empty()
+                && keywords
+                    .iter()
+                    .all(|keyword| text.contains(&keyword.to_lowercase()))
+        })
+    }
+
+    /// Whether the consent banner is present in the given page HTML
+    pub fn banner_present(&self, html: &str) -> bool {
+        let document = ::scraper::Html::parse_document(html);
+
+        let selector_match = self.button_selectors.iter().any(|selector| {
+            ::scraper::Selector::parse(selector)
+                .map(|selector| document.select(&selector).next().is_some())
+                .unwrap_or(false)
+        });
+        if selector_match {
+            return true;
+        }
+
+        let buttons = ::scraper::Selector::parse("input[type='button'], button")
+            .expect("static selector is valid");
+        document.select(&buttons).any(|button| {
+            let text = match button.value().attr("value") {
+                Some(value) => value.to_string(),
+                None => button.text().collect::<String>(),
+            };
+            self.matches_text(&text)
+        })
+    }
+
+    /// JavaScript that clicks the first matching consent button.
+    /// Expects the selectors and text patterns as `arguments[0]` and `arguments[1]`.
+    pub const CLICK_SCRIPT: &'static str = r#"
+        var selectors = arguments[0];
+        var patterns = arguments[1];
+        var buttons = document.querySelectorAll('input[type="button"], button');
+        for (var i = 0; i < buttons.length; i++) {
+            var btn = buttons[i];
+            var text = (btn.value || btn.textContent || '').toLowerCase();
+            for (var p = 0; p < patterns.length; p++) {
+                var keywords = patterns[p];
+                if (keywords.length > 0 && keywords.every(function (k) {
+                    return text.includes(k.toLowerCase());
+                })) {
+                    btn.click();
+                    return true;
+                }
+            }
+        }
+        for (var s = 0; s < selectors.length; s++) {
+            var cookieBtn = document.querySelector(selectors[s]);
+            if (cookieBtn) {
+                cookieBtn.click();
+                return true;
+            }
+        }
+        return false;
+    "#;
+}
+
+/// How the contributor number is typed into the SP form inputs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormFieldsConfig {
+    /// CSS selector matching the number inputs, in page order
+    pub input_selector: String,
+    /// Digit count per input when the number is split across several fields
+    pub split: Vec<usize>,
+}
+
+impl Default for FormFieldsConfig {
+    fn default() -> Self {
+        Self {
+            input_selector: "input[type='text']".to_string(),
+            split: vec![3, 3, 4, 1],
+        }
+    }
+}
+
+impl FormFieldsConfig {
+    /// Splits the digits into one value per input: the whole number for a single
+    /// input, the configured `split` when the input count matches it
+    pub fn split_number(&self, digits: &str, input_count: usize) -> Result<Vec<String>> {
+        if input_count == 1 {
+            return Ok(vec![digits.to_string()]);
+        }
+        if input_count != self.split.len() {
+            bail!(
+                "Formulário com {} campos de entrada; esperado 1 ou {} (split {:?} em form_fields)",
+                input_count,
+                self.split.len(),
+                self.split
+            );
+        }
+
+        let expected: usize = self.split.iter().sum();
+        let chars: Vec<char> = digits.chars().collect();
+        if chars.len() != expected {
+            bail!(
+                "Número de cadastro com {} dígitos; o split {:?} espera {}",
+                chars.len(),
+                self.split,
+                expected
+            );
+        }
+
+        let mut start = 0;
+        Ok(self
+            .split
+            .iter()
+            .map(|len| {
+                let part: String = chars[start..start + len].iter().collect();
+                start += len;
+                part
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BANNER_HTML: &str = include_str!("../../tests/fixtures/sp_cookie_banner.html");
+    const RESULT_HTML: &str = include_str!("../../tests/fixtures/sp_iptu_result.html");
+
+    #[test]
+    fn test_default_config_detects_saved_banner() {
+        let config = CookieConsentConfig::default();
+        assert!(config.banner_present(BANNER_HTML));
+        assert!(!config.banner_present(RESULT_HTML));
+    }
+
+    #[test]
+    fn test_text_patterns_require_every_keyword() {
+        let config = CookieConsentConfig::default();
+        assert!(config.matches_text("Autorizo o uso de Cookies"));
+        assert!(!config.matches_text("Autorizo"));
+        assert!(!config.matches_text("Aceitar todos"));
+    }
+
+    #[test]
+    fn test_custom_patterns_from_json() {
+        let selectors: ScraperSelectors = serde_json::from_str(
+            r#"{ "cookie_consent": { "max_attempts": 5, "button_selectors": [], "text_patterns": [["aceitar", "todos"]] } }"#,
+        )
+        .unwrap();
+        let consent = &selectors.cookie_consent;
+
+        assert_eq!(consent.max_attempts, 5);
+        assert_eq!(consent.on_failure, ConsentFailurePolicy::Retry);
+        assert!(
+            consent.banner_present(r#"<html><body><button>Aceitar todos</button></body></html>"#)
+        );
+        assert!(!consent.banner_present(BANNER_HTML));
+    }
+
+    #[test]
+    fn test_missing_keys_use_defaults() {
+        let selectors: ScraperSelectors =
+            serde_json::from_str(r#"{ "cookie_consent": { "max_attempts": 1 } }"#).unwrap();
+
+        assert_eq!(selectors.cookie_consent.max_attempts, 1);
+        assert_eq!(
+            serde_json::from_str::<CookieConsentConfig>(r#"{ "on_failure": "abort" }"#)
+                .unwrap()
+                .on_failure,
+            ConsentFailurePolicy::Abort
+        );
+        assert_eq!(
+            selectors.cookie_consent.button_selectors,
+            CookieConsentConfig::default().button_selectors
+        );
+    }
+
+    #[test]
+    fn test_split_number_adapts_to_input_count() {
+        let form = FormFieldsConfig::default();
+        assert_eq!(
+            form.split_number("00112345678", 4).unwrap(),
+            vec!["001", "123", "4567", "8"]
+        );
+        assert_eq!(
+            form.split_number("00112345678", 1).unwrap(),
+            vec!["00112345678"]
+        );
+        assert!(form.split_number("00112345678", 3).is_err());
+        assert!(form.split_number("0011234567", 4).is_err());
+
+        let custom: ScraperSelectors =
+            serde_json::from_str(r#"{ "form_fields": { "split": [6, 5] } }"#).unwrap();
+        assert_eq!(custom.form_fields.input_selector, "input[type='text']");
+        assert_eq!(
+            custom.form_fields.split_number("00112345678", 2).unwrap(),
+            vec!["001123", "45678"]
+        );
+    }
+
+    #[test]
+    fn test_site_overrides_keep_other_defaults() {
+        let selectors: ScraperSelectors = serde_json::from_str(
+            r#"{ "site": { "submit_button_id": "btnConsultar", "result_fields": { "uso": "txtUsoImovel" } } }"#,
+        )
+        .unwrap();
+        let site = &selectors.site;
+
+        assert_eq!(site.submit_button_id, "btnConsultar");
+        assert_eq!(site.result_fields.uso, "txtUsoImovel");
+        assert_eq!(site.result_fields.numero_cadastro, "txtNumIPTU");
+        assert_eq!(site.form_url, ScraperSiteConfig::default().form_url);
+    }
+}
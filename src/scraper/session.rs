@@ -0,0 +1,140 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use thirtyfour::prelude::*;
+use tracing::{debug, info};
+
+use super::CookieConsentConfig;
+
+/// A browser cookie saved between runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    pub secure: bool,
+    pub http_only: bool,
+}
+
+/// Saves and restores the cookies of one site (e.g. the accepted cookie consent),
+/// stored per host in `sp_session_<host>.json`
+pub struct CookieSessionStore {
+    url: String,
+    session_file: PathBuf,
+}
+
+/// Host part of a URL, used to keep one session file per site
+fn url_host(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = without_scheme.split(['/', '?', '#']).next().unwrap_or("");
+    authority.rsplit('@').next().unwrap_or(authority)
+}
+
+impl CookieSessionStore {
+    pub fn for_url(url: &str) -> Self {
+        let host = url_host(url).replace([':', '.'], "_");
+        Self {
+            url: url.to_string(),
+            session_file: PathBuf::from(format!("sp_session_{}.json", host)),
+        }
+    }
+
+    /// Save the cookies of the current browser session
+    pub async fn save(&self, driver: &WebDriver) -> Result<()> {
+        let cookies = driver.get_all_cookies().await?;
+
+        let saved: Vec<SavedCookie> = cookies
+            .iter()
+            .map(|cookie| SavedCookie {
+                name: cookie.name().to_string(),
+                value: cookie.value().to_string(),
+                domain: cookie.domain().map(|s| s.to_string()),
+                path: cookie.path().map(|s| s.to_string()),
+                secure: cookie.secure().unwrap_or(false),
+                http_only: cookie.http_only().unwrap_or(false),
+            })
+            .collect();
+
+        let json = serde_json::to_string_pretty(&saved)?;
+        fs::write(&self.session_file, json).context("Failed to write session file")?;
+
+        info!(
+            "✅ Saved {} cookies to {:?}",
+            saved.len(),
+            self.session_file
+        );
+        Ok(())
+    }
+
+    /// Load the saved cookies into `driver` and check the consent banner is gone.
+    /// Returns `false` when there is no saved session or it no longer skips consent.
+    pub async fn restore(&self, driver: &WebDriver, consent: &CookieConsentConfig) -> Result<bool> {
+        if !self.session_file.exists() {
+            debug!("No saved session found at {:?}", self.session_file);
+            return Ok(false);
+        }
+
+        let json = fs::read_to_string(&self.session_file).context("Failed to read session file")?;
+        let saved: Vec<SavedCookie> =
+            serde_json::from_str(&json).context("Failed to parse session file")?;
+
+        // Cookies can only be set for the page currently loaded
+        driver.goto(&self.url).await?;
+        for cookie in saved {
+            let mut restored = Cookie::new(cookie.name.clone(), cookie.value.clone());
+            if let Some(domain) = cookie.domain {
+                restored.set_domain(domain);
+            }
+            if let Some(path) = cookie.path {
+                restored.set_path(path);
+            }
+            restored.set_secure(cookie.secure);
+            restored.set_http_only(cookie.http_only);
+
+            if let Err(e) = driver.add_cookie(restored).await {
+                debug!("Failed to add cookie {}: {}", cookie.name, e);
+            }
+        }
+
+        driver.refresh().await?;
+        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+
+        Ok(!consent.banner_present(&driver.source().await?))
+    }
+
+    /// Delete the saved session
+    pub fn clear(&self) -> Result<()> {
+        if self.session_file.exists() {
+            fs::remove_file(&self.session_file).context("Failed to delete session file")?;
+            info!("🗑️  Cleared saved session {:?}", self.session_file);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_file_is_per_host() {
+        assert_eq!(
+            url_host("https://www3.prefeitura.sp.gov.br/sf8663/formsinternet/principal.aspx"),
+            "www3.prefeitura.sp.gov.br"
+        );
+        assert_eq!(
+            url_host("http://user@localhost:9515/status"),
+            "localhost:9515"
+        );
+
+        let store = CookieSessionStore::for_url(
+            "https://www3.prefeitura.sp.gov.br/sf8663/formsinternet/principal.aspx",
+        );
+        assert_eq!(
+            store.session_file,
+            PathBuf::from("sp_session_www3_prefeitura_sp_gov_br.json")
+        );
+    }
+}
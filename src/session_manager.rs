@@ -19,13 +19,26 @@ pub struct CookieData {
 /// Session manager for persisting and restoring browser sessions
 pub struct SessionManager {
     session_file: PathBuf,
+    /// Page opened before restoring cookies and when checking the session
+    domain_url: String,
+    /// CSS selector that only exists while logged in. When unset, the session
+    /// counts as valid if no password field is displayed.
+    validity_selector: Option<String>,
 }
 
 impl SessionManager {
-    /// Create a new session manager
-    pub fn new() -> Self {
-        let session_file = PathBuf::from("dbase_session.json");
-        Self { session_file }
+    /// Create a session manager that keeps its cookies in `session_file` and
+    /// checks them against `domain_url`
+    pub fn with_config(
+        session_file: impl Into<PathBuf>,
+        domain_url: impl Into<String>,
+        validity_selector: Option<String>,
+    ) -> Self {
+        Self {
+            session_file: session_file.into(),
+            domain_url: domain_url.into(),
+            validity_selector,
+        }
     }
 
     /// Save cookies from current browser session
@@ -47,7 +60,10 @@ impl SessionManager {
             .collect();
 
         let json = serde_json::to_string_pretty(&cookie_data)?;
-        fs::write(&self.session_file, json).context("Failed to write session file")?;
+        // Write then rename so concurrent sessions never read a half-written file
+        let tmp_file = self.session_file.with_extension("json.tmp");
+        fs::write(&tmp_file, json).context("Failed to write session file")?;
+        fs::rename(&tmp_file, &self.session_file).context("Failed to write session file")?;
 
         info!(
             "✅ Saved {} cookies to {:?}",
@@ -72,7 +88,7 @@ impl SessionManager {
             serde_json::from_str(&json).context("Failed to parse session file")?;
 
         // Navigate to domain first (required for setting cookies)
-        driver.goto("https://app.dbase.com.br").await?;
+        driver.goto(&self.domain_url).await?;
         tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
 
         // Add each cookie
@@ -103,13 +119,16 @@ impl SessionManager {
     /// Check if session is still valid
     pub async fn is_session_valid(&self, driver: &WebDriver) -> Result<bool> {
         // Navigate to the app and check if we're logged in
-        driver
-            .goto("https://app.dbase.com.br/sistema/consultas/")
-            .await?;
+        driver.goto(&self.domain_url).await?;
         tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
 
-        // Check if we can find the CEP search form (indicates logged in)
-        let is_valid = driver.find(By::Css("input[name='e_cep']")).await.is_ok();
+        let is_valid = match &self.validity_selector {
+            Some(selector) => driver.find(By::Css(selector.as_str())).await.is_ok(),
+            None => match driver.find(By::Css("input[type='password']")).await {
+                Ok(elem) => !elem.is_displayed().await.unwrap_or(false),
+                Err(_) => true,
+            },
+        };
 
         if is_valid {
             info!("✅ Saved session is still valid!");
@@ -135,8 +154,25 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_session_manager_new() {
-        let manager = SessionManager::new();
+    fn test_session_manager_with_config() {
+        let manager = SessionManager::with_config(
+            "diretrix_session.json",
+            "https://www.diretrixconsultoria.com.br",
+            None,
+        );
+        assert_eq!(manager.session_file, PathBuf::from("diretrix_session.json"));
+        assert_eq!(manager.domain_url, "https://www.diretrixconsultoria.com.br");
+        assert!(manager.validity_selector.is_none());
+
+        let manager = SessionManager::with_config(
+            "dbase_session.json",
+            "https://app.dbase.com.br/sistema/consultas/",
+            Some("input[name='e_cep']".to_string()),
+        );
         assert_eq!(manager.session_file, PathBuf::from("dbase_session.json"));
+        assert_eq!(
+            manager.validity_selector.as_deref(),
+            Some("input[name='e_cep']")
+        );
     }
 }
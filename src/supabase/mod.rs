@@ -1,6 +1,17 @@
-use anyhow::Result;
-use reqwest::Client;
+use anyhow::{Context, Result};
+use base64::Engine;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::time::Duration;
+
+use crate::dbase_scraper::AddressRecord;
+use crate::diretrix_enrichment::GetCustomerData;
+use crate::diretrix_scraper::PropertyRecord;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PendingJob {
@@ -12,6 +23,9 @@ pub struct PendingJob {
     pub created_at: String,
     #[serde(default)]
     pub batch_id: Option<String>,
+    /// When the job was claimed, if the table has a `claimed_at` column
+    #[serde(default)]
+    pub claimed_at: Option<String>,
     #[serde(skip)]
     pub from_priority_table: bool,
 }
@@ -28,6 +42,14 @@ pub struct IPTUResult {
     pub complemento: Option<String>,
     pub bairro: Option<String>,
     pub cep: Option<String>,
+    #[serde(default)]
+    pub valor_venal: Option<String>,
+    #[serde(default)]
+    pub area_terreno: Option<String>,
+    #[serde(default)]
+    pub area_construida: Option<String>,
+    #[serde(default)]
+    pub uso: Option<String>,
     pub sucesso: bool,
     pub erro: Option<String>,
     pub batch_id: Option<String>,
@@ -35,11 +57,246 @@ pub struct IPTUResult {
     pub processed_by: Option<String>,
 }
 
+/// Columns of a results export (`results --export`), produced by `result_row`
+pub const RESULT_COLUMNS: [&str; 19] = [
+    "id",
+    "contributor_number",
+    "numero_cadastro",
+    "nome_proprietario",
+    "nome_compromissario",
+    "endereco",
+    "numero",
+    "complemento",
+    "bairro",
+    "cep",
+    "valor_venal",
+    "area_terreno",
+    "area_construida",
+    "uso",
+    "sucesso",
+    "erro",
+    "batch_id",
+    "timestamp",
+    "processed_by",
+];
+
+/// Flatten a result into a row matching `RESULT_COLUMNS`
+pub fn result_row(result: &IPTUResult) -> Vec<String> {
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    vec![
+        text(&result.id),
+        result.contributor_number.clone(),
+        text(&result.numero_cadastro),
+        text(&result.nome_proprietario),
+        text(&result.nome_compromissario),
+        text(&result.endereco),
+        text(&result.numero),
+        text(&result.complemento),
+        text(&result.bairro),
+        text(&result.cep),
+        text(&result.valor_venal),
+        text(&result.area_terreno),
+        text(&result.area_construida),
+        text(&result.uso),
+        result.sucesso.to_string(),
+        text(&result.erro),
+        text(&result.batch_id),
+        result.timestamp.clone(),
+        text(&result.processed_by),
+    ]
+}
+
+/// Retries after the first attempt for requests that hit a 5xx or a network
+/// error, unless `SUPABASE_MAX_RETRIES` says otherwise
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Max contributor numbers per `in.(...)` filter, keeping request URLs short
+const IN_FILTER_CHUNK: usize = 200;
+
+/// Most rows a PostgREST response carries with Supabase's default `max-rows`;
+/// larger page sizes are capped to it
+pub const MAX_ROWS_PER_REQUEST: usize = 1000;
+
+/// Job queue column holding when a row was marked 'p'
+pub const CLAIMED_AT_COLUMN: &str = "claimed_at";
+
+/// Job queue column holding when a row got its final status ('s', 'e' or 'n')
+pub const PROCESSED_AT_COLUMN: &str = "processed_at";
+
+/// Rows per upsert request
+const UPSERT_CHUNK: usize = 500;
+
+/// Table `dbase --upload` writes to unless `SUPABASE_DBASE_TABLE` is set
+pub const DEFAULT_DBASE_TABLE: &str = "dbase_addresses";
+/// Table `diretrix --upload` writes to unless `SUPABASE_DIRETRIX_TABLE` is set
+pub const DEFAULT_DIRETRIX_TABLE: &str = "diretrix_properties";
+
+/// Unique key of `DEFAULT_DBASE_TABLE` rows
+const DBASE_CONFLICT_COLUMNS: [&str; 4] = ["cpf_cnpj", "cep", "numero", "complemento"];
+/// Unique key of `DEFAULT_DIRETRIX_TABLE` rows
+const DIRETRIX_CONFLICT_COLUMNS: [&str; 1] = ["iptu"];
+
+/// Row of the Dbase addresses table
+fn dbase_row(record: &AddressRecord, scraped_at: &str) -> serde_json::Value {
+    serde_json::json!({
+        "cpf_cnpj": record.cpf_cnpj,
+        "nome_razao_social": record.nome_razao_social,
+        "logradouro": record.logradouro,
+        "numero": record.numero,
+        "complemento": record.complemento,
+        "bairro": record.bairro,
+        "cep": record.cep,
+        "scraped_at": scraped_at,
+    })
+}
+
+/// Row of the Diretrix properties table; the enrichment is stored as JSON
+fn diretrix_row(
+    record: &PropertyRecord,
+    enrichment: Option<&GetCustomerData>,
+    scraped_at: &str,
+) -> serde_json::Value {
+    serde_json::json!({
+        "iptu": record.iptu,
+        "owner": record.owner,
+        "street": record.street,
+        "number": record.number,
+        "complement": record.complement,
+        "complement2": record.complement2,
+        "neighborhood": record.neighborhood,
+        "document1": record.document1,
+        "document2": record.document2,
+        "enrichment": enrichment,
+        "scraped_at": scraped_at,
+    })
+}
+
+/// Drop rows repeating the `key` columns of an earlier row. Postgres rejects
+/// an upsert that touches the same row twice.
+fn dedupe_rows(rows: Vec<serde_json::Value>, key: &[&str]) -> Vec<serde_json::Value> {
+    let mut seen = HashSet::new();
+    rows.into_iter()
+        .filter(|row| {
+            seen.insert(
+                key.iter()
+                    .map(|column| row[*column].to_string())
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect()
+}
+
+/// Inconsistencies between `iptus` (results) and `iptus_list` (status)
+#[derive(Debug, Default)]
+pub struct ReconcileReport {
+    pub list_rows: usize,
+    pub result_rows: usize,
+    /// Have a result row but the list status is not `s`
+    pub results_not_marked_success: Vec<String>,
+    /// Marked `s` in the list but have no result row
+    pub success_without_result: Vec<String>,
+    /// Marked `e` in the list and have no result row
+    pub error_without_result: Vec<String>,
+}
+
+impl ReconcileReport {
+    /// Cross-check list statuses against the set of numbers that have results.
+    /// Result rows for numbers absent from the list are ignored.
+    pub fn build(
+        list_statuses: &HashMap<String, Option<String>>,
+        result_numbers: &HashSet<String>,
+    ) -> Self {
+        let mut report = ReconcileReport {
+            list_rows: list_statuses.len(),
+            result_rows: result_numbers.len(),
+            ..Default::default()
+        };
+
+        for (number, status) in list_statuses {
+            let has_result = result_numbers.contains(number);
+            match (status.as_deref(), has_result) {
+                (Some("s"), false) => report.success_without_result.push(number.clone()),
+                (Some("e"), false) => report.error_without_result.push(number.clone()),
+                // Claimed by a run in progress, which sets the final status itself
+                (Some("p"), _) | (Some("s"), true) | (_, false) => {}
+                (_, true) => report.results_not_marked_success.push(number.clone()),
+            }
+        }
+
+        report.results_not_marked_success.sort();
+        report.success_without_result.sort();
+        report.error_without_result.sort();
+        report
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.results_not_marked_success.is_empty()
+            && self.success_without_result.is_empty()
+            && self.error_without_result.is_empty()
+    }
+}
+
+/// Names of the tables the client reads and writes, so one project can hold
+/// several environments (e.g. `staging_iptus`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableNames {
+    /// Scraping results
+    pub iptus: String,
+    /// Job queue
+    pub iptus_list: String,
+    /// Job queue drained before `iptus_list`
+    pub iptus_list_priority: String,
+    pub batches: String,
+    pub iptu_queue: String,
+}
+
+impl Default for TableNames {
+    fn default() -> Self {
+        Self {
+            iptus: "iptus".to_string(),
+            iptus_list: "iptus_list".to_string(),
+            iptus_list_priority: "iptus_list_priority".to_string(),
+            batches: "batches".to_string(),
+            iptu_queue: "iptu_queue".to_string(),
+        }
+    }
+}
+
+/// Columns added to `iptus` after the original schema; only sent when the
+/// table is known to have them (see `with_extended_iptu_columns`).
+const EXTENDED_IPTU_COLUMNS: [&str; 4] = ["valor_venal", "area_terreno", "area_construida", "uso"];
+
 pub struct SupabaseClient {
     client: Client,
     base_url: String,
     api_key: String,
     service_role_key: Option<String>,
+    extended_iptu_columns: bool,
+    /// Write `claimed_at` / `processed_at` with each status change
+    job_timestamps: bool,
+    tables: TableNames,
+    raw_html_table: Option<String>,
+    max_retries: u32,
+    /// First backoff delay; doubled after every retry
+    retry_base_delay: Duration,
+}
+
+/// PATCH body setting `status` and its timestamp: `claimed_at` for a claim ('p'),
+/// `processed_at` for a final status, and a cleared `claimed_at` on release
+fn status_update(status: Option<&str>, now: &str) -> serde_json::Value {
+    match status {
+        Some("p") => serde_json::json!({ "status": "p", CLAIMED_AT_COLUMN: now }),
+        Some(status) => serde_json::json!({ "status": status, PROCESSED_AT_COLUMN: now }),
+        None => serde_json::json!({ "status": null, CLAIMED_AT_COLUMN: null }),
+    }
+}
+
+/// Gzip the page source and base64 it for a text column
+pub fn compress_html(html: &str) -> Result<String> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(html.as_bytes())?;
+    let compressed = encoder.finish()?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(compressed))
 }
 
 impl SupabaseClient {
@@ -49,6 +306,12 @@ impl SupabaseClient {
             base_url,
             api_key,
             service_role_key: None,
+            extended_iptu_columns: false,
+            job_timestamps: false,
+            tables: TableNames::default(),
+            raw_html_table: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_base_delay: Duration::from_secs(2),
         }
     }
 
@@ -57,91 +320,118 @@ impl SupabaseClient {
         self
     }
 
-    pub async fn fetch_pending_jobs(&self, limit: usize) -> Result<Vec<PendingJob>> {
-        let auth_key: &String = self.service_role_key.as_ref().unwrap_or(&self.api_key);
+    pub fn with_extended_iptu_columns(mut self, enabled: bool) -> Self {
+        self.extended_iptu_columns = enabled;
+        self
+    }
+
+    pub fn with_table_names(mut self, tables: TableNames) -> Self {
+        self.tables = tables;
+        self
+    }
 
-        tracing::info!("Checking iptus_list_priority table for pending jobs...");
-        let priority_url: String = format!("{}/rest/v1/iptus_list_priority", self.base_url);
+    fn table_url(&self, table: &str) -> String {
+        format!("{}/rest/v1/{}", self.base_url, table)
+    }
 
-        let priority_response: reqwest::Response = self
-            .client
-            .get(&priority_url)
-            .header("apikey", auth_key)
-            .header("Authorization", format!("Bearer {}", auth_key))
-            .query(&[
-                ("select", "contributor_number,status"),
-                ("status", "is.null"),
-                ("order", "contributor_number.desc"),
-                ("limit", &limit.to_string()),
-            ])
-            .send()
-            .await?;
+    /// The job table a claimed number came from
+    fn job_table(&self, from_priority_table: bool) -> &str {
+        if from_priority_table {
+            &self.tables.iptus_list_priority
+        } else {
+            &self.tables.iptus_list
+        }
+    }
 
-        if priority_response.status().is_success() {
-            let text = priority_response.text().await?;
-            tracing::debug!("Response from iptus_list_priority: {}", text);
+    /// Only enable once both job tables have `claimed_at` and `processed_at` columns
+    pub fn with_job_timestamps(mut self, enabled: bool) -> Self {
+        self.job_timestamps = enabled;
+        self
+    }
 
-            let mut priority_jobs =
-                serde_json::from_str::<Vec<PendingJob>>(&text).map_err(|e| {
-                    anyhow::anyhow!(
-                        "Failed to parse priority response: {}. Response: {}",
-                        e,
-                        text
-                    )
-                })?;
+    /// Table receiving raw page sources for `--store-raw-html`
+    pub fn with_raw_html_table(mut self, table: Option<String>) -> Self {
+        self.raw_html_table = table;
+        self
+    }
+
+    /// Retries for idempotent requests failing with a 5xx or a network error
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Send an idempotent request (GET, PATCH or upsert POST), retrying network
+    /// errors and 5xx responses with exponential backoff. Any other response,
+    /// including a 4xx, is returned at once for the caller to check; once the
+    /// retries run out the last 5xx response or network error is returned.
+    async fn send_with_retry(&self, request: RequestBuilder) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let Some(current) = request.try_clone() else {
+                // Streaming bodies cannot be replayed
+                return Ok(request.send().await?);
+            };
+
+            let retry_reason = match current.send().await {
+                Ok(response) if !response.status().is_server_error() => return Ok(response),
+                Ok(response) if attempt >= self.max_retries => return Ok(response),
+                Err(err) if attempt >= self.max_retries => return Err(err.into()),
+                Ok(response) => format!("status {}", response.status()),
+                Err(err) => err.to_string(),
+            };
+
+            attempt += 1;
+            let delay = self.retry_base_delay * 2u32.pow(attempt - 1);
+            tracing::warn!(
+                "Supabase request failed ({}), retrying in {:?} ({}/{})",
+                retry_reason,
+                delay,
+                attempt,
+                self.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
 
-            if !priority_jobs.is_empty() {
+    /// Up to `limit` unclaimed jobs from the priority table or, when it has none,
+    /// from the main job table; fetched in pages so limits above the PostgREST
+    /// row cap are filled
+    pub async fn fetch_pending_jobs(&self, limit: usize) -> Result<Vec<PendingJob>> {
+        let priority_table = &self.tables.iptus_list_priority;
+        tracing::info!("Checking {} table for pending jobs...", priority_table);
+
+        match self
+            .fetch_pending_from(priority_table, limit, MAX_ROWS_PER_REQUEST)
+            .await
+        {
+            Ok(mut priority_jobs) if !priority_jobs.is_empty() => {
                 tracing::info!(
-                    "Found {} priority jobs in iptus_list_priority",
-                    priority_jobs.len()
+                    "Found {} priority jobs in {}",
+                    priority_jobs.len(),
+                    priority_table
                 );
 
                 for job in &mut priority_jobs {
                     job.from_priority_table = true;
                 }
                 return Ok(priority_jobs);
-            } else {
-                tracing::info!(
-                    "No pending jobs found in iptus_list_priority, checking iptus_list..."
-                );
             }
-        } else {
-            let error_text = priority_response.text().await?;
-            tracing::warn!("Could not fetch from iptus_list_priority: {}", error_text);
-        }
-
-        let url = format!("{}/rest/v1/iptus_list", self.base_url);
-
-        let response = self
-            .client
-            .get(&url)
-            .header("apikey", auth_key)
-            .header("Authorization", format!("Bearer {}", auth_key))
-            .query(&[
-                ("select", "contributor_number,status"),
-                ("status", "is.null"),
-                ("order", "contributor_number.desc"),
-                ("limit", &limit.to_string()),
-            ])
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            anyhow::bail!(
-                "Failed to fetch pending jobs from both tables: {}",
-                error_text
-            );
+            Ok(_) => tracing::info!(
+                "No pending jobs found in {}, checking {}...",
+                priority_table,
+                self.tables.iptus_list
+            ),
+            Err(e) => tracing::warn!("Could not fetch from {}: {:#}", priority_table, e),
         }
 
-        let text = response.text().await?;
-        tracing::debug!("Response from iptus_list: {}", text);
-
-        let jobs = serde_json::from_str::<Vec<PendingJob>>(&text)
-            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}. Response: {}", e, text))?;
+        let jobs = self
+            .fetch_pending_from(&self.tables.iptus_list, limit, MAX_ROWS_PER_REQUEST)
+            .await
+            .context("Failed to fetch pending jobs from both tables")?;
 
         if !jobs.is_empty() {
-            tracing::info!("Found {} jobs in iptus_list", jobs.len());
+            tracing::info!("Found {} jobs in {}", jobs.len(), self.tables.iptus_list);
         } else {
             tracing::info!("No pending jobs found in either table");
         }
@@ -149,87 +439,129 @@ impl SupabaseClient {
         Ok(jobs)
     }
 
+    /// Unclaimed jobs of one table, newest number first, `page_size` rows per
+    /// request until `limit` is reached or the table runs out
+    async fn fetch_pending_from(
+        &self,
+        table: &str,
+        limit: usize,
+        page_size: usize,
+    ) -> Result<Vec<PendingJob>> {
+        self.fetch_paged(
+            table,
+            &[
+                ("select", "contributor_number,status"),
+                ("status", "is.null"),
+                ("order", "contributor_number.desc"),
+            ],
+            Some(limit),
+            page_size,
+        )
+        .await
+    }
+
+    /// Mark the jobs as processing ('p'); returns how many rows matched
     pub async fn claim_jobs(
         &self,
         job_ids: Vec<String>,
         _machine_id: &str,
         from_priority_table: bool,
-    ) -> Result<()> {
-        let table_name = if from_priority_table {
-            "iptus_list_priority"
-        } else {
-            "iptus_list"
-        };
-        let url = format!("{}/rest/v1/{}", self.base_url, table_name);
-
-        let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
-
-        let update_data = serde_json::json!({
-            "status": "p",  // p for processing
-        });
-
+    ) -> Result<usize> {
+        let table_name = self.job_table(from_priority_table);
         tracing::info!(
             "Claiming {} jobs from {} (marking all as 'p' in a single request)",
             job_ids.len(),
             table_name
         );
 
-        let in_clause = job_ids
-            .iter()
-            .map(|id| format!("\"{}\"", id))
-            .collect::<Vec<_>>()
-            .join(",");
+        let claimed = self
+            .set_status_in(table_name, &job_ids, Some("p"))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to claim jobs: {}", e))?;
+
+        tracing::info!(
+            "Successfully claimed {} of {} jobs (marked as 'p')",
+            claimed,
+            job_ids.len()
+        );
+
+        Ok(claimed)
+    }
+
+    pub async fn upload_results(&self, results: Vec<IPTUResult>) -> Result<usize> {
+        let url = self.table_url(&self.tables.iptus);
+
+        let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
+
+        let mut rows = serde_json::to_value(&results)?;
+        if !self.extended_iptu_columns {
+            strip_extended_iptu_columns(&mut rows);
+        }
 
         let response = self
-            .client
-            .patch(&url)
-            .header("apikey", auth_key)
-            .header("Authorization", format!("Bearer {}", auth_key))
-            .header("Content-Type", "application/json")
-            .query(&[("contributor_number", format!("in.({})", in_clause))])
-            .json(&update_data)
-            .send()
+            .send_with_retry(
+                self.client
+                    .post(&url)
+                    .header("apikey", auth_key)
+                    .header("Authorization", format!("Bearer {}", auth_key))
+                    .header("Content-Type", "application/json")
+                    .header("Prefer", "resolution=merge-duplicates") // Use upsert instead of insert
+                    .json(&rows),
+            )
             .await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
-            anyhow::bail!("Failed to claim jobs: {}", error_text);
+            anyhow::bail!("Failed to upload results: {}", error_text);
         }
 
-        tracing::info!(
-            "Successfully claimed {} jobs (all marked as 'p')",
-            job_ids.len()
-        );
-
-        Ok(())
+        Ok(results.len())
     }
 
-    pub async fn upload_results(&self, results: Vec<IPTUResult>) -> Result<usize> {
-        let url = format!("{}/rest/v1/iptus", self.base_url);
+    /// Store the gzipped, base64-encoded page source a result was parsed from.
+    /// Does nothing when no raw HTML table is configured.
+    pub async fn upload_raw_html(
+        &self,
+        contributor_number: &str,
+        html: &str,
+        batch_id: Option<&str>,
+    ) -> Result<()> {
+        let Some(table) = &self.raw_html_table else {
+            return Ok(());
+        };
+        let url = self.table_url(table);
 
         let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
 
+        let row = serde_json::json!({
+            "contributor_number": contributor_number,
+            "captured_at": chrono::Utc::now().to_rfc3339(),
+            "batch_id": batch_id,
+            "encoding": "gzip+base64",
+            "html": compress_html(html)?,
+        });
+
         let response = self
             .client
             .post(&url)
             .header("apikey", auth_key)
             .header("Authorization", format!("Bearer {}", auth_key))
             .header("Content-Type", "application/json")
-            .header("Prefer", "resolution=merge-duplicates") // Use upsert instead of insert
-            .json(&results)
+            .json(&row)
             .send()
             .await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
-            anyhow::bail!("Failed to upload results: {}", error_text);
+            anyhow::bail!("Failed to upload raw HTML: {}", error_text);
         }
 
-        Ok(results.len())
+        Ok(())
     }
 
+    /// Insert a new `processing` batch row and return its id
     pub async fn create_batch(&self, total: i32) -> Result<String> {
-        let url = format!("{}/rest/v1/batches", self.base_url);
+        let url = self.table_url(&self.tables.batches);
         let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
 
         let batch_id = uuid::Uuid::new_v4().to_string();
@@ -244,15 +576,25 @@ impl SupabaseClient {
         });
 
         let response = self
-            .client
-            .post(&url)
-            .header("apikey", auth_key)
-            .header("Authorization", format!("Bearer {}", auth_key))
-            .header("Content-Type", "application/json")
-            .json(&batch_data)
-            .send()
+            .send_with_retry(
+                self.client
+                    .post(&url)
+                    .header("apikey", auth_key)
+                    .header("Authorization", format!("Bearer {}", auth_key))
+                    .header("Content-Type", "application/json")
+                    .json(&batch_data),
+            )
             .await?;
 
+        // The id is freshly generated, so a conflict means an earlier attempt
+        // that failed on our side was in fact inserted
+        if response.status() == reqwest::StatusCode::CONFLICT {
+            tracing::info!(
+                "Batch {} was already created by an earlier attempt",
+                batch_id
+            );
+            return Ok(batch_id);
+        }
         if !response.status().is_success() {
             let error_text = response.text().await?;
             anyhow::bail!("Failed to create batch: {}", error_text);
@@ -268,7 +610,7 @@ impl SupabaseClient {
         sucesso: i32,
         erros: i32,
     ) -> Result<()> {
-        let url = format!("{}/rest/v1/batches", self.base_url);
+        let url = self.table_url(&self.tables.batches);
         let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
 
         let update = serde_json::json!({
@@ -278,14 +620,15 @@ impl SupabaseClient {
         });
 
         let response = self
-            .client
-            .patch(&url)
-            .header("apikey", auth_key)
-            .header("Authorization", format!("Bearer {}", auth_key))
-            .header("Content-Type", "application/json")
-            .query(&[("id", format!("eq.{}", batch_id))])
-            .json(&update)
-            .send()
+            .send_with_retry(
+                self.client
+                    .patch(&url)
+                    .header("apikey", auth_key)
+                    .header("Authorization", format!("Bearer {}", auth_key))
+                    .header("Content-Type", "application/json")
+                    .query(&[("id", format!("eq.{}", batch_id))])
+                    .json(&update),
+            )
             .await?;
 
         if !response.status().is_success() {
@@ -298,44 +641,46 @@ impl SupabaseClient {
 
     #[allow(dead_code)]
     pub async fn update_job_status(&self, job_id: &str, status: &str) -> Result<()> {
-        let url = format!("{}/rest/v1/iptu_queue", self.base_url);
+        let url = self.table_url(&self.tables.iptu_queue);
 
         let update_data = serde_json::json!({
             "status": status,
             "completed_at": chrono::Utc::now().to_rfc3339(),
         });
 
-        self.client
-            .patch(&url)
-            .header("apikey", &self.api_key)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .query(&[("id", format!("eq.{}", job_id))])
-            .json(&update_data)
-            .send()
-            .await?;
+        self.send_with_retry(
+            self.client
+                .patch(&url)
+                .header("apikey", &self.api_key)
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .header("Content-Type", "application/json")
+                .query(&[("id", format!("eq.{}", job_id))])
+                .json(&update_data),
+        )
+        .await?;
 
         Ok(())
     }
 
     pub async fn check_existing_iptu(&self, contributor_number: &str) -> Result<bool> {
-        let url = format!("{}/rest/v1/iptus", self.base_url);
+        let url = self.table_url(&self.tables.iptus);
         let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
 
         let response = self
-            .client
-            .get(&url)
-            .header("apikey", auth_key)
-            .header("Authorization", format!("Bearer {}", auth_key))
-            .query(&[
-                (
-                    "contributor_number",
-                    format!("eq.{}", contributor_number).as_str(),
-                ),
-                ("select", "contributor_number"),
-                ("limit", "1"),
-            ])
-            .send()
+            .send_with_retry(
+                self.client
+                    .get(&url)
+                    .header("apikey", auth_key)
+                    .header("Authorization", format!("Bearer {}", auth_key))
+                    .query(&[
+                        (
+                            "contributor_number",
+                            format!("eq.{}", contributor_number).as_str(),
+                        ),
+                        ("select", "contributor_number"),
+                        ("limit", "1"),
+                    ]),
+            )
             .await?;
 
         if response.status().is_success() {
@@ -348,89 +693,325 @@ impl SupabaseClient {
         }
     }
 
+    /// Mark numbers as scraped ('s'); returns how many rows matched
     pub async fn mark_iptu_list_as_success(
         &self,
         contributor_numbers: Vec<String>,
         from_priority_table: bool,
-    ) -> Result<()> {
-        let table_name = if from_priority_table {
-            "iptus_list_priority"
-        } else {
-            "iptus_list"
-        };
-        let url = format!("{}/rest/v1/{}", self.base_url, table_name);
+    ) -> Result<usize> {
+        let table_name = self.job_table(from_priority_table);
+        self.set_status_in(table_name, &contributor_numbers, Some("s"))
+            .await
+    }
 
-        let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
+    /// Mark numbers whose scrape failed ('e'); returns how many rows matched
+    pub async fn mark_iptu_list_as_error(
+        &self,
+        contributor_numbers: Vec<String>,
+        from_priority_table: bool,
+    ) -> Result<usize> {
+        let table_name = self.job_table(from_priority_table);
+        self.set_status_in(table_name, &contributor_numbers, Some("e"))
+            .await
+    }
 
-        let update_data = serde_json::json!({
-            "status": "s",
-        });
+    /// Mark numbers the SP site reported as not found ('n'), so they are not
+    /// retried as errors; returns how many rows matched
+    pub async fn mark_iptu_list_as_not_found(
+        &self,
+        contributor_numbers: Vec<String>,
+        from_priority_table: bool,
+    ) -> Result<usize> {
+        let table_name = self.job_table(from_priority_table);
+        self.set_status_in(table_name, &contributor_numbers, Some("n"))
+            .await
+    }
 
-        for number in contributor_numbers {
-            self.client
-                .patch(&url)
-                .header("apikey", auth_key)
-                .header("Authorization", format!("Bearer {}", auth_key))
-                .header("Content-Type", "application/json")
-                .query(&[("contributor_number", format!("eq.{}", number))])
-                .json(&update_data)
-                .send()
+    /// Page through a table, returning every row of the selected columns
+    async fn fetch_all_rows(
+        &self,
+        table: &str,
+        select: &str,
+        page_size: usize,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.fetch_paged(
+            table,
+            &[("select", select), ("order", "contributor_number.asc")],
+            None,
+            page_size,
+        )
+        .await
+    }
+
+    /// Read `table` with `filters` in pages of `page_size` rows (capped at
+    /// `MAX_ROWS_PER_REQUEST`) until `limit` rows are read or a page comes back
+    /// empty. A short page is not the end: the server may cap pages below the
+    /// requested size.
+    async fn fetch_paged<T: DeserializeOwned>(
+        &self,
+        table: &str,
+        filters: &[(&str, &str)],
+        limit: Option<usize>,
+        page_size: usize,
+    ) -> Result<Vec<T>> {
+        let url = self.table_url(table);
+        let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
+        let page_size = page_size.clamp(1, MAX_ROWS_PER_REQUEST);
+
+        let mut rows = Vec::new();
+        loop {
+            let offset = rows.len();
+            let requested = match limit {
+                Some(limit) if offset >= limit => break,
+                Some(limit) => page_size.min(limit - offset),
+                None => page_size,
+            };
+            let response = self
+                .send_with_retry(
+                    self.client
+                        .get(&url)
+                        .header("apikey", auth_key)
+                        .header("Authorization", format!("Bearer {}", auth_key))
+                        .query(filters)
+                        .query(&[
+                            ("limit", requested.to_string()),
+                            ("offset", offset.to_string()),
+                        ]),
+                )
                 .await?;
+
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                anyhow::bail!(
+                    "Failed to fetch {} (offset {}): {}",
+                    table,
+                    offset,
+                    error_text
+                );
+            }
+
+            let text = response.text().await?;
+            let page = serde_json::from_str::<Vec<T>>(&text).map_err(|e| {
+                anyhow::anyhow!("Failed to parse response: {}. Response: {}", e, text)
+            })?;
+            if page.is_empty() {
+                break;
+            }
+            rows.extend(page);
+            tracing::debug!("Fetched {} rows from {} so far", rows.len(), table);
         }
 
-        Ok(())
+        Ok(rows)
     }
 
-    pub async fn mark_iptu_list_as_error(
+    /// Status of every contributor number in `iptus_list`
+    pub async fn fetch_list_statuses(
         &self,
-        contributor_numbers: Vec<String>,
+        page_size: usize,
+    ) -> Result<HashMap<String, Option<String>>> {
+        let rows = self
+            .fetch_all_rows(
+                &self.tables.iptus_list,
+                "contributor_number,status",
+                page_size,
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let number = row.get("contributor_number")?.as_str()?.to_string();
+                let status = row
+                    .get("status")
+                    .and_then(|s| s.as_str())
+                    .map(|s| s.to_string());
+                Some((number, status))
+            })
+            .collect())
+    }
+
+    /// Every contributor number that has a row in `iptus`
+    pub async fn fetch_result_numbers(&self, page_size: usize) -> Result<HashSet<String>> {
+        let rows = self
+            .fetch_all_rows(&self.tables.iptus, "contributor_number", page_size)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| Some(row.get("contributor_number")?.as_str()?.to_string()))
+            .collect())
+    }
+
+    /// Set the `iptus_list` status (or clear it with `None`) for many numbers,
+    /// one PATCH per chunk of numbers
+    pub async fn set_list_status(
+        &self,
+        contributor_numbers: &[String],
+        status: Option<&str>,
+    ) -> Result<usize> {
+        self.set_status_in(&self.tables.iptus_list, contributor_numbers, status)
+            .await
+    }
+
+    /// Hand claimed jobs that were never scraped back to the queue (status cleared)
+    pub async fn release_jobs(
+        &self,
+        contributor_numbers: &[String],
         from_priority_table: bool,
-    ) -> Result<()> {
-        let table_name = if from_priority_table {
-            "iptus_list_priority"
+    ) -> Result<usize> {
+        let table_name = self.job_table(from_priority_table);
+        self.set_status_in(table_name, contributor_numbers, None)
+            .await
+    }
+
+    /// Set `status` on every listed contributor number with one PATCH per
+    /// `IN_FILTER_CHUNK` numbers, returning how many rows were updated
+    async fn set_status_in(
+        &self,
+        table_name: &str,
+        contributor_numbers: &[String],
+        status: Option<&str>,
+    ) -> Result<usize> {
+        let url = self.table_url(table_name);
+        let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
+        let update_data = if self.job_timestamps {
+            let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+            status_update(status, &now)
         } else {
-            "iptus_list"
+            serde_json::json!({ "status": status })
         };
-        let url = format!("{}/rest/v1/{}", self.base_url, table_name);
 
-        let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
+        let mut updated = 0;
+        for chunk in contributor_numbers.chunks(IN_FILTER_CHUNK) {
+            let in_clause = chunk
+                .iter()
+                .map(|id| format!("\"{}\"", id))
+                .collect::<Vec<_>>()
+                .join(",");
+
+            let response = self
+                .send_with_retry(
+                    self.client
+                        .patch(&url)
+                        .header("apikey", auth_key)
+                        .header("Authorization", format!("Bearer {}", auth_key))
+                        .header("Content-Type", "application/json")
+                        .header("Prefer", "return=representation")
+                        .query(&[
+                            ("contributor_number", format!("in.({})", in_clause)),
+                            ("select", "contributor_number".to_string()),
+                        ])
+                        .json(&update_data),
+                )
+                .await?;
 
-        let update_data = serde_json::json!({
-            "status": "e",  // e for error
-        });
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                anyhow::bail!("Failed to update {} status: {}", table_name, error_text);
+            }
 
-        for number in contributor_numbers {
-            self.client
-                .patch(&url)
-                .header("apikey", auth_key)
-                .header("Authorization", format!("Bearer {}", auth_key))
-                .header("Content-Type", "application/json")
-                .query(&[("contributor_number", format!("eq.{}", number))])
-                .json(&update_data)
-                .send()
+            // Only the rows that matched come back, one per updated number
+            updated += response.json::<Vec<serde_json::Value>>().await?.len();
+        }
+
+        Ok(updated)
+    }
+
+    /// Put jobs left as 'p' for more than `older_than_minutes` (a run that crashed
+    /// after claiming them) back in the queue of both job tables; returns how many
+    /// were reset. Rows without a `claimed_at` are left alone.
+    pub async fn reset_stale_claims(&self, older_than_minutes: u64) -> Result<usize> {
+        let cutoff = chrono::Utc::now() - chrono::Duration::minutes(older_than_minutes as i64);
+        let cutoff = cutoff.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
+        let update_data = serde_json::json!({ "status": null, CLAIMED_AT_COLUMN: null });
+
+        let mut reset = 0;
+        for table_name in [&self.tables.iptus_list_priority, &self.tables.iptus_list] {
+            let url = self.table_url(table_name);
+            let response = self
+                .send_with_retry(
+                    self.client
+                        .patch(&url)
+                        .header("apikey", auth_key)
+                        .header("Authorization", format!("Bearer {}", auth_key))
+                        .header("Content-Type", "application/json")
+                        .header("Prefer", "return=representation")
+                        .query(&[
+                            ("status", "eq.p".to_string()),
+                            (CLAIMED_AT_COLUMN, format!("lt.{}", cutoff)),
+                            ("select", "contributor_number".to_string()),
+                        ])
+                        .json(&update_data),
+                )
                 .await?;
+
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                anyhow::bail!(
+                    "Failed to reset stale claims in {}: {}",
+                    table_name,
+                    error_text
+                );
+            }
+
+            let count = response.json::<Vec<serde_json::Value>>().await?.len();
+            if count > 0 {
+                tracing::info!(
+                    "Reset {} stale claim(s) in {} (claimed before {})",
+                    count,
+                    table_name,
+                    cutoff
+                );
+            }
+            reset += count;
         }
 
-        Ok(())
+        Ok(reset)
     }
 
     pub async fn get_results(&self, limit: i32, offset: i32) -> Result<Vec<IPTUResult>> {
-        let url = format!("{}/rest/v1/iptus", self.base_url);
+        self.fetch_results(limit, offset, None).await
+    }
+
+    /// Results uploaded by one batch, newest first
+    pub async fn get_results_by_batch(
+        &self,
+        batch_id: &str,
+        limit: i32,
+        offset: i32,
+    ) -> Result<Vec<IPTUResult>> {
+        self.fetch_results(limit, offset, Some(batch_id)).await
+    }
+
+    async fn fetch_results(
+        &self,
+        limit: i32,
+        offset: i32,
+        batch_id: Option<&str>,
+    ) -> Result<Vec<IPTUResult>> {
+        let url = self.table_url(&self.tables.iptus);
 
         let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
 
+        let mut query = vec![
+            ("select", "*".to_string()),
+            ("order", "timestamp.desc".to_string()),
+            ("limit", limit.to_string()),
+            ("offset", offset.to_string()),
+        ];
+        if let Some(batch_id) = batch_id {
+            query.push(("batch_id", format!("eq.{}", batch_id)));
+        }
+
         let response = self
-            .client
-            .get(&url)
-            .header("apikey", auth_key)
-            .header("Authorization", format!("Bearer {}", auth_key))
-            .query(&[
-                ("select", "*"),
-                ("order", "timestamp.desc"),
-                ("limit", &limit.to_string()),
-                ("offset", &offset.to_string()),
-            ])
-            .send()
+            .send_with_retry(
+                self.client
+                    .get(&url)
+                    .header("apikey", auth_key)
+                    .header("Authorization", format!("Bearer {}", auth_key))
+                    .query(&query),
+            )
             .await?;
 
         if !response.status().is_success() {
@@ -442,8 +1023,140 @@ impl SupabaseClient {
         Ok(results)
     }
 
+    /// Successful results with an `id` after `after_id`, in `id` order, so a
+    /// caller can page through the table and resume from the last id it saw
+    pub async fn fetch_successful_results_after(
+        &self,
+        after_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<IPTUResult>> {
+        let url = self.table_url(&self.tables.iptus);
+        let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
+
+        let mut query = vec![
+            ("select", "*".to_string()),
+            ("sucesso", "eq.true".to_string()),
+            ("order", "id.asc".to_string()),
+            ("limit", limit.to_string()),
+        ];
+        if let Some(after_id) = after_id {
+            query.push(("id", format!("gt.{}", after_id)));
+        }
+
+        let response = self
+            .send_with_retry(
+                self.client
+                    .get(&url)
+                    .header("apikey", auth_key)
+                    .header("Authorization", format!("Bearer {}", auth_key))
+                    .query(&query),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Failed to fetch results: {}", error_text);
+        }
+
+        Ok(response.json::<Vec<IPTUResult>>().await?)
+    }
+
+    /// Upsert enrichment rows into `table`, replacing any existing row for the
+    /// same `contributor_number`
+    pub async fn upsert_enrichments(
+        &self,
+        table: &str,
+        rows: &[serde_json::Value],
+    ) -> Result<usize> {
+        self.upsert_rows(table, rows, &["contributor_number"])
+            .await
+            .context("Failed to upsert enrichments")
+    }
+
+    /// Upsert Dbase addresses into `table`, keyed on CPF/CNPJ, CEP, number and
+    /// complement. Returns the number of distinct rows sent.
+    pub async fn upload_dbase_records(
+        &self,
+        table: &str,
+        records: &[AddressRecord],
+    ) -> Result<usize> {
+        let scraped_at = chrono::Utc::now().to_rfc3339();
+        let rows = records
+            .iter()
+            .map(|record| dbase_row(record, &scraped_at))
+            .collect();
+        let rows = dedupe_rows(rows, &DBASE_CONFLICT_COLUMNS);
+        self.upsert_rows(table, &rows, &DBASE_CONFLICT_COLUMNS)
+            .await
+            .context("Failed to upload Dbase records")
+    }
+
+    /// Upsert Diretrix properties and their enrichment (aligned by index, or empty) into
+    /// `table`, keyed on the IPTU number. Returns the number of distinct rows sent.
+    pub async fn upload_diretrix_records(
+        &self,
+        table: &str,
+        records: &[PropertyRecord],
+        enrichment: &[Option<GetCustomerData>],
+    ) -> Result<usize> {
+        let scraped_at = chrono::Utc::now().to_rfc3339();
+        let rows = records
+            .iter()
+            .enumerate()
+            .map(|(idx, record)| {
+                diretrix_row(
+                    record,
+                    enrichment.get(idx).and_then(Option::as_ref),
+                    &scraped_at,
+                )
+            })
+            .collect();
+        let rows = dedupe_rows(rows, &DIRETRIX_CONFLICT_COLUMNS);
+        self.upsert_rows(table, &rows, &DIRETRIX_CONFLICT_COLUMNS)
+            .await
+            .context("Failed to upload Diretrix records")
+    }
+
+    /// Upsert `rows` into `table` in chunks of `UPSERT_CHUNK`, merging with
+    /// existing rows that share the `on_conflict` columns
+    async fn upsert_rows(
+        &self,
+        table: &str,
+        rows: &[serde_json::Value],
+        on_conflict: &[&str],
+    ) -> Result<usize> {
+        let url = self.table_url(table);
+        let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
+        let on_conflict = on_conflict.join(",");
+
+        for chunk in rows.chunks(UPSERT_CHUNK) {
+            let response = self
+                .send_with_retry(
+                    self.client
+                        .post(&url)
+                        .header("apikey", auth_key)
+                        .header("Authorization", format!("Bearer {}", auth_key))
+                        .header("Content-Type", "application/json")
+                        .header("Prefer", "resolution=merge-duplicates")
+                        .query(&[("on_conflict", on_conflict.as_str())])
+                        .json(chunk),
+                )
+                .await?;
+
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                anyhow::bail!("Upsert into {} failed: {}", table, error_text);
+            }
+        }
+
+        Ok(rows.len())
+    }
+
+    /// Mark a batch as completed. Idempotent: an already completed batch is left
+    /// untouched (keeping its original `completed_at`). Network errors and 5xx
+    /// responses are retried with exponential backoff.
     pub async fn complete_batch(&self, batch_id: &str) -> Result<()> {
-        let url = format!("{}/rest/v1/batches", self.base_url);
+        let url = self.table_url(&self.tables.batches);
         let auth_key = self.service_role_key.as_ref().unwrap_or(&self.api_key);
 
         let update = serde_json::json!({
@@ -452,15 +1165,20 @@ impl SupabaseClient {
         });
 
         let response = self
-            .client
-            .patch(&url)
-            .header("apikey", auth_key)
-            .header("Authorization", format!("Bearer {}", auth_key))
-            .header("Content-Type", "application/json")
-            .query(&[("id", format!("eq.{}", batch_id))])
-            .json(&update)
-            .send()
-            .await?;
+            .send_with_retry(
+                self.client
+                    .patch(&url)
+                    .header("apikey", auth_key)
+                    .header("Authorization", format!("Bearer {}", auth_key))
+                    .header("Content-Type", "application/json")
+                    .query(&[
+                        ("id", format!("eq.{}", batch_id)),
+                        ("status", "neq.completed".to_string()),
+                    ])
+                    .json(&update),
+            )
+            .await
+            .with_context(|| format!("Failed to complete batch {}", batch_id))?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
@@ -470,3 +1188,437 @@ impl SupabaseClient {
         Ok(())
     }
 }
+
+/// Drop the extended IPTU columns from serialized rows so uploads keep working
+/// against `iptus` tables that predate them
+fn strip_extended_iptu_columns(rows: &mut serde_json::Value) {
+    if let Some(rows) = rows.as_array_mut() {
+        for row in rows.iter_mut().filter_map(|row| row.as_object_mut()) {
+            for column in EXTENDED_IPTU_COLUMNS {
+                row.remove(column);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_html_round_trip() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let html = "<html><body><input name='txtProprietarioNome' value='JOSÉ'/></body></html>";
+        let encoded = compress_html(html).unwrap();
+
+        let compressed = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .unwrap();
+        let mut decoded = String::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, html);
+    }
+
+    #[test]
+    fn test_reconcile_report_classifies_drift() {
+        let list: HashMap<String, Option<String>> = [
+            ("001", Some("s")),
+            ("002", None),
+            ("003", Some("e")),
+            ("004", Some("s")),
+            ("005", Some("p")),
+            ("006", Some("e")),
+            ("007", None),
+        ]
+        .into_iter()
+        .map(|(n, s)| (n.to_string(), s.map(|s| s.to_string())))
+        .collect();
+        let results: HashSet<String> = ["001", "002", "003", "005", "999"]
+            .into_iter()
+            .map(|n| n.to_string())
+            .collect();
+
+        let report = ReconcileReport::build(&list, &results);
+
+        assert_eq!(report.list_rows, 7);
+        assert_eq!(report.result_rows, 5);
+        assert_eq!(report.results_not_marked_success, vec!["002", "003"]);
+        assert_eq!(report.success_without_result, vec!["004"]);
+        assert_eq!(report.error_without_result, vec!["006"]);
+        assert!(!report.is_consistent());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_rows_keeps_paging_past_short_pages() {
+        // The server caps pages at 2 rows although 3 were asked for
+        let base_url = serve_responses(vec![
+            (
+                200,
+                r#"[{"contributor_number":"001"},{"contributor_number":"002"}]"#,
+            ),
+            (
+                200,
+                r#"[{"contributor_number":"003"},{"contributor_number":"004"}]"#,
+            ),
+            (200, r#"[{"contributor_number":"005"}]"#),
+            (200, "[]"),
+        ])
+        .await;
+        let client = SupabaseClient::new(base_url, "key".to_string());
+
+        let numbers = client.fetch_result_numbers(3).await.unwrap();
+        assert_eq!(numbers.len(), 5);
+        assert!(numbers.contains("005"));
+    }
+
+    #[test]
+    fn test_strip_extended_iptu_columns() {
+        let mut rows = serde_json::json!([
+            { "contributor_number": "00804501234", "valor_venal": "R$ 1,00", "uso": "RESIDENCIAL" }
+        ]);
+        strip_extended_iptu_columns(&mut rows);
+
+        assert_eq!(rows[0]["contributor_number"], "00804501234");
+        assert!(rows[0].get("valor_venal").is_none());
+        assert!(rows[0].get("uso").is_none());
+    }
+
+    /// Serve one canned HTTP status and body per connection, in order
+    async fn serve_responses(responses: Vec<(u16, &'static str)>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_retries_server_errors_only() {
+        let base_url = serve_responses(vec![(503, ""), (502, ""), (200, "[]"), (400, "")]).await;
+        let mut client = SupabaseClient::new(base_url.clone(), "key".to_string());
+        client.retry_base_delay = Duration::from_millis(1);
+
+        let response = client
+            .send_with_retry(client.client.get(&base_url))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        // A 4xx comes back on the first attempt for the caller to report
+        let response = client
+            .send_with_retry(client.client.get(&base_url))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_create_batch_retries_and_accepts_its_own_insert() {
+        let base_url = serve_responses(vec![(503, ""), (409, "duplicate key"), (400, "bad")]).await;
+        let mut client = SupabaseClient::new(base_url, "key".to_string());
+        client.retry_base_delay = Duration::from_millis(1);
+
+        let batch_id = client.create_batch(10).await.unwrap();
+        assert!(uuid::Uuid::parse_str(&batch_id).is_ok());
+
+        let err = client.create_batch(10).await.unwrap_err();
+        assert!(err.to_string().contains("Failed to create batch"));
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_gives_up_after_max_retries() {
+        let base_url = serve_responses(vec![(500, ""), (500, ""), (200, "[]")]).await;
+        let mut client =
+            SupabaseClient::new(base_url.clone(), "key".to_string()).with_max_retries(1);
+        client.retry_base_delay = Duration::from_millis(1);
+
+        let response = client
+            .send_with_retry(client.client.get(&base_url))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 500);
+    }
+
+    #[tokio::test]
+    async fn test_mark_returns_matched_rows() {
+        let base_url = serve_responses(vec![
+            (
+                200,
+                r#"[{"contributor_number":"001"},{"contributor_number":"002"}]"#,
+            ),
+            (200, "[]"),
+        ])
+        .await;
+        let client = SupabaseClient::new(base_url, "key".to_string());
+
+        let numbers = vec!["001".to_string(), "002".to_string(), "003".to_string()];
+        assert_eq!(
+            client
+                .mark_iptu_list_as_success(numbers, false)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            client
+                .mark_iptu_list_as_error(vec!["999".to_string()], true)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pending_from_pages_up_to_limit() {
+        let base_url = serve_responses(vec![
+            (
+                200,
+                r#"[{"contributor_number":"005","status":null},{"contributor_number":"004","status":null}]"#,
+            ),
+            (
+                200,
+                r#"[{"contributor_number":"003","status":null},{"contributor_number":"002","status":null}]"#,
+            ),
+            (200, r#"[{"contributor_number":"001","status":null}]"#),
+        ])
+        .await;
+        let client = SupabaseClient::new(base_url, "key".to_string());
+
+        let jobs = client.fetch_pending_from("iptus_list", 5, 2).await.unwrap();
+        let numbers: Vec<&str> = jobs
+            .iter()
+            .map(|job| job.contributor_number.as_str())
+            .collect();
+        assert_eq!(numbers, vec!["005", "004", "003", "002", "001"]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pending_from_continues_after_capped_page() {
+        // Pages of 3 requested, but the server caps them at 2
+        let base_url = serve_responses(vec![
+            (
+                200,
+                r#"[{"contributor_number":"004","status":null},{"contributor_number":"003","status":null}]"#,
+            ),
+            (
+                200,
+                r#"[{"contributor_number":"002","status":null},{"contributor_number":"001","status":null}]"#,
+            ),
+        ])
+        .await;
+        let client = SupabaseClient::new(base_url, "key".to_string());
+
+        assert_eq!(
+            client
+                .fetch_pending_from("iptus_list", 4, 3)
+                .await
+                .unwrap()
+                .len(),
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pending_from_stops_on_empty_page() {
+        let base_url = serve_responses(vec![
+            (
+                200,
+                r#"[{"contributor_number":"002","status":null},{"contributor_number":"001","status":null}]"#,
+            ),
+            (200, "[]"),
+        ])
+        .await;
+        let client = SupabaseClient::new(base_url, "key".to_string());
+
+        assert_eq!(
+            client
+                .fetch_pending_from("iptus_list", 10, 2)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_table_names_override_urls() {
+        let client = SupabaseClient::new("http://db".to_string(), "key".to_string())
+            .with_table_names(TableNames {
+                iptus_list: "staging_iptus_list".to_string(),
+                ..TableNames::default()
+            });
+
+        assert_eq!(client.job_table(false), "staging_iptus_list");
+        assert_eq!(client.job_table(true), "iptus_list_priority");
+        assert_eq!(
+            client.table_url(&client.tables.batches),
+            "http://db/rest/v1/batches"
+        );
+    }
+
+    #[test]
+    fn test_status_update_sets_matching_timestamp() {
+        let now = "2026-01-01T00:00:00Z";
+        assert_eq!(
+            status_update(Some("p"), now),
+            serde_json::json!({ "status": "p", "claimed_at": now })
+        );
+        assert_eq!(
+            status_update(Some("n"), now),
+            serde_json::json!({ "status": "n", "processed_at": now })
+        );
+        assert_eq!(
+            status_update(None, now),
+            serde_json::json!({ "status": null, "claimed_at": null })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reset_stale_claims_counts_both_tables() {
+        let base_url = serve_responses(vec![
+            (200, r#"[{"contributor_number":"001"}]"#),
+            (
+                200,
+                r#"[{"contributor_number":"002"},{"contributor_number":"003"}]"#,
+            ),
+        ])
+        .await;
+        let client = SupabaseClient::new(base_url, "key".to_string());
+        assert_eq!(client.reset_stale_claims(60).await.unwrap(), 3);
+
+        let base_url = serve_responses(vec![(400, "column claimed_at does not exist")]).await;
+        let client = SupabaseClient::new(base_url, "key".to_string());
+        let err = client.reset_stale_claims(60).await.unwrap_err();
+        assert!(err.to_string().contains("claimed_at"));
+    }
+
+    #[test]
+    fn test_upload_rows_are_deduped_on_conflict_key() {
+        let address = |numero: &str, nome: &str| AddressRecord {
+            cpf_cnpj: "607.661.908-20".to_string(),
+            nome_razao_social: nome.to_string(),
+            logradouro: "AV HORACIO LAFER".to_string(),
+            numero: numero.to_string(),
+            complemento: String::new(),
+            bairro: "ITAIM BIBI".to_string(),
+            cep: "04538080".to_string(),
+        };
+        let rows = [
+            address("120", "MARIO"),
+            address("120", "MARIO L"),
+            address("130", "MARIO"),
+        ]
+        .iter()
+        .map(|record| dbase_row(record, "2026-01-01T00:00:00Z"))
+        .collect();
+        let rows = dedupe_rows(rows, &DBASE_CONFLICT_COLUMNS);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["nome_razao_social"], "MARIO");
+        assert_eq!(rows[1]["numero"], "130");
+
+        let property = PropertyRecord {
+            owner: "MARIA".to_string(),
+            iptu: "001.123.4567-8".to_string(),
+            street: "RUA A".to_string(),
+            number: "10".to_string(),
+            complement: String::new(),
+            complement2: String::new(),
+            neighborhood: "CENTRO".to_string(),
+            document1: Some("12345678901".to_string()),
+            document2: None,
+        };
+        let row = diretrix_row(&property, None, "2026-01-01T00:00:00Z");
+        assert_eq!(row["iptu"], "001.123.4567-8");
+        assert!(row["enrichment"].is_null());
+        assert!(row["document2"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_upload_dbase_records_chunks_and_reports_errors() {
+        let records: Vec<AddressRecord> = (0..UPSERT_CHUNK + 1)
+            .map(|n| AddressRecord {
+                cpf_cnpj: format!("{:011}", n),
+                nome_razao_social: "X".to_string(),
+                logradouro: "RUA".to_string(),
+                numero: "1".to_string(),
+                complemento: String::new(),
+                bairro: "B".to_string(),
+                cep: "01000000".to_string(),
+            })
+            .collect();
+
+        let base_url = serve_responses(vec![(201, ""), (201, "")]).await;
+        let client = SupabaseClient::new(base_url, "key".to_string());
+        assert_eq!(
+            client
+                .upload_dbase_records(DEFAULT_DBASE_TABLE, &records)
+                .await
+                .unwrap(),
+            UPSERT_CHUNK + 1
+        );
+
+        let base_url = serve_responses(vec![(409, "conflict")]).await;
+        let client = SupabaseClient::new(base_url, "key".to_string());
+        let err = client
+            .upload_dbase_records(DEFAULT_DBASE_TABLE, &records[..1])
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("conflict"));
+    }
+
+    #[test]
+    fn test_result_row_matches_columns() {
+        let result = IPTUResult {
+            id: Some("42".to_string()),
+            contributor_number: "00804501234".to_string(),
+            numero_cadastro: None,
+            nome_proprietario: Some("JOSÉ DA SILVA".to_string()),
+            nome_compromissario: None,
+            endereco: Some("RUA A".to_string()),
+            numero: Some("10".to_string()),
+            complemento: None,
+            bairro: Some("CENTRO".to_string()),
+            cep: Some("01001000".to_string()),
+            valor_venal: None,
+            area_terreno: None,
+            area_construida: None,
+            uso: Some("RESIDENCIAL".to_string()),
+            sucesso: true,
+            erro: None,
+            batch_id: Some("batch-1".to_string()),
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            processed_by: Some("cli".to_string()),
+        };
+
+        let row = result_row(&result);
+        assert_eq!(row.len(), RESULT_COLUMNS.len());
+        let field = |column: &str| {
+            let index = RESULT_COLUMNS.iter().position(|c| *c == column).unwrap();
+            row[index].clone()
+        };
+        assert_eq!(field("contributor_number"), "00804501234");
+        assert_eq!(field("nome_proprietario"), "JOSÉ DA SILVA");
+        assert_eq!(field("uso"), "RESIDENCIAL");
+        assert_eq!(field("sucesso"), "true");
+        assert_eq!(field("erro"), "");
+        assert_eq!(field("batch_id"), "batch-1");
+    }
+}
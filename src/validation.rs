@@ -0,0 +1,88 @@
+/// Check a CPF's two mod-11 check digits; punctuation is ignored, and
+/// repeated-digit numbers such as `111.111.111-11` are rejected
+pub fn is_valid_cpf(value: &str) -> bool {
+    let Some(digits) = document_digits(value, 11) else {
+        return false;
+    };
+    let weights: Vec<u32> = (2..=11).rev().collect();
+
+    check_digit(&digits[..9], &weights[1..]) == digits[9]
+        && check_digit(&digits[..10], &weights) == digits[10]
+}
+
+/// Check a CNPJ's two mod-11 check digits; punctuation is ignored, and
+/// repeated-digit numbers are rejected
+pub fn is_valid_cnpj(value: &str) -> bool {
+    let Some(digits) = document_digits(value, 14) else {
+        return false;
+    };
+    let weights = [6, 5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2];
+
+    check_digit(&digits[..12], &weights[1..]) == digits[12]
+        && check_digit(&digits[..13], &weights) == digits[13]
+}
+
+/// The digits of `value` when there are exactly `len` of them and they are
+/// not all the same
+fn document_digits(value: &str, len: usize) -> Option<Vec<u32>> {
+    let digits: Vec<u32> = value.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() != len || digits.iter().all(|d| *d == digits[0]) {
+        return None;
+    }
+    Some(digits)
+}
+
+/// Weighted sum mod 11, where a remainder below 2 gives check digit 0
+fn check_digit(digits: &[u32], weights: &[u32]) -> u32 {
+    let sum: u32 = digits.iter().zip(weights).map(|(d, w)| d * w).sum();
+    match sum % 11 {
+        0 | 1 => 0,
+        remainder => 11 - remainder,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_cpf() {
+        let cases = [
+            ("529.982.247-25", true),
+            ("52998224725", true),
+            ("111.444.777-35", true),
+            ("123.456.789-09", true),
+            ("01234567890", true),
+            ("529.982.247-24", false),
+            ("123.456.789-01", false),
+            ("111.111.111-11", false),
+            ("00000000000", false),
+            ("5299822472", false),
+            ("529982247250", false),
+            ("", false),
+        ];
+        for (cpf, expected) in cases {
+            assert_eq!(is_valid_cpf(cpf), expected, "{}", cpf);
+        }
+    }
+
+    #[test]
+    fn test_is_valid_cnpj() {
+        let cases = [
+            ("11.222.333/0001-81", true),
+            ("11222333000181", true),
+            ("12.345.678/0001-95", true),
+            ("02345678000131", true),
+            ("11.222.333/0001-80", false),
+            ("02345678000195", false),
+            ("11.111.111/1111-11", false),
+            ("00000000000000", false),
+            ("1122233300018", false),
+            ("52998224725", false),
+            ("", false),
+        ];
+        for (cnpj, expected) in cases {
+            assert_eq!(is_valid_cnpj(cnpj), expected, "{}", cnpj);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::scraper::ScraperResult;
+use futures::StreamExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Webhook POSTs in flight at once
+pub const WEBHOOK_CONCURRENCY: usize = 4;
+
+/// Delivery counts reported when the webhook is finished
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WebhookStats {
+    pub delivered: usize,
+    pub failed: usize,
+}
+
+/// Pushes every completed `ScraperResult` to `--result-webhook` as JSON.
+/// Deliveries run in the background with bounded concurrency; a failed POST only
+/// logs a warning and never fails the scrape.
+pub struct ResultWebhook {
+    sender: mpsc::UnboundedSender<ScraperResult>,
+    worker: tokio::task::JoinHandle<WebhookStats>,
+}
+
+/// Cheap handle for queueing results from a scraper callback
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    sender: mpsc::UnboundedSender<ScraperResult>,
+}
+
+impl WebhookNotifier {
+    pub fn notify(&self, result: &ScraperResult) {
+        // The worker only stops once every sender is gone, so this cannot fail
+        let _ = self.sender.send(result.clone());
+    }
+}
+
+impl ResultWebhook {
+    pub fn spawn(url: String, concurrency: usize) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel::<ScraperResult>();
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+
+        let worker = tokio::spawn(async move {
+            let delivered = AtomicUsize::new(0);
+            let failed = AtomicUsize::new(0);
+            let (client, url, delivered_ref, failed_ref) = (&client, &url, &delivered, &failed);
+
+            futures::stream::unfold(receiver, |mut receiver| async move {
+                receiver.recv().await.map(|result| (result, receiver))
+            })
+            .for_each_concurrent(concurrency.max(1), |result| async move {
+                match client.post(url).json(&result).send().await {
+                    Ok(response) if response.status().is_success() => {
+                        delivered_ref.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Ok(response) => {
+                        failed_ref.fetch_add(1, Ordering::Relaxed);
+                        warn!(
+                            "Result webhook rejected {} (status {})",
+                            result.contributor_number,
+                            response.status()
+                        );
+                    }
+                    Err(e) => {
+                        failed_ref.fetch_add(1, Ordering::Relaxed);
+                        warn!(
+                            "Result webhook failed for {}: {}",
+                            result.contributor_number, e
+                        );
+                    }
+                }
+            })
+            .await;
+
+            WebhookStats {
+                delivered: delivered.into_inner(),
+                failed: failed.into_inner(),
+            }
+        });
+
+        Self { sender, worker }
+    }
+
+    pub fn notifier(&self) -> WebhookNotifier {
+        WebhookNotifier {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Wait for queued deliveries to finish. Notifiers handed to callbacks must
+    /// have been dropped, which happens once the batch they were used in returns.
+    pub async fn finish(self) -> WebhookStats {
+        drop(self.sender);
+        let stats = self.worker.await.unwrap_or_default();
+        info!(
+            "Result webhook: {} delivered, {} failed",
+            stats.delivered, stats.failed
+        );
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn result(number: &str) -> ScraperResult {
+        ScraperResult {
+            contributor_number: number.to_string(),
+            numero_cadastro: None,
+            nome_proprietario: Some("MARIA".to_string()),
+            nome_compromissario: None,
+            endereco: None,
+            numero: None,
+            complemento: None,
+            bairro: None,
+            cep: None,
+            valor_venal: None,
+            area_terreno: None,
+            area_construida: None,
+            uso: None,
+            success: true,
+            not_found: false,
+            consent_failed: false,
+            error: None,
+            raw_html: Some("<html></html>".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook_posts_results_and_isolates_failures() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).contains("}") {
+                let read = socket.read(&mut buffer).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+
+        let webhook = ResultWebhook::spawn(format!("http://{}/hook", addr), 2);
+        webhook.notifier().notify(&result("100.200.300-4"));
+        let stats = webhook.finish().await;
+        assert_eq!(stats.delivered, 1);
+
+        let request = server.await.unwrap();
+        assert!(request.contains("\"contributor_number\":\"100.200.300-4\""));
+        assert!(!request.contains("raw_html"));
+
+        // Nothing listens on the freed port any more: the failure is only counted
+        let webhook = ResultWebhook::spawn(format!("http://{}/hook", addr), 2);
+        webhook.notifier().notify(&result("100.200.300-4"));
+        assert_eq!(
+            webhook.finish().await,
+            WebhookStats {
+                delivered: 0,
+                failed: 1
+            }
+        );
+    }
+}
@@ -1,7 +1,11 @@
 // Integration tests for the scraper module
 // Similar to _test.go in Go, but in Rust we use a separate tests/ directory
 
-use iptu_cli::scraper::{ScraperConfig, ScraperResult};
+use iptu_cli::browser::Browser;
+use iptu_cli::scraper::{
+    FailureTracker, ScraperConfig, ScraperResult, ScraperSelectors, DEFAULT_COOLDOWN_SECS,
+    DEFAULT_FAILURE_THRESHOLD, DEFAULT_FAILURE_WINDOW_SECS, DEFAULT_RESULT_WAIT_SECS,
+};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -24,10 +28,25 @@ async fn test_scraper_config_creation() {
         timeout_secs: 60,
         retry_attempts: 2,
         rate_limit_per_hour: 50,
+        capture_console: false,
+        selectors: ScraperSelectors::default(),
+        stagger_base_ms: 2000,
+        stagger_step_ms: 2000,
+        store_raw_html: false,
+        reuse_cookies: false,
+        proxies: Vec::new(),
+        webdriver_url: "http://localhost:9515".to_string(),
+        browser: Browser::Chrome,
+        failure_window_secs: DEFAULT_FAILURE_WINDOW_SECS,
+        failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+        cooldown_secs: DEFAULT_COOLDOWN_SECS,
+        failure_state_file: None,
+        debug_dir: None,
+        result_wait_secs: DEFAULT_RESULT_WAIT_SECS,
     };
 
     assert_eq!(config.max_concurrent, 3);
-    assert_eq!(config.headless, true);
+    assert!(config.headless);
     assert_eq!(config.timeout_secs(), 60);
     assert_eq!(config.retry_attempts(), 2);
     assert_eq!(config.rate_limit_per_hour, 50);
@@ -45,8 +64,15 @@ async fn test_scraper_result_fields() {
         complemento: Some("Apt 101".to_string()),
         bairro: Some("Test District".to_string()),
         cep: Some("12345-678".to_string()),
+        valor_venal: None,
+        area_terreno: None,
+        area_construida: None,
+        uso: None,
         success: true,
+        not_found: false,
+        consent_failed: false,
         error: None,
+        raw_html: None,
     };
 
     assert_eq!(result.contributor_number, "100.200.300-4");
@@ -68,8 +94,15 @@ async fn test_scraper_error_handling() {
         complemento: None,
         bairro: None,
         cep: None,
+        valor_venal: None,
+        area_terreno: None,
+        area_construida: None,
+        uso: None,
         success: false,
+        not_found: false,
+        consent_failed: false,
         error: Some("Network timeout".to_string()),
+        raw_html: None,
     };
 
     assert!(!result.success);
@@ -88,6 +121,21 @@ async fn test_empty_batch_processing() {
         timeout_secs: 30,
         retry_attempts: 1,
         rate_limit_per_hour: 100,
+        capture_console: false,
+        selectors: ScraperSelectors::default(),
+        stagger_base_ms: 2000,
+        stagger_step_ms: 2000,
+        store_raw_html: false,
+        reuse_cookies: false,
+        proxies: Vec::new(),
+        webdriver_url: "http://localhost:9515".to_string(),
+        browser: Browser::Chrome,
+        failure_window_secs: DEFAULT_FAILURE_WINDOW_SECS,
+        failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+        cooldown_secs: DEFAULT_COOLDOWN_SECS,
+        failure_state_file: None,
+        debug_dir: None,
+        result_wait_secs: DEFAULT_RESULT_WAIT_SECS,
     };
 
     // Verify config values are set correctly
@@ -102,14 +150,14 @@ async fn test_empty_batch_processing() {
 
     // In a real test with WebDriver:
     // let engine = ScraperEngine::new(_config).await.unwrap();
-    // let results = engine.process_batch_with_callback(jobs, create_test_callback()).await;
+    // let results = engine.process_batch_with_callback(jobs, create_test_callback(), None).await;
     // assert_eq!(results.len(), 0);
 }
 
 // Test multiple job processing
 #[test]
 fn test_job_list_preparation() {
-    let jobs = vec![
+    let jobs = [
         "123.456.789-0".to_string(),
         "987.654.321-0".to_string(),
         "111.222.333-4".to_string(),
@@ -140,8 +188,15 @@ async fn test_concurrent_operations() {
             complemento: None,
             bairro: None,
             cep: None,
+            valor_venal: None,
+            area_terreno: None,
+            area_construida: None,
+            uso: None,
             success: true,
+            not_found: false,
+            consent_failed: false,
             error: None,
+            raw_html: None,
         });
     });
 
@@ -158,8 +213,15 @@ async fn test_concurrent_operations() {
             complemento: None,
             bairro: None,
             cep: None,
+            valor_venal: None,
+            area_terreno: None,
+            area_construida: None,
+            uso: None,
             success: false,
+            not_found: false,
+            consent_failed: false,
             error: Some("Test error".to_string()),
+            raw_html: None,
         });
     });
 
@@ -244,6 +306,26 @@ fn test_failure_scenarios() {
     }
 }
 
+// Same scenarios against the real tracker
+#[test]
+fn test_failure_tracker_cooldown_rules() {
+    let now = 1_700_000_000;
+
+    let mut tracker = FailureTracker::default();
+    tracker.record_failure_at(false, now);
+    tracker.record_failure_at(false, now + 1);
+    assert!(!tracker.should_cooldown_at(now + 1), "two failures");
+
+    tracker.record_failure_at(false, now + 2);
+    assert!(tracker.should_cooldown_at(now + 2), "three recent failures");
+    assert_eq!(tracker.stats_at(now + 2).recent_failures, 3);
+
+    tracker.record_success();
+    tracker.record_failure_at(false, now + 3);
+    assert!(!tracker.should_cooldown_at(now + 3), "success resets");
+    assert_eq!(tracker.stats_at(now + 3).total_failures, 1);
+}
+
 // Benchmark-like test for processing time estimation
 #[tokio::test]
 async fn test_processing_time_estimation() {